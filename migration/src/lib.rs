@@ -19,6 +19,20 @@ mod m20250924_000001_create_user_profile;
 mod m20250925_000001_create_auth_identity;
 mod m20250925_010000_backfill_user_profile;
 mod m20251004_000001_drop_staff_id_from_appointment;
+mod m20261018_000001_add_childbirth_incident_type;
+mod m20261018_000002_create_dispatch_decision_table;
+mod m20261018_000003_create_dispatch_override_table;
+mod m20261018_000004_create_emergency_ambulance_table;
+mod m20261018_000005_add_geohash_columns;
+mod m20261018_000006_add_eta_columns;
+mod m20261018_000007_add_emergency_duplicates;
+mod m20261018_000008_add_emergency_milestones;
+mod m20261018_000009_create_hospital_diversion_table;
+mod m20261018_000010_add_emergency_verification;
+mod m20261018_000011_create_ambulance_location_table;
+mod m20261018_000012_create_geofence_event_table;
+mod m20261018_000013_add_ambulance_service_mileage;
+mod m20261018_000014_create_ambulance_crew_table;
 pub struct Migrator;
 
 #[async_trait::async_trait]
@@ -44,6 +58,20 @@ impl MigratorTrait for Migrator {
             Box::new(m20250925_000001_create_auth_identity::Migration),
             Box::new(m20250925_010000_backfill_user_profile::Migration),
             Box::new(m20251004_000001_drop_staff_id_from_appointment::Migration),
+            Box::new(m20261018_000001_add_childbirth_incident_type::Migration),
+            Box::new(m20261018_000002_create_dispatch_decision_table::Migration),
+            Box::new(m20261018_000003_create_dispatch_override_table::Migration),
+            Box::new(m20261018_000004_create_emergency_ambulance_table::Migration),
+            Box::new(m20261018_000005_add_geohash_columns::Migration),
+            Box::new(m20261018_000006_add_eta_columns::Migration),
+            Box::new(m20261018_000007_add_emergency_duplicates::Migration),
+            Box::new(m20261018_000008_add_emergency_milestones::Migration),
+            Box::new(m20261018_000009_create_hospital_diversion_table::Migration),
+            Box::new(m20261018_000010_add_emergency_verification::Migration),
+            Box::new(m20261018_000011_create_ambulance_location_table::Migration),
+            Box::new(m20261018_000012_create_geofence_event_table::Migration),
+            Box::new(m20261018_000013_add_ambulance_service_mileage::Migration),
+            Box::new(m20261018_000014_create_ambulance_crew_table::Migration),
         ]
     }
 }
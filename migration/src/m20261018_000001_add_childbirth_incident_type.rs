@@ -0,0 +1,24 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        // Births need a neonatal unit, so they get their own incident type
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "ALTER TYPE emergency_incidenttype_enum ADD VALUE IF NOT EXISTS 'CHILDBIRTH';",
+        ))
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // Postgres cannot drop a value from an enum type; the extra value is harmless
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        // One row per automatic dispatch, with the ranked candidates the allocator considered
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"
+            CREATE TABLE IF NOT EXISTS dispatch_decision (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                emergency_id UUID NOT NULL REFERENCES emergency(id) ON DELETE CASCADE,
+                ambulance_id UUID NOT NULL REFERENCES ambulance(id) ON DELETE CASCADE,
+                strategy VARCHAR(32) NOT NULL,
+                capability VARCHAR(32) NOT NULL,
+                distance_km DOUBLE PRECISION NOT NULL,
+                score DOUBLE PRECISION NOT NULL,
+                candidates JSONB NOT NULL DEFAULT '[]'::jsonb,
+                created_at TIMESTAMP NOT NULL DEFAULT now()
+            );
+            "#,
+        ))
+        .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "CREATE INDEX IF NOT EXISTS idx_dispatch_decision_emergency_id ON dispatch_decision (emergency_id, created_at);",
+        ))
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table("dispatch_decision")
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,46 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        // Manual assignments by human dispatchers, kept apart from dispatch_decision
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"
+            CREATE TABLE IF NOT EXISTS dispatch_override (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                emergency_id UUID NOT NULL REFERENCES emergency(id) ON DELETE CASCADE,
+                ambulance_id UUID NOT NULL REFERENCES ambulance(id) ON DELETE CASCADE,
+                previous_ambulance_id UUID REFERENCES ambulance(id) ON DELETE SET NULL,
+                reason TEXT NOT NULL,
+                forced BOOLEAN NOT NULL DEFAULT FALSE,
+                dispatcher VARCHAR(255) NOT NULL,
+                created_at TIMESTAMP NOT NULL DEFAULT now()
+            );
+            "#,
+        ))
+        .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "CREATE INDEX IF NOT EXISTS idx_dispatch_override_emergency_id ON dispatch_override (emergency_id, created_at);",
+        ))
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table("dispatch_override")
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
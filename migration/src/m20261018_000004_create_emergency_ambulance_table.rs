@@ -0,0 +1,69 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        // Every unit sent to an emergency; emergency.ambulance_id stays as the lead unit
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"
+            CREATE TABLE IF NOT EXISTS emergency_ambulance (
+                emergency_id UUID NOT NULL REFERENCES emergency(id) ON DELETE CASCADE,
+                ambulance_id UUID NOT NULL REFERENCES ambulance(id) ON DELETE CASCADE,
+                assigned_at TIMESTAMP NOT NULL DEFAULT now(),
+                released_at TIMESTAMP NULL,
+                PRIMARY KEY (emergency_id, ambulance_id)
+            );
+            "#,
+        ))
+        .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "CREATE INDEX IF NOT EXISTS idx_emergency_ambulance_active ON emergency_ambulance (ambulance_id) WHERE released_at IS NULL;",
+        ))
+        .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"
+            INSERT INTO emergency_ambulance (emergency_id, ambulance_id, assigned_at, released_at)
+            SELECT id,
+                   ambulance_id,
+                   updated_at,
+                   CASE WHEN status IN ('RESOLVED', 'CANCELLED', 'FAILED') THEN updated_at END
+            FROM emergency
+            WHERE ambulance_id IS NOT NULL
+            ON CONFLICT DO NOTHING;
+            "#,
+        ))
+        .await?;
+        // Which unit carries each patient
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "ALTER TABLE emergency_patient ADD COLUMN IF NOT EXISTS ambulance_id UUID NULL REFERENCES ambulance(id) ON DELETE SET NULL;",
+        ))
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "ALTER TABLE emergency_patient DROP COLUMN IF EXISTS ambulance_id;",
+        ))
+        .await?;
+        manager
+            .drop_table(
+                Table::drop()
+                    .table("emergency_ambulance")
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
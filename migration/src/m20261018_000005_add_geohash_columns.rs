@@ -0,0 +1,112 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Standard base32 geohash, used once to backfill existing rows; the
+/// application keeps the columns up to date afterwards.
+const GEOHASH_ENCODE_FN: &str = r#"
+CREATE OR REPLACE FUNCTION geohash_encode_backfill(lat DOUBLE PRECISION, lon DOUBLE PRECISION, len INT)
+RETURNS TEXT AS $$
+DECLARE
+    alphabet CONSTANT TEXT := '0123456789bcdefghjkmnpqrstuvwxyz';
+    lat_lo DOUBLE PRECISION := -90;
+    lat_hi DOUBLE PRECISION := 90;
+    lon_lo DOUBLE PRECISION := -180;
+    lon_hi DOUBLE PRECISION := 180;
+    mid DOUBLE PRECISION;
+    hash TEXT := '';
+    code INT := 0;
+    bit_count INT := 0;
+    even BOOLEAN := TRUE;
+BEGIN
+    WHILE length(hash) < len LOOP
+        IF even THEN
+            mid := (lon_lo + lon_hi) / 2;
+            IF lon >= mid THEN code := code * 2 + 1; lon_lo := mid; ELSE code := code * 2; lon_hi := mid; END IF;
+        ELSE
+            mid := (lat_lo + lat_hi) / 2;
+            IF lat >= mid THEN code := code * 2 + 1; lat_lo := mid; ELSE code := code * 2; lat_hi := mid; END IF;
+        END IF;
+        even := NOT even;
+        bit_count := bit_count + 1;
+        IF bit_count = 5 THEN
+            hash := hash || substr(alphabet, code + 1, 1);
+            code := 0;
+            bit_count := 0;
+        END IF;
+    END LOOP;
+    RETURN hash;
+END;
+$$ LANGUAGE plpgsql IMMUTABLE;
+"#;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        for table in ["ambulance", "emergency"] {
+            db.execute(Statement::from_string(
+                manager.get_database_backend(),
+                format!("ALTER TABLE {table} ADD COLUMN IF NOT EXISTS geohash VARCHAR(12) NULL;"),
+            ))
+            .await?;
+            // varchar_pattern_ops lets `geohash LIKE 'prefix%'` use the index
+            db.execute(Statement::from_string(
+                manager.get_database_backend(),
+                format!(
+                    "CREATE INDEX IF NOT EXISTS idx_{table}_geohash ON {table} (geohash varchar_pattern_ops);"
+                ),
+            ))
+            .await?;
+        }
+
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            GEOHASH_ENCODE_FN,
+        ))
+        .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"
+            UPDATE ambulance
+            SET geohash = geohash_encode_backfill(location_latitude, location_longitude, 9)
+            WHERE geohash IS NULL;
+            "#,
+        ))
+        .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"
+            UPDATE emergency
+            SET geohash = geohash_encode_backfill(emergency_latitude, emergency_longitude, 9)
+            WHERE geohash IS NULL;
+            "#,
+        ))
+        .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "DROP FUNCTION IF EXISTS geohash_encode_backfill(DOUBLE PRECISION, DOUBLE PRECISION, INT);",
+        ))
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        for table in ["ambulance", "emergency"] {
+            db.execute(Statement::from_string(
+                manager.get_database_backend(),
+                format!("DROP INDEX IF EXISTS idx_{table}_geohash;"),
+            ))
+            .await?;
+            db.execute(Statement::from_string(
+                manager.get_database_backend(),
+                format!("ALTER TABLE {table} DROP COLUMN IF EXISTS geohash;"),
+            ))
+            .await?;
+        }
+        Ok(())
+    }
+}
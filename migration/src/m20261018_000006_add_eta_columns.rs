@@ -0,0 +1,35 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Tables that record the drive-time estimate made at dispatch.
+const TABLES: [&str; 3] = ["emergency", "emergency_ambulance", "dispatch_decision"];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        for table in TABLES {
+            db.execute(Statement::from_string(
+                manager.get_database_backend(),
+                format!("ALTER TABLE {table} ADD COLUMN IF NOT EXISTS eta_seconds INTEGER NULL;"),
+            ))
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        for table in TABLES {
+            db.execute(Statement::from_string(
+                manager.get_database_backend(),
+                format!("ALTER TABLE {table} DROP COLUMN IF EXISTS eta_seconds;"),
+            ))
+            .await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        // Likely duplicates point at the incident they were first reported as
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "ALTER TABLE emergency ADD COLUMN IF NOT EXISTS parent_emergency_id UUID NULL REFERENCES emergency(id) ON DELETE SET NULL;",
+        ))
+        .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "CREATE INDEX IF NOT EXISTS idx_emergency_parent_emergency_id ON emergency (parent_emergency_id) WHERE parent_emergency_id IS NOT NULL;",
+        ))
+        .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"
+            CREATE TABLE IF NOT EXISTS emergency_merge (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                parent_emergency_id UUID NOT NULL REFERENCES emergency(id) ON DELETE CASCADE,
+                merged_emergency_id UUID NOT NULL REFERENCES emergency(id) ON DELETE CASCADE,
+                patients_moved INTEGER NOT NULL DEFAULT 0,
+                units_moved INTEGER NOT NULL DEFAULT 0,
+                reason TEXT NULL,
+                merged_by VARCHAR(255) NOT NULL,
+                snapshot JSONB NOT NULL,
+                merged_at TIMESTAMP NOT NULL DEFAULT now()
+            );
+            "#,
+        ))
+        .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "CREATE INDEX IF NOT EXISTS idx_emergency_merge_parent ON emergency_merge (parent_emergency_id, merged_at);",
+        ))
+        .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "CREATE INDEX IF NOT EXISTS idx_emergency_merge_merged ON emergency_merge (merged_emergency_id);",
+        ))
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table("emergency_merge")
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        let db = manager.get_connection();
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "ALTER TABLE emergency DROP COLUMN IF EXISTS parent_emergency_id;",
+        ))
+        .await?;
+        Ok(())
+    }
+}
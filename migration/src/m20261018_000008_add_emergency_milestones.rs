@@ -0,0 +1,101 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Milestone columns and the status whose first change entry backfills them.
+const STATUS_MILESTONES: [(&str, &str); 3] = [
+    ("at_scene_at", "AtScene"),
+    ("transporting_at", "InTransitToHospital"),
+    ("at_hospital_at", "ArrivedAtHospital"),
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"
+            ALTER TABLE emergency
+                ADD COLUMN IF NOT EXISTS dispatched_at TIMESTAMP NULL,
+                ADD COLUMN IF NOT EXISTS en_route_at TIMESTAMP NULL,
+                ADD COLUMN IF NOT EXISTS at_scene_at TIMESTAMP NULL,
+                ADD COLUMN IF NOT EXISTS transporting_at TIMESTAMP NULL,
+                ADD COLUMN IF NOT EXISTS at_hospital_at TIMESTAMP NULL;
+            "#,
+        ))
+        .await?;
+        // Reports range over `created_at`
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "CREATE INDEX IF NOT EXISTS idx_emergency_created_at ON emergency (created_at);",
+        ))
+        .await?;
+
+        // Older rows may carry a `resolved_at` stamped at creation
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "UPDATE emergency SET resolved_at = NULL WHERE resolved_at IS NOT NULL AND status <> 'RESOLVED';",
+        ))
+        .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"
+            UPDATE emergency e
+            SET dispatched_at = first_unit.assigned_at
+            FROM (
+                SELECT emergency_id, MIN(assigned_at) AS assigned_at
+                FROM emergency_ambulance
+                GROUP BY emergency_id
+            ) first_unit
+            WHERE e.id = first_unit.emergency_id AND e.dispatched_at IS NULL;
+            "#,
+        ))
+        .await?;
+        for (column, status) in STATUS_MILESTONES {
+            db.execute(Statement::from_string(
+                manager.get_database_backend(),
+                format!(
+                    r#"
+                    UPDATE emergency e
+                    SET {column} = (
+                        SELECT MIN((entry->>'at')::timestamp)
+                        FROM jsonb_array_elements(e.modification_attempts) entry
+                        WHERE entry->>'type' = 'CHANGE'
+                          AND entry->>'field' = 'STATUS'
+                          AND entry->>'new' = '{status}'
+                    )
+                    WHERE e.{column} IS NULL
+                      AND jsonb_typeof(e.modification_attempts) = 'array';
+                    "#
+                ),
+            ))
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "DROP INDEX IF EXISTS idx_emergency_created_at;",
+        ))
+        .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"
+            ALTER TABLE emergency
+                DROP COLUMN IF EXISTS dispatched_at,
+                DROP COLUMN IF EXISTS en_route_at,
+                DROP COLUMN IF EXISTS at_scene_at,
+                DROP COLUMN IF EXISTS transporting_at,
+                DROP COLUMN IF EXISTS at_hospital_at;
+            "#,
+        ))
+        .await?;
+        Ok(())
+    }
+}
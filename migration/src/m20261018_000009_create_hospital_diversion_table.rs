@@ -0,0 +1,49 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        // A NULL department diverts the whole emergency department of the hospital
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"
+            CREATE TABLE IF NOT EXISTS hospital_diversion (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                hospital_id UUID NOT NULL REFERENCES hospital(id) ON DELETE CASCADE,
+                department department_name_enum NULL,
+                reason TEXT NOT NULL,
+                starts_at TIMESTAMP NOT NULL,
+                ends_at TIMESTAMP NULL,
+                declared_by VARCHAR(255) NOT NULL,
+                lifted_at TIMESTAMP NULL,
+                lifted_by VARCHAR(255) NULL,
+                created_at TIMESTAMP NOT NULL DEFAULT now(),
+                CHECK (ends_at IS NULL OR ends_at > starts_at)
+            );
+            "#,
+        ))
+        .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "CREATE INDEX IF NOT EXISTS idx_hospital_diversion_hospital_id ON hospital_diversion (hospital_id, starts_at);",
+        ))
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table("hospital_diversion")
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,53 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"DO $$
+            BEGIN
+                IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'emergency_verification_enum') THEN
+                    CREATE TYPE emergency_verification_enum AS ENUM ('VERIFIED', 'PROVISIONAL', 'REJECTED');
+                END IF;
+            END $$;"#,
+        ))
+        .await?;
+        // Existing cases were all created by authenticated staff
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"
+            ALTER TABLE emergency
+                ADD COLUMN IF NOT EXISTS verification_status emergency_verification_enum NOT NULL DEFAULT 'VERIFIED',
+                ADD COLUMN IF NOT EXISTS callback_phone VARCHAR(32) NULL;
+            "#,
+        ))
+        .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "CREATE INDEX IF NOT EXISTS idx_emergency_provisional ON emergency (created_at) WHERE verification_status = 'PROVISIONAL';",
+        ))
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "ALTER TABLE emergency DROP COLUMN IF EXISTS verification_status, DROP COLUMN IF EXISTS callback_phone;",
+        ))
+        .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "DROP TYPE IF EXISTS emergency_verification_enum;",
+        ))
+        .await?;
+        Ok(())
+    }
+}
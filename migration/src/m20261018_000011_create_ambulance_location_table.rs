@@ -0,0 +1,88 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        // A device resending a batch must not duplicate its points
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"
+            CREATE TABLE IF NOT EXISTS ambulance_location (
+                id BIGSERIAL PRIMARY KEY,
+                ambulance_id UUID NOT NULL REFERENCES ambulance(id) ON DELETE CASCADE,
+                recorded_at TIMESTAMP NOT NULL,
+                latitude DECIMAL(9, 6) NOT NULL,
+                longitude DECIMAL(9, 6) NOT NULL,
+                speed_kmh DOUBLE PRECISION NULL,
+                heading DOUBLE PRECISION NULL,
+                accuracy_m DOUBLE PRECISION NULL,
+                received_at TIMESTAMP NOT NULL DEFAULT now(),
+                UNIQUE (ambulance_id, recorded_at)
+            );
+            "#,
+        ))
+        .await?;
+        // History is append-only; rows only leave through retention
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"
+            CREATE OR REPLACE FUNCTION ambulance_location_append_only() RETURNS trigger AS $$
+            BEGIN
+                RAISE EXCEPTION 'ambulance_location is append-only';
+            END;
+            $$ LANGUAGE plpgsql;
+            "#,
+        ))
+        .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"
+            DROP TRIGGER IF EXISTS trg_ambulance_location_append_only ON ambulance_location;
+            CREATE TRIGGER trg_ambulance_location_append_only
+                BEFORE UPDATE ON ambulance_location
+                FOR EACH ROW EXECUTE FUNCTION ambulance_location_append_only();
+            "#,
+        ))
+        .await?;
+        // Time of the fix behind the current position, so late batches do not move it back
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "ALTER TABLE ambulance ADD COLUMN IF NOT EXISTS location_updated_at TIMESTAMP NULL;",
+        ))
+        .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "CREATE INDEX IF NOT EXISTS idx_ambulance_location_recorded_at ON ambulance_location (recorded_at);",
+        ))
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "ALTER TABLE ambulance DROP COLUMN IF EXISTS location_updated_at;",
+        ))
+        .await?;
+        manager
+            .drop_table(
+                Table::drop()
+                    .table("ambulance_location")
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "DROP FUNCTION IF EXISTS ambulance_location_append_only();",
+        ))
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"DO $$
+            BEGIN
+                IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'geofence_kind_enum') THEN
+                    CREATE TYPE geofence_kind_enum AS ENUM ('SCENE', 'HOSPITAL');
+                END IF;
+                IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'geofence_outcome_enum') THEN
+                    CREATE TYPE geofence_outcome_enum AS ENUM ('APPLIED', 'PROPOSED', 'ACCEPTED', 'DISMISSED', 'REVERTED');
+                END IF;
+            END $$;"#,
+        ))
+        .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"
+            CREATE TABLE IF NOT EXISTS geofence_event (
+                id UUID PRIMARY KEY,
+                ambulance_id UUID NOT NULL REFERENCES ambulance(id) ON DELETE CASCADE,
+                emergency_id UUID NULL REFERENCES emergency(id) ON DELETE SET NULL,
+                hospital_id UUID NULL REFERENCES hospital(id) ON DELETE SET NULL,
+                fence geofence_kind_enum NOT NULL,
+                entered BOOLEAN NOT NULL,
+                from_status ambulance_status_enum NOT NULL,
+                to_status ambulance_status_enum NOT NULL,
+                outcome geofence_outcome_enum NOT NULL,
+                latitude DECIMAL(9, 6) NOT NULL,
+                longitude DECIMAL(9, 6) NOT NULL,
+                distance_m DOUBLE PRECISION NOT NULL,
+                recorded_at TIMESTAMP NOT NULL,
+                created_at TIMESTAMP NOT NULL DEFAULT now(),
+                resolved_at TIMESTAMP NULL,
+                resolved_by TEXT NULL
+            );
+            "#,
+        ))
+        .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "CREATE INDEX IF NOT EXISTS idx_geofence_event_ambulance ON geofence_event (ambulance_id, created_at);",
+        ))
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        manager
+            .drop_table(Table::drop().table("geofence_event").if_exists().to_owned())
+            .await?;
+        for statement in [
+            "DROP TYPE IF EXISTS geofence_outcome_enum;",
+            "DROP TYPE IF EXISTS geofence_kind_enum;",
+        ] {
+            db.execute(Statement::from_string(
+                manager.get_database_backend(),
+                statement,
+            ))
+            .await?;
+        }
+        Ok(())
+    }
+}
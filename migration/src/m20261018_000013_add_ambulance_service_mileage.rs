@@ -0,0 +1,29 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        // Odometer at the last service, so service can fall due by distance as well as by date
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "ALTER TABLE ambulance ADD COLUMN IF NOT EXISTS last_service_mileage INTEGER NULL;",
+        ))
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "ALTER TABLE ambulance DROP COLUMN IF EXISTS last_service_mileage;",
+        ))
+        .await?;
+        Ok(())
+    }
+}
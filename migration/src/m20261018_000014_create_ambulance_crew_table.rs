@@ -0,0 +1,132 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        // An older `staff_schedule` keyed staff, departments and hospitals by
+        // integers, which no longer match their UUID ids. Its rows cannot be
+        // linked to anyone, so it is kept aside as `staff_schedule_legacy`
+        // with its indexes renamed out of the way.
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"DO $$
+            BEGIN
+                IF EXISTS (
+                    SELECT 1 FROM information_schema.columns
+                    WHERE table_name = 'staff_schedule' AND column_name = 'staff_id' AND data_type <> 'uuid'
+                ) THEN
+                    ALTER TABLE staff_schedule RENAME TO staff_schedule_legacy;
+                    ALTER INDEX IF EXISTS staff_schedule_pkey RENAME TO staff_schedule_legacy_pkey;
+                    ALTER INDEX IF EXISTS staff_schedule_staff_schedule_ic_key RENAME TO staff_schedule_legacy_staff_schedule_ic_key;
+                END IF;
+            END $$;"#,
+        ))
+        .await?;
+        // Shifts of staff members; a shift ending before it starts runs past midnight
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"
+            CREATE TABLE IF NOT EXISTS staff_schedule (
+                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+                staff_schedule_ic VARCHAR NOT NULL UNIQUE,
+                staff_id UUID NOT NULL REFERENCES staff(id) ON DELETE CASCADE,
+                department_id UUID NOT NULL REFERENCES department(id) ON DELETE CASCADE,
+                hospital_id UUID NOT NULL REFERENCES hospital(id) ON DELETE CASCADE,
+                schedule_date DATE NOT NULL,
+                start_time TIME NOT NULL,
+                end_time TIME NOT NULL,
+                created_at TIMESTAMP NOT NULL DEFAULT now(),
+                updated_at TIMESTAMP NOT NULL DEFAULT now()
+            );
+            "#,
+        ))
+        .await?;
+        // A UUID table created by hand may lack the ic column
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"DO $$
+            BEGIN
+                IF NOT EXISTS (
+                    SELECT 1 FROM information_schema.columns
+                    WHERE table_name = 'staff_schedule' AND column_name = 'staff_schedule_ic'
+                ) THEN
+                    ALTER TABLE staff_schedule ADD COLUMN staff_schedule_ic VARCHAR;
+                    UPDATE staff_schedule SET staff_schedule_ic = id::text;
+                    ALTER TABLE staff_schedule ALTER COLUMN staff_schedule_ic SET NOT NULL;
+                    ALTER TABLE staff_schedule ADD CONSTRAINT staff_schedule_staff_schedule_ic_key UNIQUE (staff_schedule_ic);
+                END IF;
+            END $$;"#,
+        ))
+        .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "CREATE INDEX IF NOT EXISTS idx_staff_schedule_staff_date ON staff_schedule (staff_id, schedule_date);",
+        ))
+        .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"DO $$
+            BEGIN
+                IF NOT EXISTS (SELECT 1 FROM pg_type WHERE typname = 'crew_role_enum') THEN
+                    CREATE TYPE crew_role_enum AS ENUM ('DRIVER', 'PARAMEDIC', 'PHYSICIAN');
+                END IF;
+            END $$;"#,
+        ))
+        .await?;
+        // A shift is worked on one ambulance only
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"
+            CREATE TABLE IF NOT EXISTS ambulance_crew (
+                id UUID PRIMARY KEY,
+                ambulance_id UUID NOT NULL REFERENCES ambulance(id) ON DELETE CASCADE,
+                staff_id UUID NOT NULL REFERENCES staff(id) ON DELETE CASCADE,
+                staff_schedule_id UUID NOT NULL UNIQUE REFERENCES staff_schedule(id) ON DELETE CASCADE,
+                role crew_role_enum NOT NULL,
+                assigned_by TEXT NOT NULL,
+                created_at TIMESTAMP NOT NULL DEFAULT now()
+            );
+            "#,
+        ))
+        .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "CREATE INDEX IF NOT EXISTS idx_ambulance_crew_ambulance ON ambulance_crew (ambulance_id);",
+        ))
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        manager
+            .drop_table(Table::drop().table("ambulance_crew").if_exists().to_owned())
+            .await?;
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "DROP TYPE IF EXISTS crew_role_enum;",
+        ))
+        .await?;
+        // Put an older integer-keyed table back; otherwise `staff_schedule`
+        // is kept, it may predate this migration
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"DO $$
+            BEGIN
+                IF EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = 'staff_schedule_legacy') THEN
+                    DROP TABLE IF EXISTS staff_schedule;
+                    ALTER TABLE staff_schedule_legacy RENAME TO staff_schedule;
+                    ALTER INDEX IF EXISTS staff_schedule_legacy_pkey RENAME TO staff_schedule_pkey;
+                    ALTER INDEX IF EXISTS staff_schedule_legacy_staff_schedule_ic_key RENAME TO staff_schedule_staff_schedule_ic_key;
+                END IF;
+            END $$;"#,
+        ))
+        .await?;
+        Ok(())
+    }
+}
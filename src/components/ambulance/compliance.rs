@@ -0,0 +1,405 @@
+use chrono::{Duration, NaiveDateTime};
+use log::error;
+use sea_orm::prelude::Uuid;
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+use serde::Serialize;
+
+use crate::components::ambulance::lifecycle::{allowed_transitions, is_off_road};
+use crate::components::ambulance::services::AmbulanceService;
+use crate::components::emergency::history::{COMPLIANCE_ACTOR, ChangeSource};
+use crate::entity::ambulance::{self, AmbulanceId, AmbulancePayload};
+use crate::entity::hospital;
+use crate::entity::sea_orm_active_enums::AmbulanceStatusEnum;
+use crate::http_response::HttpCodeW;
+use crate::http_response::error_handler::CustomError;
+use crate::utils::helpers::{env_or, now_time};
+
+/// Environment variable with the distance between services, in km.
+pub const SERVICE_INTERVAL_KM_ENV: &str = "SERVICE_INTERVAL_KM";
+/// Environment variable with how many km before the interval a service is due soon.
+pub const SERVICE_WARNING_KM_ENV: &str = "SERVICE_WARNING_KM";
+/// Environment variable with how many days before `next_service_date` a service is due soon.
+pub const SERVICE_WARNING_DAYS_ENV: &str = "SERVICE_WARNING_DAYS";
+/// Environment variable with how many days before expiry insurance is flagged.
+pub const INSURANCE_WARNING_DAYS_ENV: &str = "INSURANCE_WARNING_DAYS";
+/// Environment variable with the compliance job period in seconds.
+pub const COMPLIANCE_INTERVAL_ENV: &str = "COMPLIANCE_INTERVAL_SECS";
+const DEFAULT_SERVICE_INTERVAL_KM: i32 = 15_000;
+const DEFAULT_SERVICE_WARNING_KM: i32 = 1_000;
+const DEFAULT_SERVICE_WARNING_DAYS: i64 = 14;
+const DEFAULT_INSURANCE_WARNING_DAYS: i64 = 30;
+const DEFAULT_COMPLIANCE_INTERVAL_SECS: u64 = 3600;
+
+/// How fit a vehicle is to be on the road, worst last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ComplianceState {
+    Compliant,
+    /// Still dispatchable, but something needs doing soon.
+    DueSoon,
+    /// Kept out of allocation and taken off the road.
+    NonCompliant,
+}
+
+/// What a compliance issue is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ComplianceCheck {
+    ServiceDate,
+    ServiceMileage,
+    Insurance,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComplianceIssue {
+    pub check: ComplianceCheck,
+    pub state: ComplianceState,
+    pub detail: String,
+}
+
+/// The fields of a vehicle compliance depends on.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ServiceRecord {
+    pub next_service_date: Option<NaiveDateTime>,
+    pub mileage: Option<i32>,
+    pub last_service_mileage: Option<i32>,
+    pub insurance_expiry_date: Option<NaiveDateTime>,
+}
+
+impl From<&ambulance::Model> for ServiceRecord {
+    fn from(vehicle: &ambulance::Model) -> Self {
+        ServiceRecord {
+            next_service_date: vehicle.next_service_date,
+            mileage: vehicle.mileage,
+            last_service_mileage: vehicle.last_service_mileage,
+            insurance_expiry_date: vehicle.insurance_expiry_date,
+        }
+    }
+}
+
+/// Compliance of one vehicle in a report.
+#[derive(Debug, Clone, Serialize)]
+pub struct VehicleCompliance {
+    pub ambulance_id: Uuid,
+    pub ambulance_ic: i32,
+    pub vehicle_number: String,
+    pub registration_number: Option<String>,
+    pub status: AmbulanceStatusEnum,
+    pub state: ComplianceState,
+    pub issues: Vec<ComplianceIssue>,
+}
+
+/// Compliance of the fleet of a hospital, worst vehicles first.
+#[derive(Debug, Clone, Serialize)]
+pub struct ComplianceReport {
+    pub hospital_id: Uuid,
+    pub generated_at: NaiveDateTime,
+    pub compliant: usize,
+    pub due_soon: usize,
+    pub non_compliant: usize,
+    pub vehicles: Vec<VehicleCompliance>,
+}
+
+/// When service and insurance fall due.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompliancePolicy {
+    pub service_interval_km: i32,
+    pub service_warning_km: i32,
+    pub service_warning_days: i64,
+    pub insurance_warning_days: i64,
+}
+
+impl Default for CompliancePolicy {
+    fn default() -> Self {
+        CompliancePolicy {
+            service_interval_km: DEFAULT_SERVICE_INTERVAL_KM,
+            service_warning_km: DEFAULT_SERVICE_WARNING_KM,
+            service_warning_days: DEFAULT_SERVICE_WARNING_DAYS,
+            insurance_warning_days: DEFAULT_INSURANCE_WARNING_DAYS,
+        }
+    }
+}
+
+impl CompliancePolicy {
+    /// Reads `SERVICE_INTERVAL_KM`, `SERVICE_WARNING_KM`, `SERVICE_WARNING_DAYS`
+    /// and `INSURANCE_WARNING_DAYS`.
+    pub fn from_env() -> Self {
+        CompliancePolicy {
+            service_interval_km: env_or(SERVICE_INTERVAL_KM_ENV, DEFAULT_SERVICE_INTERVAL_KM),
+            service_warning_km: env_or(SERVICE_WARNING_KM_ENV, DEFAULT_SERVICE_WARNING_KM),
+            service_warning_days: env_or(SERVICE_WARNING_DAYS_ENV, DEFAULT_SERVICE_WARNING_DAYS),
+            insurance_warning_days: env_or(
+                INSURANCE_WARNING_DAYS_ENV,
+                DEFAULT_INSURANCE_WARNING_DAYS,
+            ),
+        }
+    }
+
+    /// Everything due soon or overdue on a vehicle at `now`. A vehicle
+    /// without any service schedule or insurance date is flagged, not
+    /// taken off the road.
+    pub fn assess(&self, record: &ServiceRecord, now: NaiveDateTime) -> Vec<ComplianceIssue> {
+        let mut issues = Vec::new();
+        let mut flag = |check, state, detail: String| {
+            issues.push(ComplianceIssue {
+                check,
+                state,
+                detail,
+            })
+        };
+
+        if let Some(due) = record.next_service_date {
+            if due < now {
+                flag(
+                    ComplianceCheck::ServiceDate,
+                    ComplianceState::NonCompliant,
+                    format!("service overdue since {}", due.date()),
+                );
+            } else if due <= now + Duration::days(self.service_warning_days) {
+                flag(
+                    ComplianceCheck::ServiceDate,
+                    ComplianceState::DueSoon,
+                    format!("service due on {}", due.date()),
+                );
+            }
+        }
+        let driven = record
+            .mileage
+            .zip(record.last_service_mileage)
+            .map(|(mileage, serviced_at)| mileage - serviced_at);
+        if let Some(driven) = driven {
+            if driven >= self.service_interval_km {
+                flag(
+                    ComplianceCheck::ServiceMileage,
+                    ComplianceState::NonCompliant,
+                    format!(
+                        "{driven} km since the last service, interval {} km",
+                        self.service_interval_km
+                    ),
+                );
+            } else if driven >= self.service_interval_km - self.service_warning_km {
+                flag(
+                    ComplianceCheck::ServiceMileage,
+                    ComplianceState::DueSoon,
+                    format!("service due in {} km", self.service_interval_km - driven),
+                );
+            }
+        }
+        if record.next_service_date.is_none() && driven.is_none() {
+            flag(
+                ComplianceCheck::ServiceDate,
+                ComplianceState::DueSoon,
+                "no service schedule on record".to_string(),
+            );
+        }
+        match record.insurance_expiry_date {
+            Some(expiry) if expiry < now => flag(
+                ComplianceCheck::Insurance,
+                ComplianceState::NonCompliant,
+                format!("insurance expired on {}", expiry.date()),
+            ),
+            Some(expiry) if expiry <= now + Duration::days(self.insurance_warning_days) => flag(
+                ComplianceCheck::Insurance,
+                ComplianceState::DueSoon,
+                format!("insurance expires on {}", expiry.date()),
+            ),
+            Some(_) => {}
+            None => flag(
+                ComplianceCheck::Insurance,
+                ComplianceState::DueSoon,
+                "no insurance expiry on record".to_string(),
+            ),
+        }
+        issues
+    }
+
+    /// Vehicles that are not `NonCompliant` at `now`, the same rules as
+    /// `assess` in SQL so the allocator can filter before its limit.
+    pub fn condition(&self, now: NaiveDateTime) -> Condition {
+        use ambulance::Column;
+        Condition::all()
+            .add(
+                Condition::any()
+                    .add(Column::NextServiceDate.is_null())
+                    .add(Column::NextServiceDate.gte(now)),
+            )
+            .add(
+                Condition::any()
+                    .add(Column::InsuranceExpiryDate.is_null())
+                    .add(Column::InsuranceExpiryDate.gte(now)),
+            )
+            .add(
+                Condition::any()
+                    .add(Column::Mileage.is_null())
+                    .add(Column::LastServiceMileage.is_null())
+                    .add(
+                        Expr::expr(
+                            Expr::col((ambulance::Entity, Column::Mileage))
+                                .sub(Expr::col((ambulance::Entity, Column::LastServiceMileage))),
+                        )
+                        .lt(self.service_interval_km),
+                    ),
+            )
+    }
+
+    /// Refuses a vehicle that is `NonCompliant`, listing why.
+    pub fn ensure_roadworthy(
+        &self,
+        vehicle: &ambulance::Model,
+        now: NaiveDateTime,
+    ) -> Result<(), CustomError> {
+        let overdue: Vec<String> = self
+            .assess(&ServiceRecord::from(vehicle), now)
+            .into_iter()
+            .filter(|issue| issue.state == ComplianceState::NonCompliant)
+            .map(|issue| issue.detail)
+            .collect();
+        if overdue.is_empty() {
+            return Ok(());
+        }
+        Err(CustomError::new(
+            HttpCodeW::UnprocessableEntity,
+            format!(
+                "Ambulance {} is not compliant: {}",
+                vehicle.ambulance_ic,
+                overdue.join("; ")
+            ),
+        ))
+    }
+}
+
+/// Worst state among the issues of a vehicle.
+pub fn worst_state(issues: &[ComplianceIssue]) -> ComplianceState {
+    issues
+        .iter()
+        .map(|issue| issue.state)
+        .max()
+        .unwrap_or(ComplianceState::Compliant)
+}
+
+/// Whether the compliance job may take a vehicle in `status` to
+/// `Maintenance`: it is on the road and may go there from its status.
+/// Vehicles under repair or out of service stay where they are.
+pub fn should_move_to_maintenance(status: &AmbulanceStatusEnum) -> bool {
+    !is_off_road(status) && allowed_transitions(status).contains(&AmbulanceStatusEnum::Maintenance)
+}
+
+pub struct ComplianceService {
+    conn: DatabaseConnection,
+    policy: CompliancePolicy,
+}
+
+impl ComplianceService {
+    pub fn new(conn: &DatabaseConnection) -> Self {
+        Self {
+            conn: conn.clone(),
+            policy: CompliancePolicy::from_env(),
+        }
+    }
+
+    /// Compliance of every vehicle of a hospital.
+    pub async fn report(&self, hospital_id: Uuid) -> Result<ComplianceReport, CustomError> {
+        if hospital::Entity::find_by_id(hospital_id)
+            .one(&self.conn)
+            .await?
+            .is_none()
+        {
+            return Err(CustomError::new(
+                HttpCodeW::NotFound,
+                "Hospital not found".to_string(),
+            ));
+        }
+        let now = now_time();
+        let fleet = ambulance::Entity::find()
+            .filter(ambulance::Column::HospitalId.eq(hospital_id))
+            .order_by_asc(ambulance::Column::AmbulanceIc)
+            .all(&self.conn)
+            .await?;
+        let mut vehicles: Vec<VehicleCompliance> = fleet
+            .into_iter()
+            .map(|vehicle| {
+                let issues = self.policy.assess(&ServiceRecord::from(&vehicle), now);
+                let state = worst_state(&issues);
+                VehicleCompliance {
+                    ambulance_id: vehicle.id,
+                    ambulance_ic: vehicle.ambulance_ic,
+                    vehicle_number: vehicle.vehicle_number,
+                    registration_number: vehicle.registration_number,
+                    status: vehicle.status,
+                    state,
+                    issues,
+                }
+            })
+            .collect();
+        // Stable, so vehicles keep their ic order within a state
+        vehicles.sort_by_key(|vehicle| std::cmp::Reverse(vehicle.state));
+        let count = |state| vehicles.iter().filter(|v| v.state == state).count();
+        Ok(ComplianceReport {
+            hospital_id,
+            generated_at: now,
+            compliant: count(ComplianceState::Compliant),
+            due_soon: count(ComplianceState::DueSoon),
+            non_compliant: count(ComplianceState::NonCompliant),
+            vehicles,
+        })
+    }
+
+    /// Moves non-compliant vehicles to `Maintenance`. Units on a mission are
+    /// left alone until a status they may leave for maintenance from.
+    /// Returns how many vehicles were moved.
+    pub async fn enforce(&self) -> Result<usize, CustomError> {
+        let now = now_time();
+        let candidates = ambulance::Entity::find()
+            .filter(self.policy.condition(now).not())
+            .all(&self.conn)
+            .await?;
+        let service = AmbulanceService::new(&self.conn);
+        let mut moved = 0;
+        for vehicle in candidates {
+            if !should_move_to_maintenance(&vehicle.status) {
+                continue;
+            }
+            let payload = AmbulancePayload {
+                status: Some(AmbulanceStatusEnum::Maintenance),
+                ..Default::default()
+            };
+            match service
+                .update_ambulance(
+                    AmbulanceId::Uuid(vehicle.id),
+                    payload,
+                    COMPLIANCE_ACTOR,
+                    ChangeSource::Compliance,
+                )
+                .await
+            {
+                Ok(_) => moved += 1,
+                Err(e) => error!(
+                    "Could not take ambulance {} off the road: {e}",
+                    vehicle.ambulance_ic
+                ),
+            }
+        }
+        Ok(moved)
+    }
+}
+
+/// Periodically moves vehicles that fell out of compliance to `Maintenance`.
+pub async fn start_compliance_job(
+    db_conn: &DatabaseConnection,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let interval = std::time::Duration::from_secs(env_or(
+        COMPLIANCE_INTERVAL_ENV,
+        DEFAULT_COMPLIANCE_INTERVAL_SECS,
+    ));
+    println!("Fleet compliance job started (every {interval:?})");
+
+    loop {
+        match ComplianceService::new(db_conn).enforce().await {
+            Ok(0) => {}
+            Ok(count) => println!("Moved {count} non-compliant ambulances to maintenance"),
+            Err(e) => error!("Fleet compliance pass failed: {e}"),
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
@@ -0,0 +1,527 @@
+use chrono::{Duration, NaiveDateTime};
+use rust_decimal::prelude::ToPrimitive;
+use sea_orm::prelude::{Decimal, Uuid};
+use sea_orm::*;
+use serde::Serialize;
+
+use crate::entity::ambulance::CoverageQuery;
+use crate::entity::sea_orm_active_enums::{AmbulanceStatusEnum, AmbulanceTypeEnum};
+use crate::entity::{ambulance, emergency, hospital};
+use crate::http_response::HttpCodeW;
+use crate::http_response::error_handler::CustomError;
+use crate::utils::eta::estimate_matrix;
+use crate::utils::helpers::{env_or, now_time};
+use crate::utils::spatial::{BoundingBox, KM_PER_DEGREE_LATITUDE, bounding_box_condition};
+
+/// Environment variable with the response time a cell must be reached in, in minutes.
+pub const COVERAGE_TARGET_ENV: &str = "COVERAGE_TARGET_MINUTES";
+const DEFAULT_TARGET_MINUTES: f64 = 8.0;
+const DEFAULT_CELL_KM: f64 = 2.0;
+const MIN_CELL_KM: f64 = 0.25;
+const MAX_CELLS: usize = 2500;
+const DEFAULT_MAX_MOVES: usize = 3;
+const MAX_MOVES: usize = 10;
+/// Past emergencies counted as demand, in days.
+const DEMAND_DAYS: i64 = 30;
+/// Weight of a cell without past emergencies, so empty areas still break ties.
+const BASE_CELL_WEIGHT: f64 = 0.1;
+
+/// Service area cut into cells of roughly `cell_km` a side.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoverageGrid {
+    pub bounds: BoundingBox,
+    pub rows: usize,
+    pub cols: usize,
+}
+
+impl CoverageGrid {
+    /// Longitude cells are sized at the middle latitude of the area.
+    pub fn new(bounds: BoundingBox, cell_km: f64) -> Self {
+        let height_km = (bounds.max_latitude - bounds.min_latitude) * KM_PER_DEGREE_LATITUDE;
+        let middle = (bounds.min_latitude + bounds.max_latitude) / 2.0;
+        let width_km = (bounds.max_longitude - bounds.min_longitude)
+            * KM_PER_DEGREE_LATITUDE
+            * middle.to_radians().cos();
+        CoverageGrid {
+            bounds,
+            rows: ((height_km / cell_km).ceil() as usize).max(1),
+            cols: ((width_km / cell_km).ceil() as usize).max(1),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows * self.cols
+    }
+
+    fn cell_height(&self) -> f64 {
+        (self.bounds.max_latitude - self.bounds.min_latitude) / self.rows as f64
+    }
+
+    fn cell_width(&self) -> f64 {
+        (self.bounds.max_longitude - self.bounds.min_longitude) / self.cols as f64
+    }
+
+    /// Centre of the cell at `index`, counted row by row from the south-west.
+    pub fn centre(&self, index: usize) -> (f64, f64) {
+        let (row, col) = (index / self.cols, index % self.cols);
+        (
+            self.bounds.min_latitude + (row as f64 + 0.5) * self.cell_height(),
+            self.bounds.min_longitude + (col as f64 + 0.5) * self.cell_width(),
+        )
+    }
+
+    /// Index of the cell holding a point, `None` outside the area.
+    pub fn cell_of(&self, latitude: f64, longitude: f64) -> Option<usize> {
+        let b = &self.bounds;
+        if !(b.min_latitude..=b.max_latitude).contains(&latitude)
+            || !(b.min_longitude..=b.max_longitude).contains(&longitude)
+        {
+            return None;
+        }
+        let row = (((latitude - b.min_latitude) / self.cell_height()) as usize).min(self.rows - 1);
+        let col = (((longitude - b.min_longitude) / self.cell_width()) as usize).min(self.cols - 1);
+        Some(row * self.cols + col)
+    }
+}
+
+/// Suggested move of one idle unit, by index into the inputs of `suggest_moves`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlannedMove {
+    pub unit: usize,
+    pub standby: usize,
+    /// Weight of the cells over target before and after the move.
+    pub uncovered_before: f64,
+    pub uncovered_after: f64,
+}
+
+/// Best and second-best time to a cell, with the unit giving the best one.
+fn two_fastest(etas: &[f64]) -> (Option<usize>, f64, f64) {
+    let mut best = (None, f64::INFINITY, f64::INFINITY);
+    for (unit, &eta) in etas.iter().enumerate() {
+        if eta < best.1 {
+            best = (Some(unit), eta, best.1);
+        } else if eta < best.2 {
+            best.2 = eta;
+        }
+    }
+    best
+}
+
+/// Greedily picks up to `max_moves` relocations of distinct units to standby
+/// points, each time the one that most reduces the weight of cells whose
+/// fastest unit takes longer than `target_minutes`.
+///
+/// `current_eta(cell, unit)` and `standby_eta(cell, unit, standby)` give drive
+/// times in minutes from where a unit is and from a standby point.
+pub fn suggest_moves(
+    weights: &[f64],
+    units: usize,
+    standbys: usize,
+    current_eta: impl Fn(usize, usize) -> f64,
+    standby_eta: impl Fn(usize, usize, usize) -> f64,
+    target_minutes: f64,
+    max_moves: usize,
+) -> Vec<PlannedMove> {
+    let mut etas: Vec<Vec<f64>> = (0..weights.len())
+        .map(|cell| (0..units).map(|unit| current_eta(cell, unit)).collect())
+        .collect();
+    let mut moved = vec![false; units];
+    let mut moves = Vec::new();
+
+    while moves.len() < max_moves {
+        let fastest: Vec<(Option<usize>, f64, f64)> =
+            etas.iter().map(|row| two_fastest(row)).collect();
+        let uncovered_before: f64 = fastest
+            .iter()
+            .zip(weights)
+            .filter(|((_, best, _), _)| *best > target_minutes)
+            .map(|(_, weight)| weight)
+            .sum();
+
+        let mut best: Option<PlannedMove> = None;
+        for unit in (0..units).filter(|&unit| !moved[unit]) {
+            for standby in 0..standbys {
+                let uncovered_after: f64 = fastest
+                    .iter()
+                    .zip(weights)
+                    .enumerate()
+                    .filter(|(cell, ((fastest_unit, first, second), _))| {
+                        let others = if *fastest_unit == Some(unit) {
+                            *second
+                        } else {
+                            *first
+                        };
+                        others.min(standby_eta(*cell, unit, standby)) > target_minutes
+                    })
+                    .map(|(_, (_, weight))| weight)
+                    .sum();
+                if uncovered_after < uncovered_before - f64::EPSILON
+                    && best.is_none_or(|b| uncovered_after < b.uncovered_after)
+                {
+                    best = Some(PlannedMove {
+                        unit,
+                        standby,
+                        uncovered_before,
+                        uncovered_after,
+                    });
+                }
+            }
+        }
+
+        let Some(planned) = best else {
+            break;
+        };
+        for (cell, row) in etas.iter_mut().enumerate() {
+            row[planned.unit] = standby_eta(cell, planned.unit, planned.standby);
+        }
+        moved[planned.unit] = true;
+        moves.push(planned);
+    }
+    moves
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CoverageCell {
+    pub row: usize,
+    pub col: usize,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Emergencies reported in the cell over the last 30 days.
+    pub demand: u32,
+    /// Fastest drive time from an available ambulance, `None` without any.
+    pub eta_seconds: Option<i32>,
+    pub nearest_ambulance_id: Option<Uuid>,
+    pub over_target: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CoverageSummary {
+    pub available_ambulances: usize,
+    pub cells: usize,
+    pub cells_over_target: usize,
+    /// Share of the recent demand reached within target, 0–100.
+    pub demand_covered_pct: f64,
+}
+
+/// A suggested move of an idle ambulance to a hospital used as standby point.
+#[derive(Debug, Clone, Serialize)]
+pub struct RepositionSuggestion {
+    pub ambulance_id: Uuid,
+    pub ambulance_ic: i32,
+    pub standby_hospital_id: Uuid,
+    pub standby_hospital_name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub travel_seconds: i32,
+    /// Demand-weighted cells over target before and after the move, counting
+    /// earlier suggestions as done.
+    pub uncovered_weight_before: f64,
+    pub uncovered_weight_after: f64,
+}
+
+/// Response of `GET /ambulance/coverage`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CoverageReport {
+    pub generated_at: NaiveDateTime,
+    pub target_minutes: f64,
+    pub cell_km: f64,
+    pub rows: usize,
+    pub cols: usize,
+    pub summary: CoverageSummary,
+    pub suggestions: Vec<RepositionSuggestion>,
+    pub cells: Vec<CoverageCell>,
+}
+
+fn position(latitude: Decimal, longitude: Decimal) -> Option<(f64, f64)> {
+    Some((latitude.to_f64()?, longitude.to_f64()?))
+}
+
+/// Smallest box around the points, grown by `margin_km` on every side.
+fn bounds_around(points: &[(f64, f64)], margin_km: f64) -> Option<BoundingBox> {
+    let (first, rest) = points.split_first()?;
+    let mut bounds = BoundingBox {
+        min_latitude: first.0,
+        min_longitude: first.1,
+        max_latitude: first.0,
+        max_longitude: first.1,
+    };
+    for &(latitude, longitude) in rest {
+        bounds.min_latitude = bounds.min_latitude.min(latitude);
+        bounds.max_latitude = bounds.max_latitude.max(latitude);
+        bounds.min_longitude = bounds.min_longitude.min(longitude);
+        bounds.max_longitude = bounds.max_longitude.max(longitude);
+    }
+    let south_west = BoundingBox::around(bounds.min_latitude, bounds.min_longitude, margin_km);
+    let north_east = BoundingBox::around(bounds.max_latitude, bounds.max_longitude, margin_km);
+    Some(BoundingBox {
+        min_latitude: south_west.min_latitude,
+        min_longitude: south_west.min_longitude,
+        max_latitude: north_east.max_latitude,
+        max_longitude: north_east.max_longitude,
+    })
+}
+
+pub struct CoverageService {
+    conn: DatabaseConnection,
+}
+
+impl CoverageService {
+    pub fn new(conn: &DatabaseConnection) -> Self {
+        Self { conn: conn.clone() }
+    }
+
+    /// Expected response time from the available ambulances across the
+    /// service area, and moves of idle units that would close the gaps.
+    pub async fn report(&self, query: CoverageQuery) -> Result<CoverageReport, CustomError> {
+        let target_minutes = query
+            .target_minutes
+            .unwrap_or_else(|| env_or(COVERAGE_TARGET_ENV, DEFAULT_TARGET_MINUTES));
+        let cell_km = query.cell_km.unwrap_or(DEFAULT_CELL_KM);
+        let max_moves = query.max_moves.unwrap_or(DEFAULT_MAX_MOVES).min(MAX_MOVES);
+        if !(target_minutes.is_finite() && target_minutes > 0.0) {
+            return Err(CustomError::new(
+                HttpCodeW::BadRequest,
+                "target_minutes must be greater than 0".to_string(),
+            ));
+        }
+        if !(cell_km.is_finite() && cell_km >= MIN_CELL_KM) {
+            return Err(CustomError::new(
+                HttpCodeW::BadRequest,
+                format!("cell_km must be at least {MIN_CELL_KM}"),
+            ));
+        }
+
+        let ambulances = ambulance::Entity::find().all(&self.conn).await?;
+        let standbys: Vec<(hospital::Model, (f64, f64))> = hospital::Entity::find()
+            .all(&self.conn)
+            .await?
+            .into_iter()
+            .filter_map(|h| {
+                let at = position(h.latitude?, h.longitude?)?;
+                Some((h, at))
+            })
+            .collect();
+        let available: Vec<(ambulance::Model, (f64, f64))> = ambulances
+            .iter()
+            .filter(|a| a.status == AmbulanceStatusEnum::Available)
+            .filter_map(|a| {
+                Some((
+                    a.clone(),
+                    position(a.location_latitude, a.location_longitude)?,
+                ))
+            })
+            .collect();
+
+        let bounds = match (query.min_lat, query.min_lon, query.max_lat, query.max_lon) {
+            (Some(min_latitude), Some(min_longitude), Some(max_latitude), Some(max_longitude)) => {
+                if min_latitude >= max_latitude || min_longitude >= max_longitude {
+                    return Err(CustomError::new(
+                        HttpCodeW::BadRequest,
+                        "min_lat/min_lon must be below max_lat/max_lon".to_string(),
+                    ));
+                }
+                BoundingBox {
+                    min_latitude,
+                    min_longitude,
+                    max_latitude,
+                    max_longitude,
+                }
+            }
+            (None, None, None, None) => {
+                let points: Vec<(f64, f64)> = ambulances
+                    .iter()
+                    .filter_map(|a| position(a.location_latitude, a.location_longitude))
+                    .chain(standbys.iter().map(|(_, at)| *at))
+                    .collect();
+                bounds_around(&points, cell_km).ok_or(CustomError::new(
+                    HttpCodeW::UnprocessableEntity,
+                    "No ambulance or hospital is located to derive the service area from"
+                        .to_string(),
+                ))?
+            }
+            _ => {
+                return Err(CustomError::new(
+                    HttpCodeW::BadRequest,
+                    "min_lat, min_lon, max_lat and max_lon go together".to_string(),
+                ));
+            }
+        };
+        let grid = CoverageGrid::new(bounds, cell_km);
+        if grid.len() > MAX_CELLS {
+            return Err(CustomError::new(
+                HttpCodeW::BadRequest,
+                format!(
+                    "The area needs {} cells, at most {MAX_CELLS} are allowed; use a larger cell_km",
+                    grid.len()
+                ),
+            ));
+        }
+
+        let mut demand = vec![0u32; grid.len()];
+        let recent: Vec<(Decimal, Decimal)> = emergency::Entity::find()
+            .select_only()
+            .columns([
+                emergency::Column::EmergencyLatitude,
+                emergency::Column::EmergencyLongitude,
+            ])
+            .filter(emergency::Column::CreatedAt.gte(now_time() - Duration::days(DEMAND_DAYS)))
+            .filter(bounding_box_condition::<emergency::Entity>(&bounds))
+            .into_tuple()
+            .all(&self.conn)
+            .await?;
+        for (latitude, longitude) in recent {
+            if let Some(cell) =
+                position(latitude, longitude).and_then(|(lat, lon)| grid.cell_of(lat, lon))
+            {
+                demand[cell] += 1;
+            }
+        }
+        // Routing every cell from every vehicle is CPU-bound; keep it off the
+        // async workers
+        tokio::task::spawn_blocking(move || {
+            build_report(
+                grid,
+                demand,
+                available,
+                standbys,
+                target_minutes,
+                cell_km,
+                max_moves,
+            )
+        })
+        .await
+        .map_err(|e| {
+            CustomError::new(
+                HttpCodeW::InternalServerError,
+                format!("Coverage computation failed: {e}"),
+            )
+        })
+    }
+}
+
+/// Drive times from the available units to every cell, and the moves to
+/// standby points that most reduce the demand left over target.
+fn build_report(
+    grid: CoverageGrid,
+    demand: Vec<u32>,
+    available: Vec<(ambulance::Model, (f64, f64))>,
+    standbys: Vec<(hospital::Model, (f64, f64))>,
+    target_minutes: f64,
+    cell_km: f64,
+    max_moves: usize,
+) -> CoverageReport {
+    let weights: Vec<f64> = demand
+        .iter()
+        .map(|&count| BASE_CELL_WEIGHT + f64::from(count))
+        .collect();
+
+    let centres: Vec<(f64, f64)> = (0..grid.len()).map(|cell| grid.centre(cell)).collect();
+    let vehicles: Vec<((f64, f64), AmbulanceTypeEnum)> = available
+        .iter()
+        .map(|(a, at)| (*at, a.r#type.clone()))
+        .collect();
+    let current = estimate_matrix(&centres, &vehicles);
+
+    // Drive times from each standby point, once per vehicle type present
+    let mut types: Vec<AmbulanceTypeEnum> = Vec::new();
+    for (a, _) in &available {
+        if !types.contains(&a.r#type) {
+            types.push(a.r#type.clone());
+        }
+    }
+    let from_standby: Vec<((f64, f64), AmbulanceTypeEnum)> = standbys
+        .iter()
+        .flat_map(|(_, at)| types.iter().map(|t| (*at, t.clone())))
+        .collect();
+    let standby_matrix = estimate_matrix(&centres, &from_standby);
+    let type_of: Vec<usize> = available
+        .iter()
+        .map(|(a, _)| {
+            types
+                .iter()
+                .position(|t| *t == a.r#type)
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let moves = suggest_moves(
+        &weights,
+        available.len(),
+        standbys.len(),
+        |cell, unit| current[cell][unit].eta_minutes(),
+        |cell, unit, standby| {
+            standby_matrix[cell][standby * types.len() + type_of[unit]].eta_minutes()
+        },
+        target_minutes,
+        max_moves,
+    );
+    let standby_points: Vec<(f64, f64)> = standbys.iter().map(|(_, at)| *at).collect();
+    let relocation = estimate_matrix(&standby_points, &vehicles);
+    let suggestions = moves
+        .iter()
+        .map(|planned| {
+            let (unit, _) = &available[planned.unit];
+            let (standby, at) = &standbys[planned.standby];
+            RepositionSuggestion {
+                ambulance_id: unit.id,
+                ambulance_ic: unit.ambulance_ic,
+                standby_hospital_id: standby.id,
+                standby_hospital_name: standby.name.clone(),
+                latitude: at.0,
+                longitude: at.1,
+                travel_seconds: relocation[planned.standby][planned.unit].eta_seconds_rounded(),
+                uncovered_weight_before: planned.uncovered_before,
+                uncovered_weight_after: planned.uncovered_after,
+            }
+        })
+        .collect();
+
+    let cells: Vec<CoverageCell> = (0..grid.len())
+        .map(|cell| {
+            let fastest = current[cell]
+                .iter()
+                .enumerate()
+                .min_by(|a, b| a.1.eta_seconds.total_cmp(&b.1.eta_seconds));
+            let (latitude, longitude) = centres[cell];
+            CoverageCell {
+                row: cell / grid.cols,
+                col: cell % grid.cols,
+                latitude,
+                longitude,
+                demand: demand[cell],
+                eta_seconds: fastest.map(|(_, travel)| travel.eta_seconds_rounded()),
+                nearest_ambulance_id: fastest.map(|(unit, _)| available[unit].0.id),
+                over_target: fastest
+                    .is_none_or(|(_, travel)| travel.eta_minutes() > target_minutes),
+            }
+        })
+        .collect();
+
+    let total_demand: u32 = demand.iter().sum();
+    let covered_demand: u32 = cells
+        .iter()
+        .filter(|cell| !cell.over_target)
+        .map(|cell| cell.demand)
+        .sum();
+    let summary = CoverageSummary {
+        available_ambulances: available.len(),
+        cells: cells.len(),
+        cells_over_target: cells.iter().filter(|cell| cell.over_target).count(),
+        demand_covered_pct: if total_demand == 0 {
+            100.0
+        } else {
+            f64::from(covered_demand) * 100.0 / f64::from(total_demand)
+        },
+    };
+
+    CoverageReport {
+        generated_at: now_time(),
+        target_minutes,
+        cell_km,
+        rows: grid.rows,
+        cols: grid.cols,
+        summary,
+        suggestions,
+        cells,
+    }
+}
@@ -0,0 +1,361 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use sea_orm::prelude::Uuid;
+use sea_orm::*;
+use std::collections::HashMap;
+
+use crate::entity::ambulance_crew::{AmbulanceWithCrew, CrewAssignmentPayload, CrewMember};
+use crate::entity::sea_orm_active_enums::{AmbulanceTypeEnum, CrewRoleEnum, StaffRoleEnum};
+use crate::entity::{ambulance, ambulance_crew, person, staff, staff_schedule};
+use crate::http_response::HttpCodeW;
+use crate::http_response::error_handler::CustomError;
+use crate::utils::helpers::{env_or, now_time};
+
+/// Environment variable turning the minimum-crew check of dispatch on or off.
+pub const REQUIRE_MINIMUM_CREW_ENV: &str = "REQUIRE_MINIMUM_CREW";
+
+/// Whether vehicles without their minimum crew on duty are kept from dispatch.
+/// Off by default: until rosters are entered no vehicle has a crew, and the
+/// check would stop all dispatch.
+pub fn minimum_crew_required() -> bool {
+    env_or(REQUIRE_MINIMUM_CREW_ENV, false)
+}
+
+/// Crew a vehicle type needs on duty before it is sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrewRequirement {
+    pub drivers: usize,
+    pub paramedics: usize,
+    pub physicians: usize,
+}
+
+/// Minimum crew of each vehicle type. Intensive and critical care units
+/// carry a physician; a rapid response or supervisor vehicle is driven by
+/// its paramedic; transport-only vehicles just need a driver.
+pub fn minimum_crew(kind: &AmbulanceTypeEnum) -> CrewRequirement {
+    use AmbulanceTypeEnum::*;
+    let (drivers, paramedics, physicians) = match kind {
+        MobileIntensiveCareUnit | CriticalCareTransport | NeonatalAmbulance | AirAmbulance => {
+            (1, 1, 1)
+        }
+        RapidResponseVehicle | SupervisorVehicle => (0, 1, 0),
+        WheelchairVan | AmbulatoryTransport | UtilityVehicle | CommandVehicle
+        | TrainingAmbulance => (1, 0, 0),
+        _ => (1, 1, 0),
+    };
+    CrewRequirement {
+        drivers,
+        paramedics,
+        physicians,
+    }
+}
+
+/// Whether a staff member of `staff_role` may fill `crew_role`.
+pub fn qualifies(staff_role: &StaffRoleEnum, crew_role: &CrewRoleEnum) -> bool {
+    match crew_role {
+        CrewRoleEnum::Physician => *staff_role == StaffRoleEnum::Doctor,
+        CrewRoleEnum::Paramedic | CrewRoleEnum::Driver => matches!(
+            staff_role,
+            StaffRoleEnum::Doctor | StaffRoleEnum::Nurse | StaffRoleEnum::Technician
+        ),
+    }
+}
+
+/// Seats of the minimum crew of `kind` that `roles` leave empty. A physician
+/// beyond the physician seats also covers a paramedic seat.
+pub fn missing_roles(kind: &AmbulanceTypeEnum, roles: &[CrewRoleEnum]) -> Vec<CrewRoleEnum> {
+    let required = minimum_crew(kind);
+    let count = |role| roles.iter().filter(|r| **r == role).count();
+    let physicians = count(CrewRoleEnum::Physician);
+    let spare_physicians = physicians.saturating_sub(required.physicians);
+    let paramedics = count(CrewRoleEnum::Paramedic) + spare_physicians;
+    let drivers = count(CrewRoleEnum::Driver);
+
+    let mut missing = Vec::new();
+    for (role, needed, present) in [
+        (CrewRoleEnum::Driver, required.drivers, drivers),
+        (CrewRoleEnum::Paramedic, required.paramedics, paramedics),
+        (CrewRoleEnum::Physician, required.physicians, physicians),
+    ] {
+        missing.extend(std::iter::repeat_n(role, needed.saturating_sub(present)));
+    }
+    missing
+}
+
+/// Start and end of a shift. A shift not ending after it starts runs past midnight.
+pub fn shift_window(
+    date: NaiveDate,
+    start: NaiveTime,
+    end: NaiveTime,
+) -> (NaiveDateTime, NaiveDateTime) {
+    let starts_at = date.and_time(start);
+    let mut ends_at = date.and_time(end);
+    if ends_at <= starts_at {
+        ends_at += Duration::days(1);
+    }
+    (starts_at, ends_at)
+}
+
+/// Crew of the ambulances whose shift has not ended at `at`, by ambulance
+/// and in shift order.
+pub async fn load_crews<C: ConnectionTrait>(
+    conn: &C,
+    ambulance_ids: &[Uuid],
+    at: NaiveDateTime,
+) -> Result<HashMap<Uuid, Vec<CrewMember>>, DbErr> {
+    if ambulance_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    // Shifts from yesterday may still be running past midnight
+    let earliest = at.date() - Duration::days(1);
+    let rows = ambulance_crew::Entity::find()
+        .filter(ambulance_crew::Column::AmbulanceId.is_in(ambulance_ids.to_vec()))
+        .find_also_related(staff_schedule::Entity)
+        .filter(staff_schedule::Column::ScheduleDate.gte(earliest))
+        .all(conn)
+        .await?;
+    let staff_ids: Vec<Uuid> = rows.iter().map(|(crew, _)| crew.staff_id).collect();
+    let people: HashMap<Uuid, (staff::Model, Option<person::Model>)> = staff::Entity::find()
+        .filter(staff::Column::Id.is_in(staff_ids))
+        .find_also_related(person::Entity)
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|(member, person)| (member.id, (member, person)))
+        .collect();
+
+    let mut crews: HashMap<Uuid, Vec<CrewMember>> = HashMap::new();
+    for (crew, schedule) in rows {
+        let (Some(schedule), Some((member, person))) = (schedule, people.get(&crew.staff_id))
+        else {
+            continue;
+        };
+        let (shift_start, shift_end) = shift_window(
+            schedule.schedule_date,
+            schedule.start_time,
+            schedule.end_time,
+        );
+        if shift_end <= at {
+            continue;
+        }
+        crews
+            .entry(crew.ambulance_id)
+            .or_default()
+            .push(CrewMember {
+                crew_id: crew.id,
+                staff_id: crew.staff_id,
+                name: person
+                    .as_ref()
+                    .map(|p| format!("{} {}", p.first_name, p.last_name))
+                    .unwrap_or_default(),
+                staff_role: member.role.clone(),
+                role: crew.role,
+                shift_start,
+                shift_end,
+                on_duty: shift_start <= at,
+            });
+    }
+    for members in crews.values_mut() {
+        members.sort_by_key(|member| member.shift_start);
+    }
+    Ok(crews)
+}
+
+/// Seats of the minimum crew an ambulance is missing among the members on duty.
+fn missing_on_duty(
+    vehicle: &ambulance::Model,
+    crew: Option<&Vec<CrewMember>>,
+) -> Vec<CrewRoleEnum> {
+    let on_duty: Vec<CrewRoleEnum> = crew
+        .into_iter()
+        .flatten()
+        .filter(|member| member.on_duty)
+        .map(|member| member.role.clone())
+        .collect();
+    missing_roles(&vehicle.r#type, &on_duty)
+}
+
+/// Keeps the vehicles with their minimum crew on duty at `at`, all of them
+/// when `REQUIRE_MINIMUM_CREW` is off.
+pub async fn retain_crewed<C: ConnectionTrait>(
+    conn: &C,
+    vehicles: Vec<ambulance::Model>,
+    at: NaiveDateTime,
+) -> Result<Vec<ambulance::Model>, DbErr> {
+    if !minimum_crew_required() {
+        return Ok(vehicles);
+    }
+    let ids: Vec<Uuid> = vehicles.iter().map(|vehicle| vehicle.id).collect();
+    let crews = load_crews(conn, &ids, at).await?;
+    Ok(vehicles
+        .into_iter()
+        .filter(|vehicle| missing_on_duty(vehicle, crews.get(&vehicle.id)).is_empty())
+        .collect())
+}
+
+/// Refuses a vehicle without its minimum crew on duty, naming the empty seats.
+pub async fn ensure_crewed<C: ConnectionTrait>(
+    conn: &C,
+    vehicle: &ambulance::Model,
+    at: NaiveDateTime,
+) -> Result<(), CustomError> {
+    if !minimum_crew_required() {
+        return Ok(());
+    }
+    let crews = load_crews(conn, &[vehicle.id], at).await?;
+    let missing = missing_on_duty(vehicle, crews.get(&vehicle.id));
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(CustomError::new(
+        HttpCodeW::UnprocessableEntity,
+        format!(
+            "Ambulance {} has no minimum crew on duty, missing: {missing:?}",
+            vehicle.ambulance_ic
+        ),
+    ))
+}
+
+/// The vehicles with their current and upcoming crew.
+pub async fn with_crew<C: ConnectionTrait>(
+    conn: &C,
+    vehicles: Vec<ambulance::Model>,
+    at: NaiveDateTime,
+) -> Result<Vec<AmbulanceWithCrew>, DbErr> {
+    let ids: Vec<Uuid> = vehicles.iter().map(|vehicle| vehicle.id).collect();
+    let mut crews = load_crews(conn, &ids, at).await?;
+    Ok(vehicles
+        .into_iter()
+        .map(|vehicle| {
+            let crew = crews.remove(&vehicle.id).unwrap_or_default();
+            let missing_crew = missing_on_duty(&vehicle, Some(&crew));
+            AmbulanceWithCrew {
+                ambulance: vehicle,
+                crew,
+                missing_crew,
+            }
+        })
+        .collect())
+}
+
+pub struct CrewService {
+    conn: DatabaseConnection,
+}
+
+impl CrewService {
+    pub fn new(conn: &DatabaseConnection) -> Self {
+        Self { conn: conn.clone() }
+    }
+
+    /// Rosters a staff member on an ambulance for one of their shifts.
+    pub async fn assign(
+        &self,
+        ambulance_id: Uuid,
+        payload: CrewAssignmentPayload,
+        actor: &str,
+    ) -> Result<AmbulanceWithCrew, CustomError> {
+        let vehicle = self.find_ambulance(ambulance_id).await?;
+        let schedule = staff_schedule::Entity::find_by_id(payload.staff_schedule_id)
+            .one(&self.conn)
+            .await?
+            .ok_or(CustomError::new(
+                HttpCodeW::NotFound,
+                "Staff schedule not found".to_string(),
+            ))?;
+        let member = staff::Entity::find_by_id(schedule.staff_id)
+            .one(&self.conn)
+            .await?
+            .ok_or(CustomError::new(
+                HttpCodeW::NotFound,
+                "Staff member not found".to_string(),
+            ))?;
+        if !qualifies(&member.role, &payload.role) {
+            return Err(CustomError::new(
+                HttpCodeW::UnprocessableEntity,
+                format!("A {:?} cannot serve as {:?}", member.role, payload.role),
+            ));
+        }
+        if schedule.hospital_id != vehicle.hospital_id {
+            return Err(CustomError::new(
+                HttpCodeW::UnprocessableEntity,
+                "The shift is at another hospital than the ambulance".to_string(),
+            ));
+        }
+        let now = now_time();
+        let (_, shift_end) = shift_window(
+            schedule.schedule_date,
+            schedule.start_time,
+            schedule.end_time,
+        );
+        if shift_end <= now {
+            return Err(CustomError::new(
+                HttpCodeW::UnprocessableEntity,
+                "The shift has already ended".to_string(),
+            ));
+        }
+        if let Some(taken) = ambulance_crew::Entity::find()
+            .filter(ambulance_crew::Column::StaffScheduleId.eq(schedule.id))
+            .one(&self.conn)
+            .await?
+        {
+            return Err(CustomError::new(
+                HttpCodeW::Conflict,
+                format!(
+                    "The shift is already rostered on ambulance {}",
+                    taken.ambulance_id
+                ),
+            ));
+        }
+
+        ambulance_crew::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            ambulance_id: Set(vehicle.id),
+            staff_id: Set(member.id),
+            staff_schedule_id: Set(schedule.id),
+            role: Set(payload.role),
+            assigned_by: Set(actor.to_string()),
+            created_at: Set(now),
+        }
+        .insert(&self.conn)
+        .await?;
+        self.roster_of(vehicle, now).await
+    }
+
+    /// Takes a crew member off an ambulance.
+    pub async fn remove(&self, crew_id: Uuid) -> Result<AmbulanceWithCrew, CustomError> {
+        let crew = ambulance_crew::Entity::find_by_id(crew_id)
+            .one(&self.conn)
+            .await?
+            .ok_or(CustomError::new(
+                HttpCodeW::NotFound,
+                "Crew assignment not found".to_string(),
+            ))?;
+        let vehicle = self.find_ambulance(crew.ambulance_id).await?;
+        crew.delete(&self.conn).await?;
+        self.roster_of(vehicle, now_time()).await
+    }
+
+    /// An ambulance with its current and upcoming crew.
+    pub async fn roster(&self, ambulance_id: Uuid) -> Result<AmbulanceWithCrew, CustomError> {
+        let vehicle = self.find_ambulance(ambulance_id).await?;
+        self.roster_of(vehicle, now_time()).await
+    }
+
+    async fn roster_of(
+        &self,
+        vehicle: ambulance::Model,
+        at: NaiveDateTime,
+    ) -> Result<AmbulanceWithCrew, CustomError> {
+        let mut rosters = with_crew(&self.conn, vec![vehicle], at).await?;
+        Ok(rosters.remove(0))
+    }
+
+    async fn find_ambulance(&self, ambulance_id: Uuid) -> Result<ambulance::Model, CustomError> {
+        ambulance::Entity::find_by_id(ambulance_id)
+            .one(&self.conn)
+            .await?
+            .ok_or(CustomError::new(
+                HttpCodeW::NotFound,
+                "Ambulance not found".to_string(),
+            ))
+    }
+}
@@ -0,0 +1,482 @@
+use log::error;
+use rust_decimal::prelude::ToPrimitive;
+use sea_orm::prelude::{Decimal, Uuid};
+use sea_orm::*;
+
+use crate::components::ambulance::services::AmbulanceService;
+use crate::components::emergency::EmergencyService;
+use crate::components::emergency::history::{ChangeSource, GEOFENCE_ACTOR};
+use crate::entity::ambulance::{AmbulanceId, AmbulancePayload};
+use crate::entity::ambulance_location::TelemetryPoint;
+use crate::entity::sea_orm_active_enums::{
+    AmbulanceStatusEnum, GeofenceKindEnum, GeofenceOutcomeEnum,
+};
+use crate::entity::{ambulance, geofence_event, hospital};
+use crate::http_response::HttpCodeW;
+use crate::http_response::error_handler::CustomError;
+use crate::utils::helpers::{calculate_distance, env_or, now_time};
+use crate::utils::spatial::BoundingBox;
+
+/// Environment variable choosing what a crossing does: `apply`, `propose` or `off`.
+pub const GEOFENCE_MODE_ENV: &str = "GEOFENCE_MODE";
+/// Environment variable with the radius around an emergency scene, in meters.
+pub const GEOFENCE_SCENE_RADIUS_ENV: &str = "GEOFENCE_SCENE_RADIUS_M";
+/// Environment variable with the radius around a hospital, in meters.
+pub const GEOFENCE_HOSPITAL_RADIUS_ENV: &str = "GEOFENCE_HOSPITAL_RADIUS_M";
+const DEFAULT_SCENE_RADIUS_M: f64 = 150.0;
+const DEFAULT_HOSPITAL_RADIUS_M: f64 = 300.0;
+/// A unit only leaves a fence once this many radii away, so fixes jittering
+/// on the edge do not flap its status.
+pub const EXIT_RADIUS_FACTOR: f64 = 1.5;
+/// Most events `GET /ambulance/{id}/geofence-events` returns.
+const MAX_LISTED_EVENTS: u64 = 100;
+
+/// What a geofence crossing does to the status of the unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GeofenceMode {
+    /// The change is applied and can be reverted by the crew.
+    #[default]
+    Apply,
+    /// The change waits for the crew to accept it.
+    Propose,
+    /// Crossings are ignored.
+    Off,
+}
+
+impl GeofenceMode {
+    /// Reads `GEOFENCE_MODE`, defaulting to `Apply`.
+    pub fn from_env() -> Self {
+        match std::env::var(GEOFENCE_MODE_ENV) {
+            Ok(raw) => raw.parse().unwrap_or_else(|e| {
+                error!("{e}, using {:?}", Self::default());
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+impl std::str::FromStr for GeofenceMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "apply" => Ok(Self::Apply),
+            "propose" => Ok(Self::Propose),
+            "off" => Ok(Self::Off),
+            other => Err(format!("Invalid {GEOFENCE_MODE_ENV} '{other}'")),
+        }
+    }
+}
+
+/// A circle around a scene or hospital.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fence {
+    pub kind: GeofenceKindEnum,
+    pub emergency_id: Option<Uuid>,
+    pub hospital_id: Option<Uuid>,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub radius_m: f64,
+}
+
+/// A fence crossed by a unit and the status it should move to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FenceChange {
+    pub fence: Fence,
+    pub entered: bool,
+    pub distance_m: f64,
+    pub to_status: AmbulanceStatusEnum,
+}
+
+fn distance_m(fence: &Fence, (latitude, longitude): (f64, f64)) -> f64 {
+    calculate_distance(fence.latitude, fence.longitude, latitude, longitude) * 1000.0
+}
+
+/// Whether moving from `previous` to `current` enters (`Some(true)`) or
+/// leaves (`Some(false)`) the fence.
+pub fn crossing(fence: &Fence, previous: (f64, f64), current: (f64, f64)) -> Option<bool> {
+    let (before, after) = (distance_m(fence, previous), distance_m(fence, current));
+    let exit_radius_m = fence.radius_m * EXIT_RADIUS_FACTOR;
+    if before > fence.radius_m && after <= fence.radius_m {
+        Some(true)
+    } else if before <= exit_radius_m && after > exit_radius_m {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Status a unit in `status` moves to when it enters or leaves a fence.
+pub fn status_after_crossing(
+    status: &AmbulanceStatusEnum,
+    kind: &GeofenceKindEnum,
+    entered: bool,
+) -> Option<AmbulanceStatusEnum> {
+    use AmbulanceStatusEnum::*;
+    match (status, kind, entered) {
+        (Dispatched | EnRouteToScene, GeofenceKindEnum::Scene, true) => Some(AtScene),
+        (TransportingPatient | EnRouteToHospital, GeofenceKindEnum::Hospital, true) => {
+            Some(AtHospital)
+        }
+        (AtHospital, GeofenceKindEnum::Hospital, false) => Some(ReturningToBase),
+        _ => None,
+    }
+}
+
+/// First fence whose crossing between `previous` and the fix changes the
+/// status of the unit. Fences smaller than the accuracy of the fix are
+/// skipped, since it cannot tell inside from outside.
+pub fn first_change(
+    status: &AmbulanceStatusEnum,
+    fences: &[Fence],
+    previous: (f64, f64),
+    fix: &TelemetryPoint,
+) -> Option<FenceChange> {
+    let current = (
+        fix.latitude.to_f64().unwrap_or_default(),
+        fix.longitude.to_f64().unwrap_or_default(),
+    );
+    fences
+        .iter()
+        .filter(|fence| {
+            fix.accuracy_m
+                .is_none_or(|accuracy| accuracy <= fence.radius_m)
+        })
+        .find_map(|fence| {
+            let entered = crossing(fence, previous, current)?;
+            let to_status = status_after_crossing(status, &fence.kind, entered)?;
+            Some(FenceChange {
+                fence: fence.clone(),
+                entered,
+                distance_m: distance_m(fence, current),
+                to_status,
+            })
+        })
+}
+
+pub struct GeofenceService {
+    conn: DatabaseConnection,
+}
+
+impl GeofenceService {
+    pub fn new(conn: &DatabaseConnection) -> Self {
+        Self { conn: conn.clone() }
+    }
+
+    /// Checks the move of `before` to the fix against the fences that matter
+    /// for its status and applies or proposes the resulting status change.
+    pub async fn evaluate(
+        &self,
+        before: &ambulance::Model,
+        fix: &TelemetryPoint,
+    ) -> Result<Option<geofence_event::Model>, CustomError> {
+        let mode = GeofenceMode::from_env();
+        if mode == GeofenceMode::Off {
+            return Ok(None);
+        }
+        let previous = (
+            before.location_latitude.to_f64().unwrap_or_default(),
+            before.location_longitude.to_f64().unwrap_or_default(),
+        );
+        let fences = self.fences_for(before, previous, fix).await?;
+        let Some(change) = first_change(&before.status, &fences, previous, fix) else {
+            return Ok(None);
+        };
+
+        // The change and its event are written together, so no automatic
+        // change goes unrecorded
+        let now = now_time();
+        let txn = self.conn.begin().await?;
+        let outcome = match mode {
+            GeofenceMode::Propose => {
+                self.dismiss_open_proposals(&txn, before.id, GEOFENCE_ACTOR)
+                    .await?;
+                GeofenceOutcomeEnum::Proposed
+            }
+            _ => {
+                let payload = AmbulancePayload {
+                    status: Some(change.to_status.clone()),
+                    ..Default::default()
+                };
+                AmbulanceService::new(&self.conn)
+                    .update_ambulance_in(
+                        &txn,
+                        AmbulanceId::Uuid(before.id),
+                        payload,
+                        GEOFENCE_ACTOR,
+                        ChangeSource::Geofence,
+                    )
+                    .await?;
+                GeofenceOutcomeEnum::Applied
+            }
+        };
+        let event = geofence_event::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            ambulance_id: Set(before.id),
+            emergency_id: Set(change.fence.emergency_id),
+            hospital_id: Set(change.fence.hospital_id),
+            fence: Set(change.fence.kind),
+            entered: Set(change.entered),
+            from_status: Set(before.status.clone()),
+            to_status: Set(change.to_status),
+            outcome: Set(outcome),
+            latitude: Set(fix.latitude),
+            longitude: Set(fix.longitude),
+            distance_m: Set(change.distance_m),
+            recorded_at: Set(fix.recorded_at),
+            created_at: Set(now),
+            resolved_at: Set(None),
+            resolved_by: Set(None),
+        }
+        .insert(&txn)
+        .await?;
+        txn.commit().await?;
+        Ok(Some(event))
+    }
+
+    /// The scene of the unit's emergency while it heads there, and the
+    /// hospitals around it while it transports or unloads.
+    async fn fences_for(
+        &self,
+        ambulance: &ambulance::Model,
+        previous: (f64, f64),
+        fix: &TelemetryPoint,
+    ) -> Result<Vec<Fence>, CustomError> {
+        use AmbulanceStatusEnum::*;
+        match ambulance.status {
+            Dispatched | EnRouteToScene => {
+                let emergency = EmergencyService::new(&self.conn)
+                    .find_active_for_unit(ambulance.id)
+                    .await?;
+                Ok(emergency
+                    .map(|emergency| Fence {
+                        kind: GeofenceKindEnum::Scene,
+                        emergency_id: Some(emergency.id),
+                        hospital_id: None,
+                        latitude: emergency.emergency_latitude.to_f64().unwrap_or_default(),
+                        longitude: emergency.emergency_longitude.to_f64().unwrap_or_default(),
+                        radius_m: env_or(GEOFENCE_SCENE_RADIUS_ENV, DEFAULT_SCENE_RADIUS_M),
+                    })
+                    .into_iter()
+                    .collect())
+            }
+            TransportingPatient | EnRouteToHospital | AtHospital => {
+                let radius_m = env_or(GEOFENCE_HOSPITAL_RADIUS_ENV, DEFAULT_HOSPITAL_RADIUS_M);
+                let current = (
+                    fix.latitude.to_f64().unwrap_or_default(),
+                    fix.longitude.to_f64().unwrap_or_default(),
+                );
+                // Any hospital the hop could have entered or left
+                let reach_km = calculate_distance(previous.0, previous.1, current.0, current.1)
+                    + radius_m * EXIT_RADIUS_FACTOR / 1000.0;
+                let bbox = BoundingBox::around(current.0, current.1, reach_km);
+                let hospitals = hospital::Entity::find()
+                    .filter(hospital::Column::Latitude.between(
+                        Decimal::from_f64_retain(bbox.min_latitude).unwrap_or_default(),
+                        Decimal::from_f64_retain(bbox.max_latitude).unwrap_or_default(),
+                    ))
+                    .filter(hospital::Column::Longitude.between(
+                        Decimal::from_f64_retain(bbox.min_longitude).unwrap_or_default(),
+                        Decimal::from_f64_retain(bbox.max_longitude).unwrap_or_default(),
+                    ))
+                    .all(&self.conn)
+                    .await?;
+                Ok(hospitals
+                    .into_iter()
+                    .filter_map(|hospital| {
+                        Some(Fence {
+                            kind: GeofenceKindEnum::Hospital,
+                            emergency_id: None,
+                            hospital_id: Some(hospital.id),
+                            latitude: hospital.latitude?.to_f64()?,
+                            longitude: hospital.longitude?.to_f64()?,
+                            radius_m,
+                        })
+                    })
+                    .collect())
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Open proposals are stale once the unit crosses another fence.
+    async fn dismiss_open_proposals<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        ambulance_id: Uuid,
+        actor: &str,
+    ) -> Result<(), DbErr> {
+        geofence_event::Entity::update_many()
+            .col_expr(
+                geofence_event::Column::Outcome,
+                GeofenceOutcomeEnum::Dismissed.as_enum(),
+            )
+            .col_expr(
+                geofence_event::Column::ResolvedAt,
+                sea_query::Expr::value(now_time()),
+            )
+            .col_expr(
+                geofence_event::Column::ResolvedBy,
+                sea_query::Expr::value(actor),
+            )
+            .filter(geofence_event::Column::AmbulanceId.eq(ambulance_id))
+            .filter(geofence_event::Column::Outcome.eq(GeofenceOutcomeEnum::Proposed))
+            .exec(conn)
+            .await?;
+        Ok(())
+    }
+
+    /// Latest geofence events of an ambulance, newest first.
+    pub async fn find_for_ambulance(
+        &self,
+        ambulance_id: Uuid,
+    ) -> Result<Vec<geofence_event::Model>, CustomError> {
+        let events = geofence_event::Entity::find()
+            .filter(geofence_event::Column::AmbulanceId.eq(ambulance_id))
+            .order_by_desc(geofence_event::Column::CreatedAt)
+            .limit(MAX_LISTED_EVENTS)
+            .all(&self.conn)
+            .await?;
+        Ok(events)
+    }
+
+    /// Applies a proposed change through the usual status rules.
+    pub async fn accept(
+        &self,
+        event_id: Uuid,
+        actor: &str,
+    ) -> Result<geofence_event::Model, CustomError> {
+        let event = self.find_event(event_id).await?;
+        if event.outcome != GeofenceOutcomeEnum::Proposed {
+            return Err(CustomError::new(
+                HttpCodeW::Conflict,
+                format!("Geofence event is already {:?}", event.outcome),
+            ));
+        }
+        let txn = self.conn.begin().await?;
+        let unit = self.find_unit(&txn, &event).await?;
+        ensure_unchanged(&unit, &event.from_status)?;
+        let payload = AmbulancePayload {
+            status: Some(event.to_status.clone()),
+            ..Default::default()
+        };
+        AmbulanceService::new(&self.conn)
+            .update_ambulance_in(
+                &txn,
+                AmbulanceId::Uuid(event.ambulance_id),
+                payload,
+                actor,
+                ChangeSource::Geofence,
+            )
+            .await?;
+        let event = self
+            .resolve(&txn, event, GeofenceOutcomeEnum::Accepted, actor)
+            .await?;
+        txn.commit().await?;
+        Ok(event)
+    }
+
+    /// Turns down a proposal, or puts the unit back in the status it had
+    /// before an applied change. A revert undoes a wrong automatic change, so
+    /// it bypasses the transition rules; the emergency steps back and loses
+    /// the milestone the change gave it, both recorded in its timeline.
+    pub async fn reject(
+        &self,
+        event_id: Uuid,
+        actor: &str,
+    ) -> Result<geofence_event::Model, CustomError> {
+        let event = self.find_event(event_id).await?;
+        match event.outcome {
+            GeofenceOutcomeEnum::Proposed => {
+                self.resolve(&self.conn, event, GeofenceOutcomeEnum::Dismissed, actor)
+                    .await
+            }
+            GeofenceOutcomeEnum::Applied => {
+                let txn = self.conn.begin().await?;
+                let unit = self.find_unit(&txn, &event).await?;
+                ensure_unchanged(&unit, &event.to_status)?;
+                let mut active_model: ambulance::ActiveModel = unit.into();
+                active_model.status = Set(event.from_status.clone());
+                active_model.updated_at = Set(now_time());
+                let reverted = active_model.update(&txn).await?;
+                EmergencyService::new(&self.conn)
+                    .revert_unit_progress(
+                        &txn,
+                        &reverted,
+                        &event.to_status,
+                        event.created_at,
+                        actor,
+                        ChangeSource::Geofence,
+                    )
+                    .await?;
+                let event = self
+                    .resolve(&txn, event, GeofenceOutcomeEnum::Reverted, actor)
+                    .await?;
+                txn.commit().await?;
+                Ok(event)
+            }
+            _ => Err(CustomError::new(
+                HttpCodeW::Conflict,
+                format!("Geofence event is already {:?}", event.outcome),
+            )),
+        }
+    }
+
+    async fn find_event(&self, event_id: Uuid) -> Result<geofence_event::Model, CustomError> {
+        geofence_event::Entity::find_by_id(event_id)
+            .one(&self.conn)
+            .await?
+            .ok_or(CustomError::new(
+                HttpCodeW::NotFound,
+                "Geofence event not found".to_string(),
+            ))
+    }
+
+    /// The unit of an event, locked for the rest of the transaction.
+    async fn find_unit<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        event: &geofence_event::Model,
+    ) -> Result<ambulance::Model, CustomError> {
+        ambulance::Entity::find_by_id(event.ambulance_id)
+            .lock_exclusive()
+            .one(conn)
+            .await?
+            .ok_or(CustomError::new(
+                HttpCodeW::NotFound,
+                "Ambulance not found".to_string(),
+            ))
+    }
+
+    async fn resolve<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        event: geofence_event::Model,
+        outcome: GeofenceOutcomeEnum,
+        actor: &str,
+    ) -> Result<geofence_event::Model, CustomError> {
+        let mut active_model: geofence_event::ActiveModel = event.into();
+        active_model.outcome = Set(outcome);
+        active_model.resolved_at = Set(Some(now_time()));
+        active_model.resolved_by = Set(Some(actor.to_string()));
+        Ok(active_model.update(conn).await?)
+    }
+}
+
+/// A crew answering an old event must not overwrite a status set since.
+fn ensure_unchanged(
+    unit: &ambulance::Model,
+    expected: &AmbulanceStatusEnum,
+) -> Result<(), CustomError> {
+    if unit.status == *expected {
+        return Ok(());
+    }
+    Err(CustomError::new(
+        HttpCodeW::Conflict,
+        format!(
+            "Ambulance is {:?} now, not {expected:?}; set its status instead",
+            unit.status
+        ),
+    ))
+}
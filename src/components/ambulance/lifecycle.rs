@@ -0,0 +1,213 @@
+use crate::components::emergency::lifecycle::allowed_transitions as emergency_transitions;
+use crate::entity::sea_orm_active_enums::{AmbulanceStatusEnum, EmergencyStatusEnum};
+use crate::http_response::HttpCodeW;
+use crate::http_response::error_handler::CustomError;
+use AmbulanceStatusEnum::*;
+use std::collections::{HashMap, VecDeque};
+
+/// Returns the statuses a crew may move its ambulance to from `from`.
+///
+/// A mission runs Dispatched → EnRouteToScene → AtScene → TransportingPatient /
+/// EnRouteToHospital → AtHospital → ReturningToBase → Available. Off-road
+/// states (Maintenance, UnderRepair, OutOfService, Unavailable) only lead
+/// back into service through each other or `Available`.
+pub fn allowed_transitions(from: &AmbulanceStatusEnum) -> &'static [AmbulanceStatusEnum] {
+    match from {
+        Available => &[
+            Dispatched,
+            AwaitingDispatch,
+            PreparingForMission,
+            InService,
+            OnBreak,
+            Fueling,
+            Cleaning,
+            Maintenance,
+            Unavailable,
+            OutOfService,
+        ],
+        InService => &[
+            Available,
+            Dispatched,
+            AwaitingDispatch,
+            OnBreak,
+            Fueling,
+            Cleaning,
+            Maintenance,
+            Unavailable,
+        ],
+        AwaitingDispatch => &[
+            Available,
+            Dispatched,
+            PreparingForMission,
+            OnBreak,
+            Unavailable,
+        ],
+        PreparingForMission => &[Available, Dispatched, AwaitingDispatch, Unavailable],
+        Dispatched => &[
+            EnRouteToScene,
+            AtScene,
+            ReturningToBase,
+            Available,
+            Unavailable,
+        ],
+        EnRouteToScene => &[AtScene, ReturningToBase, Available, Unavailable],
+        AtScene => &[
+            TransportingPatient,
+            EnRouteToHospital,
+            ReturningToBase,
+            Available,
+        ],
+        TransportingPatient => &[EnRouteToHospital, AtHospital, Unavailable],
+        EnRouteToHospital => &[TransportingPatient, AtHospital, Unavailable],
+        AtHospital => &[
+            ReturningToBase,
+            Available,
+            AwaitingDispatch,
+            Cleaning,
+            Fueling,
+        ],
+        ReturningToBase => &[
+            Available,
+            InService,
+            Dispatched,
+            AwaitingDispatch,
+            Fueling,
+            Cleaning,
+            Maintenance,
+            Unavailable,
+        ],
+        OnBreak => &[Available, InService, AwaitingDispatch, Unavailable],
+        Fueling => &[Available, InService, AwaitingDispatch, ReturningToBase],
+        Cleaning => &[
+            Available,
+            InService,
+            AwaitingDispatch,
+            ReturningToBase,
+            Fueling,
+        ],
+        Maintenance => &[Available, UnderRepair, OutOfService],
+        UnderRepair => &[Maintenance, Available, OutOfService],
+        Unavailable => &[Available, InService, Maintenance, UnderRepair, OutOfService],
+        OutOfService => &[Available, InService, Maintenance, UnderRepair],
+    }
+}
+
+/// Validates a status change reported for an ambulance, returning a 422 with
+/// the legal next states when the jump is not allowed. Keeping the current
+/// status is always allowed.
+pub fn ensure_transition(
+    from: &AmbulanceStatusEnum,
+    to: &AmbulanceStatusEnum,
+) -> Result<(), CustomError> {
+    let allowed = allowed_transitions(from);
+    if from == to || allowed.contains(to) {
+        return Ok(());
+    }
+    let next = allowed
+        .iter()
+        .map(|s| format!("{s:?}"))
+        .collect::<Vec<String>>()
+        .join(", ");
+    Err(CustomError::new(
+        HttpCodeW::UnprocessableEntity,
+        format!("Illegal ambulance status transition from {from:?} to {to:?}. Allowed: {next}"),
+    ))
+}
+
+/// Whether the vehicle is off the road for repair, maintenance or otherwise.
+pub fn is_off_road(status: &AmbulanceStatusEnum) -> bool {
+    matches!(
+        status,
+        Maintenance | UnderRepair | OutOfService | Unavailable
+    )
+}
+
+/// Whether a unit in `status` is working an emergency, from dispatch until
+/// the hand-over at the hospital.
+pub fn is_on_mission(status: &AmbulanceStatusEnum) -> bool {
+    matches!(
+        status,
+        Dispatched
+            | EnRouteToScene
+            | AtScene
+            | TransportingPatient
+            | EnRouteToHospital
+            | AtHospital
+    )
+}
+
+/// Whether a dispatcher may take a unit in `status` off its case for another
+/// one: only while it has not reached the scene and has no patient on board.
+pub fn can_be_reassigned(status: &AmbulanceStatusEnum) -> bool {
+    matches!(status, Dispatched | EnRouteToScene)
+}
+
+/// Status the emergency of a lead unit reporting `status` should have reached.
+pub fn emergency_status_for(status: &AmbulanceStatusEnum) -> Option<EmergencyStatusEnum> {
+    let status = match status {
+        Dispatched | EnRouteToScene => EmergencyStatusEnum::InProgress,
+        AtScene => EmergencyStatusEnum::AtScene,
+        TransportingPatient | EnRouteToHospital => EmergencyStatusEnum::InTransitToHospital,
+        AtHospital => EmergencyStatusEnum::ArrivedAtHospital,
+        _ => return None,
+    };
+    Some(status)
+}
+
+/// Status the emergency of a lead unit steps back to when the unit's change
+/// from `from` to `to` is reverted. `None` when the case is not where that
+/// change put it, e.g. a dispatcher moved it on since.
+pub fn reverted_emergency_status(
+    current: &EmergencyStatusEnum,
+    from: &AmbulanceStatusEnum,
+    to: &AmbulanceStatusEnum,
+) -> Option<EmergencyStatusEnum> {
+    let reached = emergency_status_for(to)?;
+    let before = emergency_status_for(from)?;
+    (*current == reached && before != reached).then_some(before)
+}
+
+/// Shortest chain of legal emergency transitions from `from` to `to`, not
+/// counting `from`. Empty when already there, `None` when the emergency is
+/// already past `to`.
+pub fn emergency_sync_path(
+    from: &EmergencyStatusEnum,
+    to: &EmergencyStatusEnum,
+) -> Option<Vec<EmergencyStatusEnum>> {
+    if from == to {
+        return Some(Vec::new());
+    }
+    let mut previous: HashMap<EmergencyStatusEnum, EmergencyStatusEnum> = HashMap::new();
+    let mut queue = VecDeque::from([from.clone()]);
+    while let Some(status) = queue.pop_front() {
+        for next in emergency_transitions(&status) {
+            if next == from || previous.contains_key(next) {
+                continue;
+            }
+            previous.insert(next.clone(), status.clone());
+            if next == to {
+                let mut path = vec![to.clone()];
+                let mut step = status;
+                while step != *from {
+                    let before = previous[&step].clone();
+                    path.push(step);
+                    step = before;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            // Only go forward along the mission; never through a hold,
+            // escalation or closure
+            if matches!(
+                next,
+                EmergencyStatusEnum::InProgress
+                    | EmergencyStatusEnum::AtScene
+                    | EmergencyStatusEnum::InAmbulance
+                    | EmergencyStatusEnum::InTransitToHospital
+            ) {
+                queue.push_back(next.clone());
+            }
+        }
+    }
+    None
+}
@@ -1,4 +1,12 @@
+pub(crate) mod compliance;
+pub(crate) mod coverage;
+pub(crate) mod crew;
+pub(crate) mod geofence;
+pub(crate) mod lifecycle;
 pub mod routes;
 pub(crate) mod services;
+pub(crate) mod telemetry;
 
+pub use compliance::start_compliance_job;
 pub use routes::*;
+pub use telemetry::start_location_retention_job;
@@ -1,9 +1,19 @@
+use crate::components::ambulance::coverage::CoverageService;
+use crate::components::ambulance::crew::CrewService;
+use crate::components::ambulance::geofence::GeofenceService;
 use crate::components::ambulance::services::AmbulanceService;
-use crate::entity::ambulance::{AmbulanceId, AmbulancePayload};
+use crate::components::ambulance::telemetry::TelemetryService;
+use crate::components::emergency::history::ChangeSource;
+use crate::entity::ambulance::{
+    AmbulanceId, AmbulancePayload, AmbulanceStatusQuery, CoverageQuery, NearbyAmbulanceQuery,
+};
+use crate::entity::ambulance_crew::CrewAssignmentPayload;
+use crate::entity::ambulance_location::{TelemetryBatch, TrackQuery};
 use crate::http_response::error_handler::CustomError;
 use crate::http_response::http_response_builder;
+use crate::security::subject::Subject;
 use crate::shared::PaginationParams;
-use actix_web::{HttpResponse, get, patch, post, web};
+use actix_web::{HttpResponse, delete, get, patch, post, web};
 use sea_orm::DatabaseConnection;
 use uuid::Uuid;
 
@@ -24,6 +34,7 @@ async fn create(
 async fn update(
     uuid_ambulance: web::Path<Uuid>,
     ambulance_payload: web::Json<AmbulancePayload>,
+    subject: Subject,
     db_conn: web::Data<DatabaseConnection>,
 ) -> Result<HttpResponse, CustomError> {
     let service = AmbulanceService::new(db_conn.get_ref());
@@ -31,6 +42,8 @@ async fn update(
         .update_ambulance(
             AmbulanceId::Uuid(uuid_ambulance.into_inner()),
             ambulance_payload.clone(),
+            &subject.sub,
+            ChangeSource::Api,
         )
         .await?;
     let response = http_response_builder::ok(ambulance);
@@ -40,6 +53,7 @@ async fn update(
 async fn update_by_ic(
     id_ambulance: web::Path<i32>,
     ambulance_payload: web::Json<AmbulancePayload>,
+    subject: Subject,
     db_conn: web::Data<DatabaseConnection>,
 ) -> Result<HttpResponse, CustomError> {
     let service = AmbulanceService::new(db_conn.get_ref());
@@ -47,6 +61,8 @@ async fn update_by_ic(
         .update_ambulance(
             AmbulanceId::Integer(id_ambulance.into_inner()),
             ambulance_payload.clone(),
+            &subject.sub,
+            ChangeSource::Api,
         )
         .await?;
     let response = http_response_builder::ok(ambulance);
@@ -72,18 +88,177 @@ pub async fn find_all(
 
 #[get("/ambulance/status")]
 pub async fn find_all_statuses(
+    query: web::Query<AmbulanceStatusQuery>,
     db_conn: web::Data<DatabaseConnection>,
 ) -> Result<HttpResponse, CustomError> {
     let service_instance = AmbulanceService::new(db_conn.get_ref());
-    let ambulance = service_instance.find_all_status().await?;
+    let ambulance = service_instance
+        .find_all_status(query.into_inner().from)
+        .await?;
     let response = http_response_builder::ok(ambulance);
     Ok(HttpResponse::Ok().json(response))
 }
 
+#[get("/ambulance/nearby")]
+pub async fn find_nearby(
+    query: web::Query<NearbyAmbulanceQuery>,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service_instance = AmbulanceService::new(db_conn.get_ref());
+    let ambulances = service_instance.find_nearby(query.into_inner()).await?;
+    let response = http_response_builder::ok(ambulances);
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[get("/ambulance/coverage")]
+pub async fn find_coverage(
+    query: web::Query<CoverageQuery>,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service_instance = CoverageService::new(db_conn.get_ref());
+    let report = service_instance.report(query.into_inner()).await?;
+    let response = http_response_builder::ok(report);
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[get("/ambulance/{uuid_ambulance}/destinations")]
+pub async fn find_destinations(
+    uuid_ambulance: web::Path<Uuid>,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service_instance = AmbulanceService::new(db_conn.get_ref());
+    let recommendation = service_instance
+        .recommend_destinations(uuid_ambulance.into_inner())
+        .await?;
+    let response = http_response_builder::ok(recommendation);
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[post("/ambulance/{uuid_ambulance}/telemetry")]
+pub async fn ingest_telemetry(
+    uuid_ambulance: web::Path<Uuid>,
+    batch: web::Json<TelemetryBatch>,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service_instance = TelemetryService::new(db_conn.get_ref());
+    let receipt = service_instance
+        .ingest(uuid_ambulance.into_inner(), batch.into_inner())
+        .await?;
+    let response = http_response_builder::ok(receipt);
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[get("/ambulance/{uuid_ambulance}/track")]
+pub async fn find_track(
+    uuid_ambulance: web::Path<Uuid>,
+    query: web::Query<TrackQuery>,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service_instance = TelemetryService::new(db_conn.get_ref());
+    let track = service_instance
+        .track(uuid_ambulance.into_inner(), query.into_inner())
+        .await?;
+    let response = http_response_builder::ok(track);
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[get("/ambulance/{uuid_ambulance}/geofence-events")]
+pub async fn find_geofence_events(
+    uuid_ambulance: web::Path<Uuid>,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service_instance = GeofenceService::new(db_conn.get_ref());
+    let events = service_instance
+        .find_for_ambulance(uuid_ambulance.into_inner())
+        .await?;
+    let response = http_response_builder::ok(events);
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[post("/ambulance/geofence-events/{event_id}/accept")]
+pub async fn accept_geofence_event(
+    event_id: web::Path<Uuid>,
+    subject: Subject,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service_instance = GeofenceService::new(db_conn.get_ref());
+    let event = service_instance
+        .accept(event_id.into_inner(), &subject.sub)
+        .await?;
+    let response = http_response_builder::ok(event);
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[post("/ambulance/geofence-events/{event_id}/reject")]
+pub async fn reject_geofence_event(
+    event_id: web::Path<Uuid>,
+    subject: Subject,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service_instance = GeofenceService::new(db_conn.get_ref());
+    let event = service_instance
+        .reject(event_id.into_inner(), &subject.sub)
+        .await?;
+    let response = http_response_builder::ok(event);
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[post("/ambulance/{uuid_ambulance}/crew")]
+pub async fn assign_crew(
+    uuid_ambulance: web::Path<Uuid>,
+    payload: web::Json<CrewAssignmentPayload>,
+    subject: Subject,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service_instance = CrewService::new(db_conn.get_ref());
+    let roster = service_instance
+        .assign(
+            uuid_ambulance.into_inner(),
+            payload.into_inner(),
+            &subject.sub,
+        )
+        .await?;
+    let response = http_response_builder::ok(roster);
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[get("/ambulance/{uuid_ambulance}/crew")]
+pub async fn find_crew(
+    uuid_ambulance: web::Path<Uuid>,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service_instance = CrewService::new(db_conn.get_ref());
+    let roster = service_instance.roster(uuid_ambulance.into_inner()).await?;
+    let response = http_response_builder::ok(roster);
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[delete("/ambulance/crew/{crew_id}")]
+pub async fn remove_crew(
+    crew_id: web::Path<Uuid>,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service_instance = CrewService::new(db_conn.get_ref());
+    let roster = service_instance.remove(crew_id.into_inner()).await?;
+    let response = http_response_builder::ok(roster);
+    Ok(HttpResponse::Ok().json(response))
+}
+
 pub fn init_routes(config: &mut web::ServiceConfig) {
     config.service(update);
     config.service(find_all);
     config.service(create);
     config.service(update_by_ic);
     config.service(find_all_statuses);
+    config.service(find_nearby);
+    config.service(find_destinations);
+    config.service(find_coverage);
+    config.service(ingest_telemetry);
+    config.service(find_track);
+    config.service(find_geofence_events);
+    config.service(accept_geofence_event);
+    config.service(reject_geofence_event);
+    config.service(assign_crew);
+    config.service(find_crew);
+    config.service(remove_crew);
 }
@@ -1,35 +1,55 @@
 use crate::entity::ambulance::Column::Id;
 use crate::entity::ambulance::{ActiveModel, AmbulanceId, Model, StatusDto};
 use crate::entity::ambulance::{AmbulancePayload, Column, Entity};
+use crate::entity::ambulance::{NearbyAmbulance, NearbyAmbulanceQuery};
+use crate::entity::ambulance_crew::AmbulanceWithCrew;
 use crate::entity::sea_orm_active_enums::{
     AmbulanceCarDetailsMakeEnum, AmbulanceCarDetailsModelEnum, AmbulanceStatusEnum,
     AmbulanceTypeEnum,
 };
 
-use crate::entity::hospital;
+use crate::entity::{emergency, hospital};
 use crate::http_response::error_handler::CustomError;
 use crate::shared::{PaginatedResponse, PaginationInfo};
 use crate::utils::helpers::{check_if_is_duplicate_key_from_data_base, generate_ic, now_time};
+use crate::utils::spatial::find_within_radius;
 
+use crate::components::ambulance::compliance::CompliancePolicy;
+use crate::components::ambulance::crew;
+use crate::components::ambulance::lifecycle::{
+    allowed_transitions, ensure_transition, is_off_road, is_on_mission,
+};
 use crate::components::emergency::EmergencyService;
+use crate::components::emergency::history::ChangeSource;
+use crate::components::emergency::wake_allocator;
 use crate::components::hospital::HospitalService;
+use crate::components::hospital::destination::{DestinationRecommendation, DestinationService};
+use crate::components::hospital::diversion::active_diversions;
 use crate::components::patient::PatientService;
 use crate::http_response::HttpCodeW;
 use Column::AmbulanceIc;
 use hospital::Column::Name as HospitalName;
 use hospital::Entity as HospitalEntity;
 use percent_encoding::percent_decode_str;
+use rust_decimal::prelude::ToPrimitive;
+use sea_orm::TransactionTrait;
 use sea_orm::prelude::Decimal;
 use sea_orm::prelude::Uuid;
 use sea_orm::{ActiveModelTrait, ColumnTrait, Iterable, PaginatorTrait};
-use sea_orm::{DatabaseConnection, EntityTrait};
-use sea_orm::{NotSet, QueryFilter, Set};
+use sea_orm::{DatabaseConnection, DatabaseTransaction, EntityTrait, QuerySelect};
+use sea_orm::{NotSet, QueryFilter, Set, TryIntoModel};
+
+/// Radius of `GET /ambulance/nearby` when none is given, in km.
+const DEFAULT_NEARBY_RADIUS_KM: f64 = 10.0;
+/// Largest radius `GET /ambulance/nearby` accepts, in km.
+const MAX_NEARBY_RADIUS_KM: f64 = 500.0;
 
 pub struct AmbulanceService {
     conn: DatabaseConnection,
     emergency_service: EmergencyService,
     patient_service: PatientService,
     hospital_service: HospitalService,
+    destination_service: DestinationService,
 }
 
 impl AmbulanceService {
@@ -39,13 +59,41 @@ impl AmbulanceService {
             emergency_service: EmergencyService::new(conn),
             patient_service: PatientService::new(conn),
             hospital_service: HospitalService::new(conn),
+            destination_service: DestinationService::new(conn),
         }
     }
     pub(crate) async fn update_ambulance(
         &self,
         id: AmbulanceId,
         payload: AmbulancePayload,
+        actor: &str,
+        source: ChangeSource,
     ) -> Result<Model, CustomError> {
+        let txn = self.conn.begin().await?;
+        let (updated, needs_units) = self
+            .update_ambulance_in(&txn, id, payload, actor, source)
+            .await?;
+        txn.commit().await?;
+        if updated.status == AmbulanceStatusEnum::Available || needs_units {
+            wake_allocator();
+        }
+        Ok(updated)
+    }
+
+    /// Applies an ambulance update within `txn`. The unit, the destination of
+    /// its emergency and the progress its new status marks on that emergency
+    /// are written together, so the caller commits all of them or none.
+    ///
+    /// A unit leaving its mission is taken off its emergency in the same
+    /// transaction; the flag returned says that case now needs more units.
+    pub(crate) async fn update_ambulance_in(
+        &self,
+        txn: &DatabaseTransaction,
+        id: AmbulanceId,
+        payload: AmbulancePayload,
+        actor: &str,
+        source: ChangeSource,
+    ) -> Result<(Model, bool), CustomError> {
         let now = now_time();
         // Initialize a base query outside the match if there are common parts
         let base_query = Entity::find();
@@ -54,7 +102,8 @@ impl AmbulanceService {
             AmbulanceId::Uuid(value) => {
                 base_query
                     .filter(Id.eq(value)) // Assuming `Id` is the Uuid column
-                    .one(&self.conn) // Use self.db_pool if that's your connection
+                    .lock_exclusive()
+                    .one(txn)
                     .await
                     .map_err(|e| {
                         CustomError::new(
@@ -66,7 +115,8 @@ impl AmbulanceService {
             AmbulanceId::Integer(value) => {
                 base_query
                     .filter(AmbulanceIc.eq(value)) // Assuming `AmbulanceIc` is the i32 column
-                    .one(&self.conn) // Use self.db_pool if that's your connection
+                    .lock_exclusive()
+                    .one(txn)
                     .await
                     .map_err(|e| {
                         CustomError::new(
@@ -87,30 +137,27 @@ impl AmbulanceService {
             }
         };
 
+        if let Some(status) = &payload.status {
+            ensure_transition(&model.status, status)?;
+        }
+        let status_changed = payload.status.as_ref().is_some_and(|s| *s != model.status);
+        let returns_to_service = status_changed
+            && is_off_road(&model.status)
+            && payload.status.as_ref().is_some_and(|s| !is_off_road(s));
+        let previous_status = model.status.clone();
+        let destination = match payload.status {
+            Some(AmbulanceStatusEnum::TransportingPatient) => {
+                Some(self.choose_destination(&model, &payload).await?)
+            }
+            _ => None,
+        };
         let mut active_model: ActiveModel = model.into();
 
         match payload.status {
             Some(AmbulanceStatusEnum::TransportingPatient) => {
-                if payload.hospital_name.eq(&None) {
-                    return Err(CustomError::new(
-                        HttpCodeW::BadRequest,
-                        "hospital_name is required for transporting patient".to_string(),
-                    ));
+                if let Some(choice) = &destination {
+                    active_model.hospital_id = Set(choice.hospital_id);
                 }
-                let hospital = self
-                    .hospital_service
-                    .find_by_field("name", payload.hospital_name.unwrap().as_str())
-                    .await?;
-                let hospital_uid: Uuid = match hospital {
-                    None => {
-                        return Err(CustomError::new(
-                            HttpCodeW::BadRequest,
-                            "Invalid Hospital Name".to_string(),
-                        ));
-                    }
-                    Some(value) => value.id,
-                };
-                active_model.hospital_id = Set(hospital_uid);
                 self.set_transport_patient(id, &mut active_model).await?;
             }
             Some(status) => {
@@ -121,17 +168,187 @@ impl AmbulanceService {
         if payload.driver_name.is_some() {
             active_model.driver_name = Set(payload.driver_name);
         }
+        if let Some(latitude) = payload.location_latitude {
+            active_model.location_latitude = Set(latitude);
+        }
+        if let Some(longitude) = payload.location_longitude {
+            active_model.location_longitude = Set(longitude);
+        }
+        if payload.location_latitude.is_some() || payload.location_longitude.is_some() {
+            active_model.location_updated_at = Set(Some(now));
+        }
+        if payload.last_service_date.is_some() {
+            active_model.last_service_date = Set(payload.last_service_date);
+        }
+        if payload.next_service_date.is_some() {
+            active_model.next_service_date = Set(payload.next_service_date);
+        }
+        if payload.mileage.is_some() {
+            active_model.mileage = Set(payload.mileage);
+        }
+        if payload.last_service_mileage.is_some() {
+            active_model.last_service_mileage = Set(payload.last_service_mileage);
+        }
+        if payload.insurance_expiry_date.is_some() {
+            active_model.insurance_expiry_date = Set(payload.insurance_expiry_date);
+        }
+        if payload.registration_number.is_some() {
+            active_model.registration_number = Set(payload.registration_number);
+        }
+        // A vehicle only leaves the workshop once its service and insurance are in order
+        if returns_to_service {
+            let vehicle = active_model.clone().try_into_model()?;
+            CompliancePolicy::from_env().ensure_roadworthy(&vehicle, now)?;
+        }
 
         active_model.updated_at = Set(now);
         // Save changes
-        let updated = active_model.update(&self.conn).await.map_err(|e| {
+        let updated = active_model.update(txn).await.map_err(|e| {
             CustomError::new(
                 HttpCodeW::InternalServerError,
                 format!("Database error: {e}"),
             )
         })?;
+        if let Some(DestinationChoice {
+            hospital_id,
+            emergency: Some(emergency),
+            reason,
+        }) = destination
+        {
+            self.emergency_service
+                .set_destination(txn, &emergency, hospital_id, actor, reason)
+                .await?;
+        }
+        let mut needs_units = false;
+        if status_changed && is_on_mission(&previous_status) && !is_on_mission(&updated.status) {
+            needs_units = self
+                .emergency_service
+                .stand_down_unit(txn, &updated, &previous_status, actor, source)
+                .await?;
+        } else if status_changed {
+            self.emergency_service
+                .record_unit_progress(txn, &updated, actor, source)
+                .await?;
+        }
 
-        Ok(updated)
+        Ok((updated, needs_units))
+    }
+
+    /// Destination of an ambulance starting transport. A hospital named in the
+    /// payload overrides the recommendation; `autoDestination` accepts it.
+    async fn choose_destination(
+        &self,
+        ambulance: &Model,
+        payload: &AmbulancePayload,
+    ) -> Result<DestinationChoice, CustomError> {
+        let emergency = self
+            .emergency_service
+            .find_active_for_unit(ambulance.id)
+            .await?;
+        let recommended = match &emergency {
+            Some(emergency) => self
+                .destination_service
+                .recommend(
+                    emergency,
+                    ambulance_position(ambulance, payload),
+                    &ambulance.r#type,
+                )
+                .await?
+                .candidates
+                .into_iter()
+                .next(),
+            None => None,
+        };
+
+        let auto_picked = payload.auto_destination
+            && payload.hospital_id.is_none()
+            && payload.hospital_name.is_none();
+        let hospital_id = if let Some(hospital_id) = payload.hospital_id {
+            self.hospital_service
+                .find_by_field("id", &hospital_id.to_string())
+                .await?;
+            hospital_id
+        } else if let Some(hospital_name) = payload.hospital_name.as_deref() {
+            match self
+                .hospital_service
+                .find_by_field("name", hospital_name)
+                .await?
+            {
+                Some(hospital) => hospital.id,
+                None => {
+                    return Err(CustomError::new(
+                        HttpCodeW::BadRequest,
+                        "Invalid Hospital Name".to_string(),
+                    ));
+                }
+            }
+        } else if payload.auto_destination {
+            match &recommended {
+                Some(top) => top.hospital_id,
+                None => {
+                    return Err(CustomError::new(
+                        HttpCodeW::UnprocessableEntity,
+                        "No destination can be recommended: the ambulance is not working an emergency or no hospital is located".to_string(),
+                    ));
+                }
+            }
+        } else {
+            return Err(CustomError::new(
+                HttpCodeW::BadRequest,
+                "hospitalName, hospitalId or autoDestination is required for transporting patient"
+                    .to_string(),
+            ));
+        };
+
+        let mut reason = match &recommended {
+            Some(top) if top.hospital_id != hospital_id => {
+                format!("crew override of recommended {}", top.hospital_name)
+            }
+            Some(_) if auto_picked => "auto-picked recommended destination".to_string(),
+            Some(_) => "crew accepted recommended destination".to_string(),
+            None => "chosen by crew without recommendation".to_string(),
+        };
+        // Crews may still head to a diverted hospital, but it goes on record
+        let diversions = active_diversions(&self.conn, vec![hospital_id], now_time()).await?;
+        for diversion in diversions.get(&hospital_id).into_iter().flatten() {
+            reason.push_str(&format!("; on diversion: {}", diversion.reason));
+        }
+        Ok(DestinationChoice {
+            hospital_id,
+            emergency,
+            reason,
+        })
+    }
+
+    /// Destinations ranked for the emergency the ambulance is working.
+    pub async fn recommend_destinations(
+        &self,
+        ambulance_id: Uuid,
+    ) -> Result<DestinationRecommendation, CustomError> {
+        let ambulance = Entity::find_by_id(ambulance_id)
+            .one(&self.conn)
+            .await?
+            .ok_or(CustomError::new(
+                HttpCodeW::NotFound,
+                "Ambulance not found".to_string(),
+            ))?;
+        let emergency = self
+            .emergency_service
+            .find_active_for_unit(ambulance.id)
+            .await?
+            .ok_or(CustomError::new(
+                HttpCodeW::Conflict,
+                "Ambulance is not working an open emergency".to_string(),
+            ))?;
+        let recommendation = self
+            .destination_service
+            .recommend(
+                &emergency,
+                ambulance_position(&ambulance, &AmbulancePayload::default()),
+                &ambulance.r#type,
+            )
+            .await?;
+        Ok(recommendation)
     }
 
     async fn set_transport_patient(
@@ -229,6 +446,11 @@ impl AmbulanceService {
 
             let result = active_model.insert(&self.conn).await;
             if let Some(value) = check_if_is_duplicate_key_from_data_base(&mut attempts, result) {
+                if let Ok(created) = &value
+                    && created.status == AmbulanceStatusEnum::Available
+                {
+                    wake_allocator();
+                }
                 return value;
             }
         }
@@ -239,7 +461,7 @@ impl AmbulanceService {
         page: u64, // Use u64 for pagination
         per_page: u64,
         filter: Option<String>,
-    ) -> Result<PaginatedResponse<Vec<Model>>, CustomError> {
+    ) -> Result<PaginatedResponse<Vec<AmbulanceWithCrew>>, CustomError> {
         let mut query = Entity::find();
         if let Some(filter_str) = filter {
             match filter_str.split_once('=') {
@@ -278,6 +500,7 @@ impl AmbulanceService {
         let records = paginator
             .fetch_page(page) // Page is 0-indexed in SeaORM
             .await?;
+        let records = crew::with_crew(&self.conn, records, now_time()).await?;
 
         let pagination = PaginationInfo {
             current_page: page as i64, // Convert back to i64 if needed for your PaginatedResponse
@@ -294,8 +517,17 @@ impl AmbulanceService {
         })
     }
 
-    pub async fn find_all_status(&self) -> Result<Vec<StatusDto>, CustomError> {
-        let statuses: Vec<StatusDto> = AmbulanceStatusEnum::iter() // Use EnumIter to get all variants
+    /// Every ambulance status, or with `from` only those a crew may move to from it.
+    pub async fn find_all_status(
+        &self,
+        from: Option<AmbulanceStatusEnum>,
+    ) -> Result<Vec<StatusDto>, CustomError> {
+        let candidates: Vec<AmbulanceStatusEnum> = match from {
+            Some(from) => allowed_transitions(&from).to_vec(),
+            None => AmbulanceStatusEnum::iter().collect(), // Use EnumIter to get all variants
+        };
+        let statuses: Vec<StatusDto> = candidates
+            .into_iter()
             .map(|status_enum_variant| {
                 let value = serde_json::to_string(&status_enum_variant)
                     .unwrap_or_else(|_| "\"UNKNOWN\"".to_string()) // Fallback for error
@@ -311,6 +543,63 @@ impl AmbulanceService {
 
         Ok(statuses)
     }
+
+    /// Ambulances within `radius_km` of a point, nearest first, optionally
+    /// restricted to one status.
+    pub async fn find_nearby(
+        &self,
+        query: NearbyAmbulanceQuery,
+    ) -> Result<Vec<NearbyAmbulance>, CustomError> {
+        let radius_km = query.radius_km.unwrap_or(DEFAULT_NEARBY_RADIUS_KM);
+        if !(-90.0..=90.0).contains(&query.lat) || !(-180.0..=180.0).contains(&query.lon) {
+            return Err(CustomError::new(
+                HttpCodeW::BadRequest,
+                "lat must be within [-90, 90] and lon within [-180, 180]".to_string(),
+            ));
+        }
+        if !(radius_km > 0.0 && radius_km <= MAX_NEARBY_RADIUS_KM) {
+            return Err(CustomError::new(
+                HttpCodeW::BadRequest,
+                format!("radius_km must be greater than 0 and at most {MAX_NEARBY_RADIUS_KM}"),
+            ));
+        }
+
+        let mut select = Entity::find();
+        if let Some(status) = query.status {
+            select = select.filter(Column::Status.eq(status));
+        }
+        let nearby =
+            find_within_radius(&self.conn, select, query.lat, query.lon, radius_km).await?;
+        Ok(nearby
+            .into_iter()
+            .map(|(ambulance, distance_km)| NearbyAmbulance {
+                ambulance,
+                distance_km,
+            })
+            .collect())
+    }
+}
+
+/// Destination picked for an ambulance starting transport.
+struct DestinationChoice {
+    hospital_id: Uuid,
+    /// Emergency the patient comes from, when the ambulance is working one.
+    emergency: Option<emergency::Model>,
+    reason: String,
+}
+
+/// Where the ambulance is, preferring a position sent with the update.
+fn ambulance_position(ambulance: &Model, payload: &AmbulancePayload) -> (f64, f64) {
+    let latitude = payload
+        .location_latitude
+        .unwrap_or(ambulance.location_latitude);
+    let longitude = payload
+        .location_longitude
+        .unwrap_or(ambulance.location_longitude);
+    (
+        latitude.to_f64().unwrap_or_default(),
+        longitude.to_f64().unwrap_or_default(),
+    )
 }
 
 pub fn generate_payload_to_create_ambulance(payload: Option<AmbulancePayload>) -> ActiveModel {
@@ -352,6 +641,8 @@ pub fn generate_payload_to_create_ambulance(payload: Option<AmbulancePayload>) -
 
         mileage: Set(payload.mileage),
 
+        last_service_mileage: Set(payload.last_service_mileage),
+
         fuel_type: Set(payload.fuel_type),
 
         registration_number: Set(payload.registration_number),
@@ -404,7 +695,7 @@ pub fn generate_payload_to_create_ambulance(payload: Option<AmbulancePayload>) -
             Set(AmbulanceCarDetailsModelEnum::Nv350)
         },
 
-        location_latitude: if let Some(val) = payload.location_longitude {
+        location_latitude: if let Some(val) = payload.location_latitude {
             Set(val)
         } else {
             Set(Decimal::new(0, 6)) // Default value
@@ -428,6 +719,8 @@ pub fn generate_payload_to_create_ambulance(payload: Option<AmbulancePayload>) -
             Set(AmbulanceStatusEnum::Available)
         },
         hospital_id: Default::default(),
+        location_updated_at: Set(None),
+        geohash: NotSet,
     }
 }
 // Helper function to convert SCREAMING_SNAKE_CASE to a more readable format
@@ -0,0 +1,299 @@
+use chrono::{Duration, NaiveDateTime};
+use log::error;
+use rust_decimal::prelude::ToPrimitive;
+use sea_orm::prelude::Uuid;
+use sea_orm::sea_query::{Expr, OnConflict};
+use sea_orm::*;
+
+use crate::components::ambulance::geofence::GeofenceService;
+use crate::entity::ambulance_location::{
+    RejectedPoint, TelemetryBatch, TelemetryPoint, TelemetryReceipt, TrackQuery, TrackReplay,
+};
+use crate::entity::{ambulance, ambulance_location};
+use crate::http_response::HttpCodeW;
+use crate::http_response::error_handler::CustomError;
+use crate::utils::helpers::{calculate_distance, env_or, now_time};
+use crate::utils::spatial::geohash_of_decimal;
+
+/// Environment variable with the days location history is kept; `0` keeps it forever.
+pub const LOCATION_RETENTION_ENV: &str = "LOCATION_RETENTION_DAYS";
+/// Environment variable with the retention job period in seconds.
+pub const LOCATION_RETENTION_INTERVAL_ENV: &str = "LOCATION_RETENTION_INTERVAL_SECS";
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+const DEFAULT_RETENTION_INTERVAL_SECS: u64 = 3600;
+/// Most points accepted in one batch.
+pub const MAX_BATCH_POINTS: usize = 500;
+/// Device clocks may run this far ahead of the server.
+const MAX_CLOCK_SKEW_SECONDS: i64 = 120;
+/// Fastest plausible ground speed; air ambulances stay well below it.
+const MAX_SPEED_KMH: f64 = 400.0;
+/// Longest range one replay covers.
+const MAX_TRACK_HOURS: i64 = 24;
+/// Most points one replay returns.
+const MAX_TRACK_POINTS: u64 = 10_000;
+
+/// Why a point cannot be stored, `None` when it is fine. Points older than
+/// `oldest` would be purged straight away.
+pub fn point_error(
+    point: &TelemetryPoint,
+    now: NaiveDateTime,
+    oldest: Option<NaiveDateTime>,
+) -> Option<String> {
+    let latitude = point.latitude.to_f64().unwrap_or(f64::NAN);
+    let longitude = point.longitude.to_f64().unwrap_or(f64::NAN);
+    if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+        return Some("latitude must be within [-90, 90] and longitude within [-180, 180]".into());
+    }
+    if let Some(speed) = point.speed_kmh
+        && !(0.0..=MAX_SPEED_KMH).contains(&speed)
+    {
+        return Some(format!("speed_kmh must be within [0, {MAX_SPEED_KMH}]"));
+    }
+    if let Some(heading) = point.heading
+        && !(0.0..360.0).contains(&heading)
+    {
+        return Some("heading must be within [0, 360)".into());
+    }
+    if let Some(accuracy) = point.accuracy_m
+        && !(accuracy.is_finite() && accuracy >= 0.0)
+    {
+        return Some("accuracy_m must not be negative".into());
+    }
+    if point.recorded_at > now + Duration::seconds(MAX_CLOCK_SKEW_SECONDS) {
+        return Some("recorded_at is in the future".into());
+    }
+    if oldest.is_some_and(|oldest| point.recorded_at < oldest) {
+        return Some("recorded_at is older than the retention period".into());
+    }
+    None
+}
+
+/// Sum of the straight-line hops between consecutive points, in km.
+pub fn track_distance_km(points: &[ambulance_location::Model]) -> f64 {
+    points
+        .windows(2)
+        .map(|pair| {
+            calculate_distance(
+                pair[0].latitude.to_f64().unwrap_or_default(),
+                pair[0].longitude.to_f64().unwrap_or_default(),
+                pair[1].latitude.to_f64().unwrap_or_default(),
+                pair[1].longitude.to_f64().unwrap_or_default(),
+            )
+        })
+        .sum()
+}
+
+/// Start of the kept history at `now`, `None` when it is kept forever.
+fn retention_start(now: NaiveDateTime) -> Option<NaiveDateTime> {
+    let days = env_or(LOCATION_RETENTION_ENV, DEFAULT_RETENTION_DAYS);
+    (days > 0).then(|| now - Duration::days(days))
+}
+
+pub struct TelemetryService {
+    conn: DatabaseConnection,
+}
+
+impl TelemetryService {
+    pub fn new(conn: &DatabaseConnection) -> Self {
+        Self { conn: conn.clone() }
+    }
+
+    /// Stores a batch of GPS points and moves the ambulance to the newest one
+    /// when it is newer than its current position, checking the move against
+    /// the geofences. Invalid points are reported back and the rest stored;
+    /// points already received are skipped.
+    pub async fn ingest(
+        &self,
+        ambulance_id: Uuid,
+        batch: TelemetryBatch,
+    ) -> Result<TelemetryReceipt, CustomError> {
+        if batch.points.is_empty() || batch.points.len() > MAX_BATCH_POINTS {
+            return Err(CustomError::new(
+                HttpCodeW::BadRequest,
+                format!("points must hold between 1 and {MAX_BATCH_POINTS} entries"),
+            ));
+        }
+        let now = now_time();
+        let oldest = retention_start(now);
+        let mut rejected = Vec::new();
+        let mut valid = Vec::with_capacity(batch.points.len());
+        for (index, point) in batch.points.into_iter().enumerate() {
+            match point_error(&point, now, oldest) {
+                Some(reason) => rejected.push(RejectedPoint { index, reason }),
+                None => valid.push(point),
+            }
+        }
+
+        // No row lock: the allocator skips locked units, and a long batch
+        // must not hide a vehicle from dispatch
+        let ambulance = ambulance::Entity::find_by_id(ambulance_id)
+            .one(&self.conn)
+            .await?
+            .ok_or(CustomError::new(
+                HttpCodeW::NotFound,
+                "Ambulance not found".to_string(),
+            ))?;
+        let submitted = valid.len() as u64;
+        let newest = valid.iter().max_by_key(|point| point.recorded_at).cloned();
+        let stored = if valid.is_empty() {
+            0
+        } else {
+            let rows = valid
+                .into_iter()
+                .map(|point| ambulance_location::ActiveModel {
+                    id: NotSet,
+                    ambulance_id: Set(ambulance_id),
+                    recorded_at: Set(point.recorded_at),
+                    latitude: Set(point.latitude),
+                    longitude: Set(point.longitude),
+                    speed_kmh: Set(point.speed_kmh),
+                    heading: Set(point.heading),
+                    accuracy_m: Set(point.accuracy_m),
+                    received_at: Set(now),
+                });
+            ambulance_location::Entity::insert_many(rows)
+                .on_conflict(
+                    OnConflict::columns([
+                        ambulance_location::Column::AmbulanceId,
+                        ambulance_location::Column::RecordedAt,
+                    ])
+                    .do_nothing()
+                    .to_owned(),
+                )
+                .exec_without_returning(&self.conn)
+                .await?
+        };
+
+        // Moves the unit only if no newer fix got there first, in one statement
+        let position_updated = match &newest {
+            Some(newest) => {
+                ambulance::Entity::update_many()
+                    .col_expr(
+                        ambulance::Column::LocationLatitude,
+                        Expr::value(newest.latitude),
+                    )
+                    .col_expr(
+                        ambulance::Column::LocationLongitude,
+                        Expr::value(newest.longitude),
+                    )
+                    .col_expr(
+                        ambulance::Column::LocationUpdatedAt,
+                        Expr::value(newest.recorded_at),
+                    )
+                    // `update_many` skips `before_save`, which keeps the geohash
+                    .col_expr(
+                        ambulance::Column::Geohash,
+                        Expr::value(geohash_of_decimal(newest.latitude, newest.longitude)),
+                    )
+                    .col_expr(ambulance::Column::UpdatedAt, Expr::value(now))
+                    .filter(ambulance::Column::Id.eq(ambulance_id))
+                    .filter(
+                        Condition::any()
+                            .add(ambulance::Column::LocationUpdatedAt.is_null())
+                            .add(ambulance::Column::LocationUpdatedAt.lt(newest.recorded_at)),
+                    )
+                    .exec(&self.conn)
+                    .await?
+                    .rows_affected
+                    > 0
+            }
+            None => false,
+        };
+        let moved = newest.filter(|_| position_updated);
+
+        // The points are stored either way; a failed status change is only logged
+        let geofence_event = match &moved {
+            Some(newest) => GeofenceService::new(&self.conn)
+                .evaluate(&ambulance, newest)
+                .await
+                .unwrap_or_else(|e| {
+                    error!("Geofence check of ambulance {ambulance_id} failed: {e}");
+                    None
+                }),
+            None => None,
+        };
+
+        Ok(TelemetryReceipt {
+            stored,
+            duplicates: submitted - stored,
+            rejected,
+            position_updated,
+            geofence_event,
+        })
+    }
+
+    /// Points recorded by an ambulance over a range, oldest first.
+    pub async fn track(
+        &self,
+        ambulance_id: Uuid,
+        query: TrackQuery,
+    ) -> Result<TrackReplay, CustomError> {
+        let to = query.to.unwrap_or_else(now_time);
+        let from = query.from.unwrap_or(to - Duration::hours(1));
+        if from >= to || to - from > Duration::hours(MAX_TRACK_HOURS) {
+            return Err(CustomError::new(
+                HttpCodeW::BadRequest,
+                format!("from must be before to and the range at most {MAX_TRACK_HOURS} hours"),
+            ));
+        }
+        ambulance::Entity::find_by_id(ambulance_id)
+            .one(&self.conn)
+            .await?
+            .ok_or(CustomError::new(
+                HttpCodeW::NotFound,
+                "Ambulance not found".to_string(),
+            ))?;
+
+        let mut points = ambulance_location::Entity::find()
+            .filter(ambulance_location::Column::AmbulanceId.eq(ambulance_id))
+            .filter(ambulance_location::Column::RecordedAt.gte(from))
+            .filter(ambulance_location::Column::RecordedAt.lt(to))
+            .order_by_asc(ambulance_location::Column::RecordedAt)
+            .limit(MAX_TRACK_POINTS + 1)
+            .all(&self.conn)
+            .await?;
+        let truncated = points.len() as u64 > MAX_TRACK_POINTS;
+        points.truncate(MAX_TRACK_POINTS as usize);
+        Ok(TrackReplay {
+            ambulance_id,
+            from,
+            to,
+            distance_km: track_distance_km(&points),
+            truncated,
+            points,
+        })
+    }
+
+    /// Deletes history older than the retention period. Returns how many
+    /// points were removed.
+    pub async fn purge_expired(&self) -> Result<u64, CustomError> {
+        let Some(oldest) = retention_start(now_time()) else {
+            return Ok(0);
+        };
+        let deleted = ambulance_location::Entity::delete_many()
+            .filter(ambulance_location::Column::RecordedAt.lt(oldest))
+            .exec(&self.conn)
+            .await?;
+        Ok(deleted.rows_affected)
+    }
+}
+
+/// Periodically removes location history past `LOCATION_RETENTION_DAYS`.
+pub async fn start_location_retention_job(
+    db_conn: &DatabaseConnection,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let interval = std::time::Duration::from_secs(env_or(
+        LOCATION_RETENTION_INTERVAL_ENV,
+        DEFAULT_RETENTION_INTERVAL_SECS,
+    ));
+    println!("Location retention job started (every {interval:?})");
+
+    loop {
+        tokio::time::sleep(interval).await;
+        match TelemetryService::new(db_conn).purge_expired().await {
+            Ok(0) => {}
+            Ok(count) => println!("Purged {count} expired ambulance locations"),
+            Err(e) => error!("Location retention pass failed: {e}"),
+        }
+    }
+}
@@ -35,7 +35,18 @@ pub async fn find_all(
     let response = http_response_builder::ok(card);
     Ok(HttpResponse::Ok().json(response))
 }
+#[get("/card/{card_ic}/data")]
+pub async fn find_data(
+    card_ic: web::Path<i32>,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service_instance = CardService::new(db_conn.get_ref());
+    let data = service_instance.find_data(card_ic.into_inner()).await?;
+    let response = http_response_builder::ok(data);
+    Ok(HttpResponse::Ok().json(response))
+}
 pub fn init_routes(config: &mut web::ServiceConfig) {
     config.service(find_all);
+    config.service(find_data);
     config.service(create);
 }
@@ -1,4 +1,5 @@
-use crate::entity::card::{ActiveModel, CardPayload, Model};
+use crate::components::ambulance::coverage::CoverageService;
+use crate::entity::card::{ActiveModel, CardDataSource, CardPayload, Model};
 use crate::entity::{card, dashboard};
 use crate::http_response::error_handler::CustomError;
 use crate::http_response::HttpCodeW;
@@ -120,6 +121,31 @@ impl CardService {
             pagination,
         })
     }
+
+    /// Current data of a card whose `data_config` names a data source.
+    pub async fn find_data(&self, card_ic: i32) -> Result<serde_json::Value, CustomError> {
+        let card = card::Entity::find()
+            .filter(card::Column::CardIc.eq(card_ic))
+            .one(&self.conn)
+            .await?
+            .ok_or(CustomError::new(
+                HttpCodeW::NotFound,
+                "Card not found".to_string(),
+            ))?;
+        let source: CardDataSource = card
+            .data_config
+            .and_then(|config| serde_json::from_value(config).ok())
+            .ok_or(CustomError::new(
+                HttpCodeW::UnprocessableEntity,
+                "The card has no known data source in data_config".to_string(),
+            ))?;
+        let data = match source {
+            CardDataSource::Coverage(query) => {
+                serde_json::to_value(CoverageService::new(&self.conn).report(query).await?)
+            }
+        };
+        Ok(data.unwrap_or_default())
+    }
 }
 
 fn generate_payload_to_create_card(payload: Option<CardPayload>) -> ActiveModel {
@@ -0,0 +1,170 @@
+use chrono::{Duration, NaiveDateTime};
+use sea_orm::prelude::Uuid;
+use sea_orm::*;
+use std::collections::HashSet;
+
+use crate::components::emergency::lifecycle::is_terminal;
+use crate::entity::emergency;
+use crate::entity::sea_orm_active_enums::{
+    EmergencyIncidentEnum, EmergencyStatusEnum, EmergencyVerificationEnum,
+};
+use crate::utils::helpers::env_or;
+use crate::utils::spatial::find_within_radius;
+
+/// Environment variable with the distance under which two reports may be the same incident.
+pub const DUPLICATE_RADIUS_ENV: &str = "DUPLICATE_RADIUS_METERS";
+/// Environment variable with how far back, in minutes, earlier reports are compared.
+pub const DUPLICATE_WINDOW_ENV: &str = "DUPLICATE_WINDOW_MINUTES";
+const DEFAULT_RADIUS_METERS: f64 = 300.0;
+const DEFAULT_WINDOW_MINUTES: i64 = 20;
+
+/// Statuses of an incident that ended; nothing is linked to it any more.
+const CLOSED_STATUSES: [EmergencyStatusEnum; 3] = [
+    EmergencyStatusEnum::Resolved,
+    EmergencyStatusEnum::Cancelled,
+    EmergencyStatusEnum::Failed,
+];
+
+/// Incident types callers commonly use for the same event, e.g. a motorway
+/// pile-up reported as a car, motorcycle or pedestrian accident.
+fn incident_family(incident: &EmergencyIncidentEnum) -> Option<&'static str> {
+    use EmergencyIncidentEnum::*;
+    let family = match incident {
+        CarAccident | MotorcycleAccident | PedestrianAccident | TrainAccident => "TRAFFIC",
+        AirplaneCrash | ShipAccident => "TRANSPORT_DISASTER",
+        HeartAttack | Stroke | Seizure | BreathingProblem => "COLLAPSE",
+        HouseFire | ForestFire | Explosion | GasLeak | SevereBurns => "FIRE",
+        Shooting | Stabbing | Robbery | Assault | DomesticViolence | Kidnapping
+        | HostageSituation => "VIOLENCE",
+        ChemicalSpill | RadiationExposure | BiologicalHazard | Poisoning => "HAZMAT",
+        Earthquake | Flood | Tornado | Hurricane | Landslide | Tsunami | BuildingCollapse
+        | BridgeCollapse | DamFailure => "DISASTER",
+        Pandemic | InfectiousDiseaseOutbreak => "OUTBREAK",
+        _ => return None,
+    };
+    Some(family)
+}
+
+/// Whether two reports could describe the same incident. `Unknown` matches
+/// anything, since callers often cannot tell what happened.
+pub fn are_related(a: &EmergencyIncidentEnum, b: &EmergencyIncidentEnum) -> bool {
+    a == b
+        || *a == EmergencyIncidentEnum::Unknown
+        || *b == EmergencyIncidentEnum::Unknown
+        || incident_family(a).is_some_and(|family| incident_family(b) == Some(family))
+}
+
+/// Verification states an incident must be in to take a new report of
+/// state `verification` as its duplicate.
+pub fn parent_verifications(
+    verification: &EmergencyVerificationEnum,
+) -> Vec<EmergencyVerificationEnum> {
+    match verification {
+        EmergencyVerificationEnum::Provisional => vec![
+            EmergencyVerificationEnum::Verified,
+            EmergencyVerificationEnum::Provisional,
+        ],
+        _ => vec![EmergencyVerificationEnum::Verified],
+    }
+}
+
+/// Parent of `report` once `merged` is folded into `survivor`. Reports
+/// linked to the merged one move to the survivor, which never becomes its
+/// own parent; other links are kept.
+pub fn parent_after_merge(
+    report: Uuid,
+    parent: Option<Uuid>,
+    merged: Uuid,
+    survivor: Uuid,
+) -> Option<Uuid> {
+    match parent {
+        Some(parent) if parent == merged => (report != survivor).then_some(survivor),
+        other => other,
+    }
+}
+
+/// How close in space and time two reports must be to be linked.
+#[derive(Debug, Clone, Copy)]
+pub struct DuplicatePolicy {
+    pub radius_meters: f64,
+    pub window_minutes: i64,
+}
+
+impl DuplicatePolicy {
+    /// Reads `DUPLICATE_RADIUS_METERS` and `DUPLICATE_WINDOW_MINUTES`.
+    pub fn from_env() -> Self {
+        DuplicatePolicy {
+            radius_meters: env_or(DUPLICATE_RADIUS_ENV, DEFAULT_RADIUS_METERS),
+            window_minutes: env_or(DUPLICATE_WINDOW_ENV, DEFAULT_WINDOW_MINUTES),
+        }
+    }
+
+    /// Earliest open incident of a related type reported nearby within the
+    /// window, which a new report at this place and time likely duplicates.
+    ///
+    /// A confirmed report is only linked to confirmed incidents, so it is
+    /// never held back behind an unverified public report; a provisional one
+    /// may also join another provisional report and corroborate it.
+    pub async fn find_parent<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        incident: &EmergencyIncidentEnum,
+        verification: &EmergencyVerificationEnum,
+        latitude: f64,
+        longitude: f64,
+        now: NaiveDateTime,
+    ) -> Result<Option<emergency::Model>, DbErr> {
+        if self.radius_meters <= 0.0 || self.window_minutes <= 0 {
+            return Ok(None);
+        }
+        let recent = emergency::Entity::find()
+            .filter(emergency::Column::ParentEmergencyId.is_null())
+            .filter(emergency::Column::Status.is_not_in(CLOSED_STATUSES))
+            .filter(emergency::Column::VerificationStatus.is_in(parent_verifications(verification)))
+            .filter(emergency::Column::CreatedAt.gte(now - Duration::minutes(self.window_minutes)));
+        let nearby = find_within_radius(
+            conn,
+            recent,
+            latitude,
+            longitude,
+            self.radius_meters / 1000.0,
+        )
+        .await?;
+        Ok(nearby
+            .into_iter()
+            .map(|(candidate, _)| candidate)
+            .filter(|candidate| are_related(&candidate.incident_type, incident))
+            .min_by_key(|candidate| candidate.created_at))
+    }
+}
+
+/// Emergencies linked to a parent that is still open. They are handled
+/// through the parent, so they are neither staffed nor escalated on their own.
+pub async fn covered_by_parent<C: ConnectionTrait>(
+    conn: &C,
+    emergencies: &[emergency::Model],
+) -> Result<HashSet<Uuid>, DbErr> {
+    let parent_ids: Vec<Uuid> = emergencies
+        .iter()
+        .filter_map(|e| e.parent_emergency_id)
+        .collect();
+    if parent_ids.is_empty() {
+        return Ok(HashSet::new());
+    }
+    let open_parents: HashSet<Uuid> = emergency::Entity::find()
+        .filter(emergency::Column::Id.is_in(parent_ids))
+        .all(conn)
+        .await?
+        .into_iter()
+        .filter(|parent| !is_terminal(&parent.status))
+        .map(|parent| parent.id)
+        .collect();
+    Ok(emergencies
+        .iter()
+        .filter(|e| {
+            e.parent_emergency_id
+                .is_some_and(|parent| open_parents.contains(&parent))
+        })
+        .map(|e| e.id)
+        .collect())
+}
@@ -1 +1,18 @@
+use crate::entity::sea_orm_active_enums::EmergencySeverityEnum;
 
+/// Dispatch priority of a severity, higher is more urgent.
+///
+/// The Postgres enum is declared in an order that does not reflect urgency
+/// (`DECEASED` sorts last), so ranking must happen in Rust. `Unknown` is
+/// treated like `Medium` so unassessed calls are not starved.
+pub fn severity_rank(severity: &EmergencySeverityEnum) -> u8 {
+    match severity {
+        EmergencySeverityEnum::Extreme => 6,
+        EmergencySeverityEnum::Critical => 5,
+        EmergencySeverityEnum::Severe | EmergencySeverityEnum::Unstable => 4,
+        EmergencySeverityEnum::High => 3,
+        EmergencySeverityEnum::Medium | EmergencySeverityEnum::Unknown => 2,
+        EmergencySeverityEnum::Low | EmergencySeverityEnum::Stable => 1,
+        EmergencySeverityEnum::Deceased => 0,
+    }
+}
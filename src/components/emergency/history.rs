@@ -0,0 +1,195 @@
+use chrono::NaiveDateTime;
+use sea_orm::prelude::Uuid;
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+
+use crate::entity::emergency;
+use crate::entity::sea_orm_active_enums::{EmergencySeverityEnum, EmergencyStatusEnum};
+use crate::utils::helpers::now_time;
+
+/// `type` of the change entries appended to `emergency.modification_attempts`.
+pub const CHANGE_ENTRY_TYPE: &str = "CHANGE";
+
+/// Actor recorded for changes made by the automatic allocator.
+pub const ALLOCATOR_ACTOR: &str = "allocator";
+/// Actor recorded for changes made by the escalation job.
+pub const ESCALATION_ACTOR: &str = "escalation";
+/// Actor recorded for reports made through the unauthenticated public intake.
+pub const PUBLIC_ACTOR: &str = "public";
+/// Actor recorded when the auto-confirm rule confirms a public report.
+pub const AUTO_CONFIRM_ACTOR: &str = "auto-confirm";
+/// Actor recorded for status changes applied when a unit crosses a geofence.
+pub const GEOFENCE_ACTOR: &str = "geofence";
+/// Actor recorded when the compliance job takes a vehicle off the road.
+pub const COMPLIANCE_ACTOR: &str = "compliance";
+
+/// What made a change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ChangeSource {
+    Api,
+    Allocator,
+    Escalation,
+    /// Public intake and its automatic confirmation.
+    Intake,
+    /// A unit entering or leaving a geofence.
+    Geofence,
+    /// The fleet compliance job.
+    Compliance,
+}
+
+/// The part of an emergency a change touched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ChangeField {
+    Status,
+    Severity,
+    /// Lead unit, `emergency.ambulance_id`.
+    Ambulance,
+    /// Set of units working the case, including the lead.
+    Units,
+    /// Ids of the patients on the case.
+    Patients,
+    Notes,
+    /// Incident this one is linked to as a duplicate.
+    Parent,
+    /// Destination hospital, `emergency.hospital_id`.
+    Hospital,
+    /// Whether a publicly reported case was confirmed, `emergency.verification_status`.
+    Verification,
+    /// Time a milestone was reached, e.g. `at_scene_at`, cleared by a revert.
+    Milestone,
+}
+
+/// One change to an emergency, stored in `emergency.modification_attempts`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub r#type: String,
+    pub at: NaiveDateTime,
+    /// `sub` of the user, or `allocator` / `escalation` for automatic changes.
+    pub actor: String,
+    pub source: ChangeSource,
+    pub field: ChangeField,
+    pub old: serde_json::Value,
+    pub new: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Changes made by one actor in one operation, saved together.
+#[derive(Debug, Clone)]
+pub struct ChangeLog {
+    actor: String,
+    source: ChangeSource,
+    at: NaiveDateTime,
+    reason: Option<String>,
+    entries: Vec<HistoryEntry>,
+}
+
+impl ChangeLog {
+    pub fn new(actor: &str, source: ChangeSource) -> Self {
+        ChangeLog {
+            actor: actor.to_string(),
+            source,
+            at: now_time(),
+            reason: None,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Changes made by the allocator.
+    pub fn allocator() -> Self {
+        Self::new(ALLOCATOR_ACTOR, ChangeSource::Allocator)
+    }
+
+    /// Reason attached to every entry recorded afterwards.
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    /// Records a change of `field`; values that did not change are skipped.
+    pub fn record<T: Serialize + PartialEq>(&mut self, field: ChangeField, old: &T, new: &T) {
+        if old == new {
+            return;
+        }
+        self.entries.push(HistoryEntry {
+            r#type: CHANGE_ENTRY_TYPE.to_string(),
+            at: self.at,
+            actor: self.actor.clone(),
+            source: self.source,
+            field,
+            old: serde_json::to_value(old).unwrap_or_default(),
+            new: serde_json::to_value(new).unwrap_or_default(),
+            reason: self.reason.clone(),
+        });
+    }
+
+    /// The recorded entries, for callers that store them elsewhere.
+    pub fn into_entries(self) -> Vec<HistoryEntry> {
+        self.entries
+    }
+
+    /// Appends the recorded entries to the emergency's history.
+    ///
+    /// The append happens in SQL, so a stale model of the same row elsewhere in
+    /// the transaction cannot drop entries written since it was loaded.
+    pub async fn save<C: ConnectionTrait>(self, conn: &C, emergency_id: Uuid) -> Result<(), DbErr> {
+        if self.entries.is_empty() {
+            return Ok(());
+        }
+        let entries = serde_json::to_value(self.into_entries()).unwrap_or_default();
+        emergency::Entity::update_many()
+            .col_expr(
+                emergency::Column::ModificationAttempts,
+                Expr::cust_with_values(
+                    "COALESCE(modification_attempts, '[]'::jsonb) || $1",
+                    [entries],
+                ),
+            )
+            .filter(emergency::Column::Id.eq(emergency_id))
+            .exec(conn)
+            .await?;
+        Ok(())
+    }
+}
+
+/// History of an emergency, oldest first. Unreadable entries are skipped.
+pub fn history_entries(emergency: &emergency::Model) -> Vec<HistoryEntry> {
+    let mut entries: Vec<HistoryEntry> = emergency
+        .modification_attempts
+        .as_ref()
+        .and_then(|json| json.as_array())
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.get("type").and_then(|t| t.as_str()) == Some(CHANGE_ENTRY_TYPE))
+        .filter_map(|entry| serde_json::from_value(entry.clone()).ok())
+        .collect();
+    entries.sort_by_key(|entry| entry.at);
+    entries
+}
+
+/// Full history of an emergency, as returned by `GET /emergency/{ic}/timeline`.
+#[derive(Debug, Serialize)]
+pub struct EmergencyTimeline {
+    pub emergency_id: Uuid,
+    pub emergency_ic: String,
+    pub created_at: NaiveDateTime,
+    pub status: EmergencyStatusEnum,
+    pub severity: EmergencySeverityEnum,
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl From<&emergency::Model> for EmergencyTimeline {
+    fn from(emergency: &emergency::Model) -> Self {
+        EmergencyTimeline {
+            emergency_id: emergency.id,
+            emergency_ic: emergency.emergency_ic.clone(),
+            created_at: emergency.created_at,
+            status: emergency.status.clone(),
+            severity: emergency.severity.clone(),
+            entries: history_entries(emergency),
+        }
+    }
+}
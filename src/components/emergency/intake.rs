@@ -0,0 +1,302 @@
+use log::{error, info};
+use rust_decimal::prelude::ToPrimitive;
+use sea_orm::prelude::Uuid;
+use sea_orm::*;
+use std::collections::HashSet;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+use crate::components::emergency::history::{AUTO_CONFIRM_ACTOR, ChangeSource};
+use crate::components::emergency::services::EmergencyService;
+use crate::entity::emergency::{
+    self, EmergencyConfirmPayload, EmergencyRequestBody, PublicReportPayload, PublicReportReceipt,
+};
+use crate::entity::sea_orm_active_enums::{EmergencyStatusEnum, EmergencyVerificationEnum};
+use crate::http_response::HttpCodeW;
+use crate::http_response::error_handler::CustomError;
+use crate::utils::helpers::{env_or, now_time};
+use crate::utils::rate_limit::RateLimiter;
+
+/// Environment variable with the reports one IP address may make per window.
+pub const INTAKE_IP_LIMIT_ENV: &str = "PUBLIC_INTAKE_IP_LIMIT";
+/// Environment variable with the reports one callback phone may make per window.
+pub const INTAKE_PHONE_LIMIT_ENV: &str = "PUBLIC_INTAKE_PHONE_LIMIT";
+/// Environment variable with the rate limit window in minutes.
+pub const INTAKE_WINDOW_ENV: &str = "PUBLIC_INTAKE_WINDOW_MINUTES";
+/// Environment variable that, when `true`, takes the client address from
+/// `Forwarded` / `X-Forwarded-For`. Only set it behind a trusted proxy.
+pub const INTAKE_TRUST_FORWARDED_ENV: &str = "PUBLIC_INTAKE_TRUST_FORWARDED";
+/// Environment variable with the minutes after which a provisional report is
+/// confirmed without a dispatcher; `0` disables the rule.
+pub const AUTO_CONFIRM_AFTER_ENV: &str = "AUTO_CONFIRM_AFTER_MINUTES";
+/// Environment variable with how many distinct callback phones reporting the
+/// same incident confirm it; `0` disables the rule.
+pub const AUTO_CONFIRM_CALLERS_ENV: &str = "AUTO_CONFIRM_DISTINCT_CALLERS";
+const DEFAULT_IP_LIMIT: usize = 5;
+const DEFAULT_PHONE_LIMIT: usize = 3;
+const DEFAULT_WINDOW_MINUTES: u64 = 60;
+/// Most callers each limiter tracks at once.
+const MAX_TRACKED_CALLERS: usize = 10_000;
+const DEFAULT_AUTO_CONFIRM_AFTER_MINUTES: i64 = 0;
+const DEFAULT_AUTO_CONFIRM_CALLERS: usize = 0;
+/// Longest description kept from a public report.
+pub const MAX_DESCRIPTION_CHARS: usize = 1000;
+
+static IP_LIMITER: LazyLock<RateLimiter> =
+    LazyLock::new(|| limiter(INTAKE_IP_LIMIT_ENV, DEFAULT_IP_LIMIT));
+static PHONE_LIMITER: LazyLock<RateLimiter> =
+    LazyLock::new(|| limiter(INTAKE_PHONE_LIMIT_ENV, DEFAULT_PHONE_LIMIT));
+
+fn limiter(limit_env: &str, default_limit: usize) -> RateLimiter {
+    let minutes = env_or(INTAKE_WINDOW_ENV, DEFAULT_WINDOW_MINUTES);
+    RateLimiter::new(
+        env_or(limit_env, default_limit),
+        Duration::from_secs(minutes * 60),
+        MAX_TRACKED_CALLERS,
+    )
+}
+
+/// Whether the client address may be read from forwarding headers.
+pub fn trust_forwarded() -> bool {
+    env_or(INTAKE_TRUST_FORWARDED_ENV, false)
+}
+
+/// Reduces a phone number to `+` and digits, dropping spaces, dashes, dots and
+/// brackets. `None` unless 7 to 15 digits remain, the E.164 maximum.
+pub fn normalize_phone(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    let (plus, rest) = match raw.strip_prefix('+') {
+        Some(rest) => ("+", rest),
+        None => ("", raw),
+    };
+    let mut digits = String::with_capacity(rest.len());
+    for c in rest.chars() {
+        match c {
+            '0'..='9' => digits.push(c),
+            ' ' | '-' | '.' | '(' | ')' => {}
+            _ => return None,
+        }
+    }
+    (7..=15)
+        .contains(&digits.len())
+        .then(|| format!("{plus}{digits}"))
+}
+
+/// Checks a public report and turns it into a regular emergency body, with
+/// the normalised callback phone.
+pub fn validate_report(
+    payload: PublicReportPayload,
+) -> Result<(EmergencyRequestBody, String), CustomError> {
+    let bad_request = |message: &str| CustomError::new(HttpCodeW::BadRequest, message.to_string());
+    let latitude = payload.latitude.to_f64().unwrap_or(f64::NAN);
+    let longitude = payload.longitude.to_f64().unwrap_or(f64::NAN);
+    if !(-90.0..=90.0).contains(&latitude) {
+        return Err(bad_request("latitude must be between -90 and 90"));
+    }
+    if !(-180.0..=180.0).contains(&longitude) {
+        return Err(bad_request("longitude must be between -180 and 180"));
+    }
+    let phone = normalize_phone(&payload.callback_phone)
+        .ok_or_else(|| bad_request("callbackPhone must be a phone number of 7 to 15 digits"))?;
+    let description = payload
+        .description
+        .map(|d| d.trim().to_string())
+        .filter(|d| !d.is_empty());
+    if description
+        .as_ref()
+        .is_some_and(|d| d.chars().count() > MAX_DESCRIPTION_CHARS)
+    {
+        return Err(bad_request(&format!(
+            "description must be at most {MAX_DESCRIPTION_CHARS} characters"
+        )));
+    }
+
+    let body = EmergencyRequestBody {
+        notes: None,
+        emergency_latitude: payload.latitude,
+        emergency_longitude: payload.longitude,
+        incident_type: payload.incident_type,
+        severity: None,
+        description,
+        patients: None,
+    };
+    Ok((body, phone))
+}
+
+/// Counts a report against the per-IP and per-phone limits, 429 when either
+/// is exhausted. A refused report counts against neither.
+fn check_limits(client_ip: &str, phone: &str) -> Result<(), CustomError> {
+    let now = Instant::now();
+    IP_LIMITER
+        .check(client_ip, now)
+        .and_then(|_| PHONE_LIMITER.check(phone, now))
+        .map_err(|retry_after| {
+            CustomError::new(
+                HttpCodeW::TooManyRequests,
+                format!(
+                    "Too many reports, retry in {} seconds or call the emergency number",
+                    retry_after.as_secs().max(1)
+                ),
+            )
+        })?;
+    IP_LIMITER.record(client_ip, now);
+    PHONE_LIMITER.record(phone, now);
+    Ok(())
+}
+
+/// When a provisional report is confirmed without waiting for a dispatcher.
+#[derive(Debug, Clone, Copy)]
+pub struct AutoConfirmPolicy {
+    /// Minutes a report may stay unconfirmed, `0` to never confirm on time.
+    pub after_minutes: i64,
+    /// Distinct callback phones that confirm an incident, `0` to disable.
+    pub distinct_callers: usize,
+}
+
+impl AutoConfirmPolicy {
+    /// Reads `AUTO_CONFIRM_AFTER_MINUTES` and `AUTO_CONFIRM_DISTINCT_CALLERS`.
+    pub fn from_env() -> Self {
+        AutoConfirmPolicy {
+            after_minutes: env_or(AUTO_CONFIRM_AFTER_ENV, DEFAULT_AUTO_CONFIRM_AFTER_MINUTES),
+            distinct_callers: env_or(AUTO_CONFIRM_CALLERS_ENV, DEFAULT_AUTO_CONFIRM_CALLERS),
+        }
+    }
+
+    /// Why a report waiting `waited_minutes` and made by `callers` distinct
+    /// phones is confirmed, `None` while it still needs a dispatcher.
+    pub fn reason(&self, waited_minutes: i64, callers: usize) -> Option<String> {
+        if self.distinct_callers > 0 && callers >= self.distinct_callers {
+            return Some(format!("reported by {callers} distinct callers"));
+        }
+        if self.after_minutes > 0 && waited_minutes >= self.after_minutes {
+            return Some(format!(
+                "unconfirmed for {waited_minutes} min, limit {} min",
+                self.after_minutes
+            ));
+        }
+        None
+    }
+}
+
+pub struct IntakeService {
+    conn: DatabaseConnection,
+    emergencies: EmergencyService,
+    policy: AutoConfirmPolicy,
+}
+
+impl IntakeService {
+    pub fn new(conn: &DatabaseConnection) -> Self {
+        Self {
+            conn: conn.clone(),
+            emergencies: EmergencyService::new(conn),
+            policy: AutoConfirmPolicy::from_env(),
+        }
+    }
+
+    /// Records a public report from `client_ip` as a provisional emergency.
+    /// A report corroborating an earlier provisional one may confirm both.
+    pub async fn report(
+        &self,
+        payload: PublicReportPayload,
+        client_ip: &str,
+    ) -> Result<PublicReportReceipt, CustomError> {
+        let (body, phone) = validate_report(payload)?;
+        check_limits(client_ip, &phone)?;
+
+        let created = self.emergencies.create_public_report(body, phone).await?;
+        info!(
+            "Public report {} received, awaiting verification",
+            created.emergency_ic
+        );
+        let root = created.parent_emergency_id.unwrap_or(created.id);
+        let confirmed = match emergency::Entity::find_by_id(root).one(&self.conn).await? {
+            Some(root) => self.auto_confirm(&root).await?,
+            None => false,
+        };
+        Ok(PublicReportReceipt {
+            emergency_ic: created.emergency_ic,
+            status: created.status,
+            verification_status: if confirmed {
+                EmergencyVerificationEnum::Verified
+            } else {
+                created.verification_status
+            },
+            created_at: created.created_at,
+        })
+    }
+
+    /// Confirms every open provisional report the policy allows. Returns how
+    /// many were confirmed.
+    pub async fn run_auto_confirm_pass(&self) -> Result<usize, CustomError> {
+        let mut confirmed = 0;
+        for report in self.emergencies.find_provisional().await? {
+            // Duplicates are confirmed through the report they are linked to
+            if report.parent_emergency_id.is_some() {
+                continue;
+            }
+            if self.auto_confirm(&report).await? {
+                confirmed += 1;
+            }
+        }
+        Ok(confirmed)
+    }
+
+    /// Confirms `report` when the policy allows it. A report confirmed or
+    /// rejected concurrently is left alone.
+    async fn auto_confirm(&self, report: &emergency::Model) -> Result<bool, CustomError> {
+        if report.verification_status != EmergencyVerificationEnum::Provisional
+            || report.status == EmergencyStatusEnum::Cancelled
+        {
+            return Ok(false);
+        }
+        let callers = self.distinct_callers(report.id).await?;
+        let waited = (now_time() - report.created_at).num_minutes();
+        let Some(reason) = self.policy.reason(waited, callers) else {
+            return Ok(false);
+        };
+        let payload = EmergencyConfirmPayload {
+            severity: None,
+            reason: Some(reason),
+        };
+        match self
+            .emergencies
+            .confirm_report(
+                &report.emergency_ic,
+                payload,
+                AUTO_CONFIRM_ACTOR,
+                ChangeSource::Intake,
+            )
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) if matches!(e.error_status_code, HttpCodeW::Conflict) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Distinct callback phones on a report and the duplicates linked to it.
+    async fn distinct_callers(&self, report_id: Uuid) -> Result<usize, CustomError> {
+        let phones: HashSet<String> = emergency::Entity::find()
+            .filter(
+                Condition::any()
+                    .add(emergency::Column::Id.eq(report_id))
+                    .add(emergency::Column::ParentEmergencyId.eq(report_id)),
+            )
+            .filter(emergency::Column::VerificationStatus.ne(EmergencyVerificationEnum::Rejected))
+            .all(&self.conn)
+            .await?
+            .into_iter()
+            .filter_map(|e| e.callback_phone)
+            .collect();
+        Ok(phones.len())
+    }
+}
+
+/// Runs the auto-confirm rule once, logging instead of failing.
+pub async fn run_auto_confirm(conn: &DatabaseConnection) {
+    match IntakeService::new(conn).run_auto_confirm_pass().await {
+        Ok(0) => {}
+        Ok(count) => println!("Auto-confirmed {count} public reports"),
+        Err(e) => error!("Auto-confirm pass failed: {e}"),
+    }
+}
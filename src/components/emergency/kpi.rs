@@ -0,0 +1,267 @@
+use chrono::{Duration, NaiveDateTime};
+use sea_orm::prelude::Uuid;
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::entity::sea_orm_active_enums::{
+    EmergencyIncidentEnum, EmergencySeverityEnum, EmergencyVerificationEnum,
+};
+use crate::entity::{emergency, hospital};
+use crate::http_response::HttpCodeW;
+use crate::http_response::error_handler::CustomError;
+use crate::utils::helpers::now_time;
+
+/// Period covered by a report when the query gives no start.
+const DEFAULT_RANGE_DAYS: i64 = 30;
+
+/// How a response-time report splits the emergencies.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KpiGroupBy {
+    #[default]
+    None,
+    Hospital,
+    IncidentType,
+    Severity,
+}
+
+/// Query of `GET /emergency/kpi`. The range applies to `created_at` and
+/// defaults to the last 30 days.
+#[derive(Debug, Clone, Deserialize)]
+pub struct KpiQuery {
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub group_by: KpiGroupBy,
+    pub hospital_id: Option<Uuid>,
+    pub incident_type: Option<EmergencyIncidentEnum>,
+    pub severity: Option<EmergencySeverityEnum>,
+}
+
+/// Lifecycle times of one emergency, as read for a report.
+#[derive(Debug, Clone, FromQueryResult)]
+pub struct KpiSample {
+    pub hospital_id: Option<Uuid>,
+    pub incident_type: EmergencyIncidentEnum,
+    pub severity: EmergencySeverityEnum,
+    pub created_at: NaiveDateTime,
+    pub dispatched_at: Option<NaiveDateTime>,
+    pub en_route_at: Option<NaiveDateTime>,
+    pub at_scene_at: Option<NaiveDateTime>,
+    pub transporting_at: Option<NaiveDateTime>,
+    pub at_hospital_at: Option<NaiveDateTime>,
+    pub resolved_at: Option<NaiveDateTime>,
+}
+
+/// Distribution of one interval over the emergencies that reached its milestone.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DurationStats {
+    pub samples: usize,
+    pub p50_seconds: f64,
+    pub p90_seconds: f64,
+    pub p95_seconds: f64,
+    pub max_seconds: f64,
+}
+
+impl DurationStats {
+    /// `None` without samples.
+    pub fn from_samples(mut seconds: Vec<f64>) -> Option<Self> {
+        if seconds.is_empty() {
+            return None;
+        }
+        seconds.sort_by(f64::total_cmp);
+        Some(DurationStats {
+            samples: seconds.len(),
+            p50_seconds: percentile(&seconds, 50.0),
+            p90_seconds: percentile(&seconds, 90.0),
+            p95_seconds: percentile(&seconds, 95.0),
+            max_seconds: seconds[seconds.len() - 1],
+        })
+    }
+}
+
+/// Percentile `p` (0–100) of sorted values, interpolating between the two
+/// closest ranks like `percentile_cont`.
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (p / 100.0).clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+/// Time from the report to each milestone.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResponseTimes {
+    pub time_to_dispatch: Option<DurationStats>,
+    pub time_to_en_route: Option<DurationStats>,
+    pub time_to_scene: Option<DurationStats>,
+    pub time_to_transport: Option<DurationStats>,
+    pub time_to_hospital: Option<DurationStats>,
+    pub time_to_resolve: Option<DurationStats>,
+}
+
+impl ResponseTimes {
+    pub fn from_samples(samples: &[&KpiSample]) -> Self {
+        let interval = |milestone: fn(&KpiSample) -> Option<NaiveDateTime>| {
+            let seconds: Vec<f64> = samples
+                .iter()
+                .filter_map(|sample| {
+                    let elapsed = milestone(sample)? - sample.created_at;
+                    // A milestone before the report can only come from clock skew
+                    (elapsed >= Duration::zero())
+                        .then(|| elapsed.num_milliseconds() as f64 / 1000.0)
+                })
+                .collect();
+            DurationStats::from_samples(seconds)
+        };
+        ResponseTimes {
+            time_to_dispatch: interval(|s| s.dispatched_at),
+            time_to_en_route: interval(|s| s.en_route_at),
+            time_to_scene: interval(|s| s.at_scene_at),
+            time_to_transport: interval(|s| s.transporting_at),
+            time_to_hospital: interval(|s| s.at_hospital_at),
+            time_to_resolve: interval(|s| s.resolved_at),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KpiGroup {
+    /// Hospital id, incident type or severity; `all` without grouping and
+    /// `none` for emergencies that never reached a hospital.
+    pub key: String,
+    /// Hospital name when grouping by hospital.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub emergencies: usize,
+    pub response_times: ResponseTimes,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KpiReport {
+    pub from: NaiveDateTime,
+    pub to: NaiveDateTime,
+    pub group_by: KpiGroupBy,
+    pub groups: Vec<KpiGroup>,
+}
+
+/// Key of the group a sample falls in.
+pub fn group_key(sample: &KpiSample, group_by: KpiGroupBy) -> String {
+    let enum_key = |value: serde_json::Value| value.as_str().unwrap_or_default().to_string();
+    match group_by {
+        KpiGroupBy::None => "all".to_string(),
+        KpiGroupBy::Hospital => sample
+            .hospital_id
+            .map_or_else(|| "none".to_string(), |id| id.to_string()),
+        KpiGroupBy::IncidentType => {
+            enum_key(serde_json::to_value(&sample.incident_type).unwrap_or_default())
+        }
+        KpiGroupBy::Severity => {
+            enum_key(serde_json::to_value(&sample.severity).unwrap_or_default())
+        }
+    }
+}
+
+/// Groups samples and computes their response times, largest group first.
+pub fn build_groups(samples: &[KpiSample], group_by: KpiGroupBy) -> Vec<KpiGroup> {
+    let mut grouped: BTreeMap<String, Vec<&KpiSample>> = BTreeMap::new();
+    for sample in samples {
+        grouped
+            .entry(group_key(sample, group_by))
+            .or_default()
+            .push(sample);
+    }
+    let mut groups: Vec<KpiGroup> = grouped
+        .into_iter()
+        .map(|(key, members)| KpiGroup {
+            key,
+            label: None,
+            emergencies: members.len(),
+            response_times: ResponseTimes::from_samples(&members),
+        })
+        .collect();
+    groups.sort_by(|a, b| b.emergencies.cmp(&a.emergencies).then(a.key.cmp(&b.key)));
+    groups
+}
+
+pub struct KpiService {
+    conn: DatabaseConnection,
+}
+
+impl KpiService {
+    pub fn new(conn: &DatabaseConnection) -> Self {
+        Self { conn: conn.clone() }
+    }
+
+    /// Response-time percentiles of the emergencies reported in the range.
+    /// Duplicates linked to another incident are left out so a crash reported
+    /// ten times counts once.
+    pub async fn report(&self, query: KpiQuery) -> Result<KpiReport, CustomError> {
+        let to = query.to.unwrap_or_else(now_time);
+        let from = query
+            .from
+            .unwrap_or_else(|| to - Duration::days(DEFAULT_RANGE_DAYS));
+        if from >= to {
+            return Err(CustomError::new(
+                HttpCodeW::BadRequest,
+                "from must be before to".to_string(),
+            ));
+        }
+
+        let mut select = emergency::Entity::find()
+            .select_only()
+            .columns([
+                emergency::Column::HospitalId,
+                emergency::Column::IncidentType,
+                emergency::Column::Severity,
+                emergency::Column::CreatedAt,
+                emergency::Column::DispatchedAt,
+                emergency::Column::EnRouteAt,
+                emergency::Column::AtSceneAt,
+                emergency::Column::TransportingAt,
+                emergency::Column::AtHospitalAt,
+                emergency::Column::ResolvedAt,
+            ])
+            .filter(emergency::Column::CreatedAt.gte(from))
+            .filter(emergency::Column::CreatedAt.lt(to))
+            .filter(emergency::Column::ParentEmergencyId.is_null())
+            // False public reports never needed a response
+            .filter(emergency::Column::VerificationStatus.ne(EmergencyVerificationEnum::Rejected));
+        if let Some(hospital_id) = query.hospital_id {
+            select = select.filter(emergency::Column::HospitalId.eq(hospital_id));
+        }
+        if let Some(incident_type) = query.incident_type {
+            select = select.filter(emergency::Column::IncidentType.eq(incident_type));
+        }
+        if let Some(severity) = query.severity {
+            select = select.filter(emergency::Column::Severity.eq(severity));
+        }
+        let samples = select.into_model::<KpiSample>().all(&self.conn).await?;
+
+        let mut groups = build_groups(&samples, query.group_by);
+        if query.group_by == KpiGroupBy::Hospital {
+            let ids: Vec<Uuid> = samples.iter().filter_map(|s| s.hospital_id).collect();
+            let names: HashMap<String, String> = hospital::Entity::find()
+                .filter(hospital::Column::Id.is_in(ids))
+                .all(&self.conn)
+                .await?
+                .into_iter()
+                .map(|h| (h.id.to_string(), h.name))
+                .collect();
+            for group in &mut groups {
+                group.label = names.get(&group.key).cloned();
+            }
+        }
+
+        Ok(KpiReport {
+            from,
+            to,
+            group_by: query.group_by,
+            groups,
+        })
+    }
+}
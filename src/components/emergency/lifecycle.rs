@@ -0,0 +1,123 @@
+use crate::entity::emergency;
+use crate::entity::sea_orm_active_enums::{AmbulanceStatusEnum, EmergencyStatusEnum};
+use crate::http_response::error_handler::CustomError;
+use crate::http_response::HttpCodeW;
+use chrono::NaiveDateTime;
+use sea_orm::{ActiveModelTrait, Value};
+use EmergencyStatusEnum::*;
+
+/// Returns the statuses an emergency is allowed to move to from `from`.
+///
+/// The happy path is Pending → InProgress → AtScene → InAmbulance →
+/// InTransitToHospital → ArrivedAtHospital → Resolved. Cancelled, Failed and
+/// Resolved are terminal and have no outgoing transitions.
+pub fn allowed_transitions(from: &EmergencyStatusEnum) -> &'static [EmergencyStatusEnum] {
+    match from {
+        Pending => &[InProgress, WaitingForResponse, OnHold, Escalated, Cancelled],
+        WaitingForResponse => &[Pending, InProgress, OnHold, Escalated, Cancelled],
+        OnHold => &[Pending, InProgress, Escalated, Cancelled],
+        Escalated => &[InProgress, OnHold, Cancelled, Failed],
+        InProgress => &[AtScene, OnHold, Escalated, Cancelled, Failed],
+        AtScene => &[InAmbulance, TreatedAtHome, Resolved, Escalated, Failed],
+        InAmbulance => &[InTransitToHospital, Failed],
+        InTransitToHospital => &[ArrivedAtHospital, Failed],
+        ArrivedAtHospital => &[Resolved, Failed],
+        TreatedAtHome => &[Resolved],
+        Resolved | Cancelled | Failed => &[],
+    }
+}
+
+/// `true` once a case can no longer change and its ambulance can be released.
+pub fn is_terminal(status: &EmergencyStatusEnum) -> bool {
+    matches!(status, Resolved | Cancelled | Failed)
+}
+
+/// Validates a single status change, returning a 422 describing the legal
+/// next states when the jump is not allowed.
+pub fn ensure_transition(
+    from: &EmergencyStatusEnum,
+    to: &EmergencyStatusEnum,
+) -> Result<(), CustomError> {
+    let allowed = allowed_transitions(from);
+    if allowed.contains(to) {
+        return Ok(());
+    }
+    let next = if allowed.is_empty() {
+        "none, the emergency is closed".to_string()
+    } else {
+        allowed
+            .iter()
+            .map(|s| format!("{s:?}"))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+    Err(CustomError::new(
+        HttpCodeW::UnprocessableEntity,
+        format!("Illegal emergency status transition from {from:?} to {to:?}. Allowed: {next}"),
+    ))
+}
+
+/// Points in the life of an emergency whose time is kept for response-time reporting.
+/// The report itself is `created_at`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Milestone {
+    Dispatched,
+    EnRoute,
+    AtScene,
+    Transporting,
+    AtHospital,
+    Resolved,
+}
+
+impl Milestone {
+    pub fn column(&self) -> emergency::Column {
+        match self {
+            Milestone::Dispatched => emergency::Column::DispatchedAt,
+            Milestone::EnRoute => emergency::Column::EnRouteAt,
+            Milestone::AtScene => emergency::Column::AtSceneAt,
+            Milestone::Transporting => emergency::Column::TransportingAt,
+            Milestone::AtHospital => emergency::Column::AtHospitalAt,
+            Milestone::Resolved => emergency::Column::ResolvedAt,
+        }
+    }
+
+    /// Milestone reached when the emergency moves to `status`.
+    pub fn of_status(status: &EmergencyStatusEnum) -> Option<Self> {
+        match status {
+            InProgress => Some(Milestone::Dispatched),
+            AtScene => Some(Milestone::AtScene),
+            InTransitToHospital => Some(Milestone::Transporting),
+            ArrivedAtHospital => Some(Milestone::AtHospital),
+            Resolved => Some(Milestone::Resolved),
+            _ => None,
+        }
+    }
+
+    /// Milestone reached when a unit working the emergency reports `status`.
+    pub fn of_unit_status(status: &AmbulanceStatusEnum) -> Option<Self> {
+        match status {
+            AmbulanceStatusEnum::Dispatched => Some(Milestone::Dispatched),
+            AmbulanceStatusEnum::EnRouteToScene => Some(Milestone::EnRoute),
+            AmbulanceStatusEnum::AtScene => Some(Milestone::AtScene),
+            AmbulanceStatusEnum::TransportingPatient | AmbulanceStatusEnum::EnRouteToHospital => {
+                Some(Milestone::Transporting)
+            }
+            AmbulanceStatusEnum::AtHospital => Some(Milestone::AtHospital),
+            _ => None,
+        }
+    }
+}
+
+/// Stamps the time the emergency first reached `milestone`; a time already
+/// recorded is kept.
+pub fn apply_milestone(
+    active_model: &mut emergency::ActiveModel,
+    milestone: Milestone,
+    at: NaiveDateTime,
+) {
+    let column = milestone.column();
+    let recorded = active_model.get(column).into_value();
+    if matches!(recorded, None | Some(Value::ChronoDateTime(None))) {
+        active_model.set(column, Some(at).into());
+    }
+}
@@ -1,8 +1,13 @@
-mod enums;
+pub(crate) mod duplicates;
+pub(crate) mod enums;
+pub(crate) mod history;
+pub(crate) mod intake;
+pub(crate) mod kpi;
+pub(crate) mod lifecycle;
 mod routes;
-mod schedule;
+pub(crate) mod schedule;
 mod services;
 
-pub use routes::init_routes;
+pub use routes::{init_public_routes, init_routes};
 pub use schedule::*;
 pub use services::*;
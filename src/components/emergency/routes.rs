@@ -1,9 +1,20 @@
+use crate::components::emergency::history::ChangeSource;
+use crate::components::emergency::intake::{IntakeService, trust_forwarded};
+use crate::components::emergency::kpi::{KpiQuery, KpiService};
+use crate::components::emergency::schedule::{
+    AllocationPreviewQuery, AllocationStrategy, EmergencyAllocationService, EscalationService,
+    wake_allocator,
+};
 use crate::components::emergency::services::EmergencyService;
-use crate::entity::emergency::EmergencyRequestBody;
+use crate::entity::emergency::{
+    EmergencyAssignPayload, EmergencyConfirmPayload, EmergencyListQuery, EmergencyMergePayload,
+    EmergencyRejectPayload, EmergencyRequestBody, EmergencyStatusPayload, PublicReportPayload,
+};
+use crate::http_response::HttpCodeW;
 use crate::http_response::error_handler::CustomError;
 use crate::http_response::http_response_builder;
-use crate::shared::PaginationParams;
-use actix_web::{HttpResponse, get, post, web};
+use crate::security::subject::Subject;
+use actix_web::{HttpRequest, HttpResponse, get, patch, post, web};
 use sea_orm::DatabaseConnection;
 use web::Path;
 
@@ -17,39 +28,230 @@ async fn find(
     let response = http_response_builder::ok(ambulance);
     Ok(HttpResponse::Ok().json(response))
 }
+/// `GET /emergency?status=Pending,Escalated&assigned=false&sort=severity`.
+/// Unknown parameters are rejected rather than ignored.
 #[get("/emergency")]
 pub async fn find_all(
-    query: web::Query<PaginationParams>,
+    req: HttpRequest,
     db_conn: web::Data<DatabaseConnection>,
 ) -> Result<HttpResponse, CustomError> {
+    let query = web::Query::<EmergencyListQuery>::from_query(req.query_string())
+        .map_err(|e| CustomError::new(HttpCodeW::BadRequest, e.to_string()))?;
     let service_instance = EmergencyService::new(db_conn.get_ref());
 
-    let ambulance = service_instance
-        .find_all(
-            query.page.try_into().unwrap(),
-            query.per_page.try_into().unwrap(),
-            query.filter.clone(),
-        )
-        .await?;
-    let response = http_response_builder::ok(ambulance);
+    let emergencies = service_instance.find_all(query.into_inner()).await?;
+    let response = http_response_builder::ok(emergencies);
     Ok(HttpResponse::Ok().json(response))
 }
 #[post("/emergency")]
 async fn create(
     emergency: web::Json<EmergencyRequestBody>,
+    subject: Subject,
     db_conn: web::Data<DatabaseConnection>,
 ) -> Result<HttpResponse, CustomError> {
     let service = EmergencyService::new(db_conn.get_ref());
-    let created_emergency = service.create_emergency(emergency.into_inner()).await?;
-    service
-        .schedule_emergency()
-        .await
-        .expect("TODO: panic message");
+    let created_emergency = service
+        .create_emergency(emergency.into_inner(), &subject.sub)
+        .await?;
+    wake_allocator();
     let response = http_response_builder::ok(created_emergency);
     Ok(HttpResponse::Ok().json(response))
 }
+/// Unauthenticated intake for citizen apps and kiosks.
+#[post("/public/emergency")]
+async fn report_public(
+    req: HttpRequest,
+    payload: web::Json<PublicReportPayload>,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let client_ip = {
+        let connection = req.connection_info();
+        if trust_forwarded() {
+            connection.realip_remote_addr()
+        } else {
+            connection.peer_addr()
+        }
+        .unwrap_or("unknown")
+        .to_string()
+    };
+    let service = IntakeService::new(db_conn.get_ref());
+    let receipt = service.report(payload.into_inner(), &client_ip).await?;
+    let response = http_response_builder::ok(receipt);
+    Ok(HttpResponse::Ok().json(response))
+}
+#[get("/emergency/provisional")]
+async fn provisional(db_conn: web::Data<DatabaseConnection>) -> Result<HttpResponse, CustomError> {
+    let service = EmergencyService::new(db_conn.get_ref());
+    let reports = service.find_provisional().await?;
+    let response = http_response_builder::ok(reports);
+    Ok(HttpResponse::Ok().json(response))
+}
+#[post("/emergency/{ic}/confirm")]
+async fn confirm(
+    ic: Path<String>,
+    payload: Option<web::Json<EmergencyConfirmPayload>>,
+    subject: Subject,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service = EmergencyService::new(db_conn.get_ref());
+    let payload = payload.map(web::Json::into_inner).unwrap_or_default();
+    let emergency = service
+        .confirm_report(&ic, payload, &subject.sub, ChangeSource::Api)
+        .await?;
+    let response = http_response_builder::ok(emergency);
+    Ok(HttpResponse::Ok().json(response))
+}
+#[post("/emergency/{ic}/reject")]
+async fn reject(
+    ic: Path<String>,
+    payload: web::Json<EmergencyRejectPayload>,
+    subject: Subject,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service = EmergencyService::new(db_conn.get_ref());
+    let emergency = service
+        .reject_report(&ic, payload.into_inner(), &subject.sub)
+        .await?;
+    let response = http_response_builder::ok(emergency);
+    Ok(HttpResponse::Ok().json(response))
+}
+#[get("/emergency/overdue")]
+async fn overdue(db_conn: web::Data<DatabaseConnection>) -> Result<HttpResponse, CustomError> {
+    let service = EscalationService::new(db_conn.get_ref());
+    let overdue = service.find_overdue().await?;
+    let response = http_response_builder::ok(overdue);
+    Ok(HttpResponse::Ok().json(response))
+}
+#[get("/emergency/kpi")]
+async fn kpi(
+    query: web::Query<KpiQuery>,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service = KpiService::new(db_conn.get_ref());
+    let report = service.report(query.into_inner()).await?;
+    let response = http_response_builder::ok(report);
+    Ok(HttpResponse::Ok().json(response))
+}
+#[get("/emergency/allocation/preview")]
+async fn allocation_preview(
+    query: web::Query<AllocationPreviewQuery>,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let mut service = EmergencyAllocationService::new(db_conn.get_ref());
+    if let Some(strategy) = &query.strategy {
+        let strategy: AllocationStrategy = strategy
+            .parse()
+            .map_err(|e| CustomError::new(HttpCodeW::BadRequest, e))?;
+        service = service.with_strategy(strategy);
+    }
+    let preview = service.preview().await?;
+    let response = http_response_builder::ok(preview);
+    Ok(HttpResponse::Ok().json(response))
+}
+#[patch("/emergency/{ic}/status")]
+async fn update_status(
+    ic: Path<String>,
+    payload: web::Json<EmergencyStatusPayload>,
+    subject: Subject,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service = EmergencyService::new(db_conn.get_ref());
+    let emergency = service
+        .update_status(&ic, payload.into_inner(), &subject.sub)
+        .await?;
+    let response = http_response_builder::ok(emergency);
+    Ok(HttpResponse::Ok().json(response))
+}
+#[post("/emergency/{ic}/assign")]
+async fn assign(
+    ic: Path<String>,
+    payload: web::Json<EmergencyAssignPayload>,
+    subject: Subject,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service = EmergencyService::new(db_conn.get_ref());
+    let emergency = service
+        .assign_ambulance(&ic, payload.into_inner(), &subject.sub)
+        .await?;
+    let response = http_response_builder::ok(emergency);
+    Ok(HttpResponse::Ok().json(response))
+}
+#[get("/emergency/{ic}/dispatch-decisions")]
+async fn dispatch_decisions(
+    ic: Path<String>,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service = EmergencyService::new(db_conn.get_ref());
+    let decisions = service.find_dispatch_decisions(&ic).await?;
+    let response = http_response_builder::ok(decisions);
+    Ok(HttpResponse::Ok().json(response))
+}
+#[post("/emergency/{ic}/merge")]
+async fn merge(
+    ic: Path<String>,
+    payload: web::Json<EmergencyMergePayload>,
+    subject: Subject,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service = EmergencyService::new(db_conn.get_ref());
+    let merges = service
+        .merge_emergencies(&ic, payload.into_inner(), &subject.sub)
+        .await?;
+    let response = http_response_builder::ok(merges);
+    Ok(HttpResponse::Ok().json(response))
+}
+#[get("/emergency/{ic}/merges")]
+async fn merge_history(
+    ic: Path<String>,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service = EmergencyService::new(db_conn.get_ref());
+    let merges = service.find_merges(&ic).await?;
+    let response = http_response_builder::ok(merges);
+    Ok(HttpResponse::Ok().json(response))
+}
+#[post("/emergency/{ic}/unlink")]
+async fn unlink(
+    ic: Path<String>,
+    subject: Subject,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service = EmergencyService::new(db_conn.get_ref());
+    let emergency = service.unlink_duplicate(&ic, &subject.sub).await?;
+    let response = http_response_builder::ok(emergency);
+    Ok(HttpResponse::Ok().json(response))
+}
+#[get("/emergency/{ic}/timeline")]
+async fn timeline(
+    ic: Path<String>,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service = EmergencyService::new(db_conn.get_ref());
+    let timeline = service.find_timeline(&ic).await?;
+    let response = http_response_builder::ok(timeline);
+    Ok(HttpResponse::Ok().json(response))
+}
 pub fn init_routes(config: &mut web::ServiceConfig) {
+    config.service(allocation_preview);
+    // Must come before `find` so `overdue`, `kpi` and `provisional` are not taken for an emergency ic
+    config.service(overdue);
+    config.service(kpi);
+    config.service(provisional);
     config.service(find);
     config.service(find_all);
     config.service(create);
+    config.service(update_status);
+    config.service(assign);
+    config.service(dispatch_decisions);
+    config.service(merge);
+    config.service(merge_history);
+    config.service(unlink);
+    config.service(timeline);
+    config.service(confirm);
+    config.service(reject);
+}
+
+/// Routes served without a token, registered outside the `JwtAuth` scope.
+pub fn init_public_routes(config: &mut web::ServiceConfig) {
+    config.service(report_public);
 }
@@ -0,0 +1,195 @@
+use log::error;
+
+use crate::components::emergency::enums::severity_rank;
+use crate::entity::sea_orm_active_enums::EmergencySeverityEnum;
+
+/// Environment variable selecting how the allocator pairs emergencies and ambulances.
+pub const ALLOCATION_STRATEGY_ENV: &str = "ALLOCATION_STRATEGY";
+
+/// Cost of a pair that must never be chosen (incapable vehicle).
+pub const INFEASIBLE: f64 = 1.0e12;
+
+/// Reward per unit of urgency for serving an emergency at all, in minutes of driving.
+///
+/// Longer than any real drive so the optimiser never leaves an emergency
+/// waiting just to save travel time.
+const SERVICE_REWARD_MINUTES: f64 = 100_000.0;
+
+/// Extra drive time charged for sending a fallback vehicle, in minutes.
+///
+/// Smaller than the service reward (a fallback beats nothing) but longer than
+/// any real drive (a required vehicle beats a fallback wherever it is).
+pub const FALLBACK_PENALTY_MINUTES: f64 = 50_000.0;
+
+/// Minutes of waiting that add one severity step worth of urgency.
+const WAIT_MINUTES_PER_STEP: f64 = 30.0;
+
+/// How pending emergencies are paired with available ambulances.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AllocationStrategy {
+    /// Emergencies are served one by one by severity, each taking the nearest capable vehicle.
+    #[default]
+    Greedy,
+    /// All emergencies and ambulances are assigned together by minimising the weighted cost.
+    Optimal,
+}
+
+impl AllocationStrategy {
+    /// Reads `ALLOCATION_STRATEGY` (`greedy` or `optimal`), defaulting to `Greedy`.
+    pub fn from_env() -> Self {
+        match std::env::var(ALLOCATION_STRATEGY_ENV) {
+            Ok(raw) => raw.parse().unwrap_or_else(|e| {
+                error!("{e}, using {:?}", Self::default());
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AllocationStrategy::Greedy => "GREEDY",
+            AllocationStrategy::Optimal => "OPTIMAL",
+        }
+    }
+}
+
+impl std::str::FromStr for AllocationStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "greedy" => Ok(Self::Greedy),
+            "optimal" | "hungarian" => Ok(Self::Optimal),
+            other => Err(format!("Invalid {ALLOCATION_STRATEGY_ENV} '{other}'")),
+        }
+    }
+}
+
+/// Most units the allocator sends to a single emergency.
+pub const MAX_UNITS_PER_EMERGENCY: usize = 12;
+
+/// Units an emergency needs: one per patient for serious cases, one per two
+/// patients otherwise, and always at least one for the initial assessment.
+pub fn required_units(patient_count: usize, severity: &EmergencySeverityEnum) -> usize {
+    let patients_per_unit = if severity_rank(severity) >= 4 { 1 } else { 2 };
+    patient_count
+        .div_ceil(patients_per_unit)
+        .clamp(1, MAX_UNITS_PER_EMERGENCY)
+}
+
+/// Urgency of an emergency: its severity, grown by the time it has been waiting.
+pub fn urgency_weight(severity: &EmergencySeverityEnum, waited_minutes: f64) -> f64 {
+    1.0 + f64::from(severity_rank(severity)) + waited_minutes.max(0.0) / WAIT_MINUTES_PER_STEP
+}
+
+/// Cost of sending a vehicle `eta_minutes` away to an emergency of the given urgency.
+///
+/// Drive time is scaled by urgency so critical cases get the fastest vehicles,
+/// and the urgency-scaled reward makes serving more urgent cases always cheaper
+/// when there are not enough vehicles for everyone.
+pub fn pair_cost(weight: f64, eta_minutes: f64, fallback: bool) -> f64 {
+    let travel = if fallback {
+        eta_minutes + FALLBACK_PENALTY_MINUTES
+    } else {
+        eta_minutes
+    };
+    weight * (travel - SERVICE_REWARD_MINUTES)
+}
+
+/// Solves the rectangular assignment problem with the Hungarian algorithm.
+///
+/// `cost[i][j]` is the cost of giving column `j` to row `i`. Returns, for each
+/// row, the column it receives; rows are left unassigned when there are more
+/// rows than columns or when every remaining column is `INFEASIBLE`.
+pub fn solve(cost: &[Vec<f64>]) -> Vec<Option<usize>> {
+    let rows = cost.len();
+    let cols = cost.first().map_or(0, Vec::len);
+    if rows == 0 || cols == 0 {
+        return vec![None; rows];
+    }
+
+    // The algorithm below needs rows <= columns, so solve the transpose otherwise.
+    let assignment = if rows <= cols {
+        hungarian(rows, cols, |i, j| cost[i][j])
+    } else {
+        let by_col = hungarian(cols, rows, |i, j| cost[j][i]);
+        let mut by_row = vec![None; rows];
+        for (col, row) in by_col.into_iter().enumerate() {
+            if let Some(row) = row {
+                by_row[row] = Some(col);
+            }
+        }
+        by_row
+    };
+
+    assignment
+        .into_iter()
+        .enumerate()
+        .map(|(i, j)| j.filter(|&j| cost[i][j] < INFEASIBLE))
+        .collect()
+}
+
+/// Shortest augmenting path variant with potentials, O(n²·m) for n rows <= m columns.
+fn hungarian(n: usize, m: usize, cost: impl Fn(usize, usize) -> f64) -> Vec<Option<usize>> {
+    // 1-based indices, column 0 is a virtual start column.
+    let mut u = vec![0.0; n + 1];
+    let mut v = vec![0.0; m + 1];
+    let mut col_owner = vec![0usize; m + 1];
+    let mut way = vec![0usize; m + 1];
+
+    for i in 1..=n {
+        col_owner[0] = i;
+        let mut j0 = 0;
+        let mut minv = vec![f64::INFINITY; m + 1];
+        let mut used = vec![false; m + 1];
+        loop {
+            used[j0] = true;
+            let i0 = col_owner[j0];
+            let mut delta = f64::INFINITY;
+            let mut j1 = 0;
+            for j in 1..=m {
+                if used[j] {
+                    continue;
+                }
+                let reduced = cost(i0 - 1, j - 1) - u[i0] - v[j];
+                if reduced < minv[j] {
+                    minv[j] = reduced;
+                    way[j] = j0;
+                }
+                if minv[j] < delta {
+                    delta = minv[j];
+                    j1 = j;
+                }
+            }
+            for j in 0..=m {
+                if used[j] {
+                    u[col_owner[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    minv[j] -= delta;
+                }
+            }
+            j0 = j1;
+            if col_owner[j0] == 0 {
+                break;
+            }
+        }
+        loop {
+            let j1 = way[j0];
+            col_owner[j0] = col_owner[j1];
+            j0 = j1;
+            if j0 == 0 {
+                break;
+            }
+        }
+    }
+
+    let mut assignment = vec![None; n];
+    for j in 1..=m {
+        if col_owner[j] != 0 {
+            assignment[col_owner[j] - 1] = Some(j - 1);
+        }
+    }
+    assignment
+}
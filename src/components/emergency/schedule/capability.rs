@@ -0,0 +1,207 @@
+use log::error;
+use serde::{Deserialize, Serialize};
+
+use crate::entity::sea_orm_active_enums::{
+    AmbulanceTypeEnum, EmergencyIncidentEnum, EmergencySeverityEnum,
+};
+
+use AmbulanceTypeEnum::*;
+use EmergencyIncidentEnum::*;
+
+/// Environment variable holding a JSON `CapabilityMatrix` that replaces the defaults.
+pub const CAPABILITY_MATRIX_ENV: &str = "DISPATCH_CAPABILITY_MATRIX";
+
+/// One row of the capability matrix.
+///
+/// A rule applies when the incident is in `incidents` and the severity is in
+/// `severities`; an empty list matches anything. `types` lists the vehicles
+/// that can handle the case; their order does not matter.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapabilityRule {
+    #[serde(default)]
+    pub incidents: Vec<EmergencyIncidentEnum>,
+    #[serde(default)]
+    pub severities: Vec<EmergencySeverityEnum>,
+    pub types: Vec<AmbulanceTypeEnum>,
+}
+
+impl CapabilityRule {
+    fn matches(&self, incident: &EmergencyIncidentEnum, severity: &EmergencySeverityEnum) -> bool {
+        (self.incidents.is_empty() || self.incidents.contains(incident))
+            && (self.severities.is_empty() || self.severities.contains(severity))
+    }
+}
+
+/// Maps an incident and severity to the ambulance types allowed to respond.
+///
+/// Rules are evaluated top to bottom and the first match wins. When none of the
+/// required vehicles is free, the allocator may send one of the `fallback`
+/// types instead; an empty fallback keeps the emergency waiting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapabilityMatrix {
+    pub rules: Vec<CapabilityRule>,
+    #[serde(default)]
+    pub fallback: Vec<AmbulanceTypeEnum>,
+}
+
+/// Which part of the matrix produced a match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CapabilityMatch {
+    Required,
+    Fallback,
+}
+
+impl CapabilityMatch {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CapabilityMatch::Required => "REQUIRED",
+            CapabilityMatch::Fallback => "FALLBACK",
+        }
+    }
+}
+
+impl CapabilityMatrix {
+    /// Loads the matrix from `DISPATCH_CAPABILITY_MATRIX`, falling back to the
+    /// built-in defaults when the variable is missing or malformed.
+    pub fn from_env() -> Self {
+        match std::env::var(CAPABILITY_MATRIX_ENV) {
+            Ok(raw) => serde_json::from_str(&raw).unwrap_or_else(|e| {
+                error!("Invalid {CAPABILITY_MATRIX_ENV}, using defaults: {e}");
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Ambulance types able to handle the given incident and severity.
+    pub fn required_types(
+        &self,
+        incident: &EmergencyIncidentEnum,
+        severity: &EmergencySeverityEnum,
+    ) -> &[AmbulanceTypeEnum] {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(incident, severity))
+            .map(|rule| rule.types.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Classifies a vehicle for a case: a required type, an acceptable fallback, or neither.
+    pub fn classify(
+        &self,
+        incident: &EmergencyIncidentEnum,
+        severity: &EmergencySeverityEnum,
+        ambulance_type: &AmbulanceTypeEnum,
+    ) -> Option<CapabilityMatch> {
+        if self
+            .required_types(incident, severity)
+            .contains(ambulance_type)
+        {
+            Some(CapabilityMatch::Required)
+        } else if self.fallback.contains(ambulance_type) {
+            Some(CapabilityMatch::Fallback)
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for CapabilityMatrix {
+    fn default() -> Self {
+        let critical = vec![
+            EmergencySeverityEnum::Critical,
+            EmergencySeverityEnum::Severe,
+            EmergencySeverityEnum::Extreme,
+            EmergencySeverityEnum::Unstable,
+        ];
+        CapabilityMatrix {
+            rules: vec![
+                CapabilityRule {
+                    incidents: vec![ChemicalSpill, GasLeak, RadiationExposure, BiologicalHazard],
+                    severities: vec![],
+                    types: vec![HazmatAmbulance],
+                },
+                CapabilityRule {
+                    incidents: vec![Childbirth],
+                    severities: vec![],
+                    types: vec![NeonatalAmbulance, PediatricAmbulance],
+                },
+                CapabilityRule {
+                    incidents: vec![ShipAccident, Flood, Tsunami],
+                    severities: vec![],
+                    types: vec![WaterAmbulance, RescueAmbulance],
+                },
+                CapabilityRule {
+                    incidents: vec![
+                        BuildingCollapse,
+                        BridgeCollapse,
+                        Earthquake,
+                        Landslide,
+                        TrainAccident,
+                        AirplaneCrash,
+                        IndustrialAccident,
+                        Explosion,
+                        DamFailure,
+                    ],
+                    severities: vec![],
+                    types: vec![
+                        RescueAmbulance,
+                        MobileIntensiveCareUnit,
+                        AdvancedLifeSupport,
+                    ],
+                },
+                CapabilityRule {
+                    incidents: vec![],
+                    severities: critical,
+                    types: vec![
+                        AdvancedLifeSupport,
+                        MobileIntensiveCareUnit,
+                        CriticalCareTransport,
+                    ],
+                },
+                CapabilityRule {
+                    incidents: vec![
+                        HeartAttack,
+                        Stroke,
+                        Seizure,
+                        BreathingProblem,
+                        AllergicReaction,
+                        Electrocution,
+                        SevereBurns,
+                        Drowning,
+                        Shooting,
+                        Stabbing,
+                    ],
+                    severities: vec![],
+                    types: vec![
+                        AdvancedLifeSupport,
+                        MobileIntensiveCareUnit,
+                        CriticalCareTransport,
+                    ],
+                },
+                CapabilityRule {
+                    incidents: vec![],
+                    severities: vec![],
+                    types: vec![
+                        BasicLifeSupport,
+                        AdvancedLifeSupport,
+                        MobileIntensiveCareUnit,
+                        RescueAmbulance,
+                        PediatricAmbulance,
+                        BariatricAmbulance,
+                        CriticalCareTransport,
+                        RapidResponseVehicle,
+                    ],
+                },
+            ],
+            fallback: vec![
+                AdvancedLifeSupport,
+                MobileIntensiveCareUnit,
+                CriticalCareTransport,
+                BasicLifeSupport,
+                RescueAmbulance,
+            ],
+        }
+    }
+}
@@ -1,35 +1,161 @@
-use chrono::Local;
-use log::{error, warn};
+use chrono::{Local, NaiveDateTime};
+use log::{error, info, warn};
+use sea_orm::prelude::Uuid;
+use sea_orm::sea_query::{LockBehavior, LockType, OnConflict};
 use sea_orm::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 
-use crate::entity::sea_orm_active_enums::{AmbulanceStatusEnum, EmergencyStatusEnum};
-use crate::entity::{ambulance, emergency};
-use crate::http_response::error_handler::CustomError;
+use crate::components::ambulance::compliance::CompliancePolicy;
+use crate::components::ambulance::crew;
+use crate::components::emergency::duplicates::covered_by_parent;
+use crate::components::emergency::enums::severity_rank;
+use crate::components::emergency::history::{ChangeField, ChangeLog};
+use crate::components::emergency::lifecycle::{Milestone, apply_milestone};
+use crate::components::emergency::schedule::assignment::{
+    AllocationStrategy, INFEASIBLE, pair_cost, required_units, solve, urgency_weight,
+};
+use crate::components::emergency::schedule::capability::{CapabilityMatch, CapabilityMatrix};
+use crate::entity::dispatch_decision::DispatchCandidate;
+use crate::entity::sea_orm_active_enums::{
+    AmbulanceStatusEnum, AmbulanceTypeEnum, EmergencyStatusEnum, EmergencyVerificationEnum,
+};
+use crate::entity::{
+    ambulance, dispatch_decision, dispatch_override, emergency, emergency_ambulance,
+    emergency_patient,
+};
 use crate::http_response::HttpCodeW;
-use crate::utils::helpers::{calculate_distance, now_time};
+use crate::http_response::error_handler::CustomError;
+use crate::utils::eta::{Travel, TravelSnapshot, estimate_matrix};
+use crate::utils::helpers::{calculate_distance, env_or, now_time};
+use crate::utils::spatial::{BoundingBox, Geolocated, any_bounding_box_condition};
+
+/// Key of the transaction-scoped advisory lock serialising allocation runs across replicas.
+const ALLOCATION_LOCK_KEY: i64 = 0x414d_4255_4c41_4e43; // "AMBULANC"
+
+/// Environment variable with the candidate search radius around each emergency, in km.
+pub const ALLOCATION_SEARCH_RADIUS_ENV: &str = "ALLOCATION_SEARCH_RADIUS_KM";
+const DEFAULT_SEARCH_RADIUS_KM: f64 = 50.0;
+/// Most emergencies whose search areas are combined into one query.
+const MAX_SEARCH_AREAS: usize = 100;
+
+/// Candidates kept in each dispatch decision record.
+const MAX_RECORDED_CANDIDATES: usize = 10;
 
 pub struct EmergencyAllocationService {
     conn: DatabaseConnection,
+    capabilities: CapabilityMatrix,
+    strategy: AllocationStrategy,
+    /// Radius around each emergency searched first for candidates, 0 for the whole fleet.
+    search_radius_km: f64,
+    /// Vehicles overdue for service or insurance are never sent.
+    compliance: CompliancePolicy,
+}
+
+/// One emergency/ambulance pair chosen by the planner, indices into the fetched lists.
+struct PlannedDispatch {
+    emergency_index: usize,
+    ambulance_index: usize,
+    assessment: Assessment,
+}
+
+/// How well a vehicle fits an emergency.
+#[derive(Clone, Copy)]
+struct Assessment {
+    capability: CapabilityMatch,
+    distance_km: f64,
+    eta_seconds: f64,
+    /// Urgency-weighted cost, lower is better (see `assignment::pair_cost`).
+    score: f64,
+}
+
+/// Query of `GET /emergency/allocation/preview`; `strategy` overrides `ALLOCATION_STRATEGY`.
+#[derive(Debug, Deserialize)]
+pub struct AllocationPreviewQuery {
+    pub strategy: Option<String>,
+}
+
+/// What the allocator would do right now, see `EmergencyAllocationService::preview`.
+#[derive(Debug, Serialize)]
+pub struct AllocationPreview {
+    pub strategy: String,
+    pub generated_at: NaiveDateTime,
+    pub pending_emergencies: usize,
+    pub requested_units: usize,
+    pub available_ambulances: usize,
+    pub assignments: Vec<PreviewAssignment>,
+    pub unmatched: Vec<PreviewUnmatched>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreviewAssignment {
+    pub emergency_id: Uuid,
+    pub emergency_ic: String,
+    pub ambulance_id: Uuid,
+    pub ambulance_ic: i32,
+    pub capability: String,
+    pub distance_km: f64,
+    pub eta_seconds: i32,
+    pub score: f64,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreviewUnmatched {
+    pub emergency_id: Uuid,
+    pub emergency_ic: String,
+    /// Units still needed after the proposed assignments.
+    pub missing_units: usize,
+    pub reason: String,
 }
 
 impl EmergencyAllocationService {
     pub fn new(conn: &DatabaseConnection) -> Self {
-        Self { conn: conn.clone() }
+        Self {
+            conn: conn.clone(),
+            capabilities: CapabilityMatrix::from_env(),
+            strategy: AllocationStrategy::from_env(),
+            search_radius_km: env_or(ALLOCATION_SEARCH_RADIUS_ENV, DEFAULT_SEARCH_RADIUS_KM),
+            compliance: CompliancePolicy::from_env(),
+        }
+    }
+
+    /// Same service with a different strategy, used to compare strategies in previews.
+    pub fn with_strategy(mut self, strategy: AllocationStrategy) -> Self {
+        self.strategy = strategy;
+        self
     }
 
     fn clone(&self) -> Self {
         Self {
             conn: self.conn.clone(),
+            capabilities: self.capabilities.clone(),
+            strategy: self.strategy,
+            search_radius_km: self.search_radius_km,
+            compliance: self.compliance,
         }
     }
 
     pub async fn run_allocation_process(&self) -> Result<String, CustomError> {
         println!("Starting emergency allocation process");
 
+        // Route on an unlocked read first; graph searches are CPU-bound and
+        // would otherwise run while holding the allocation and row locks
+        let (emergencies, _) = Self::fetch_unit_demand(&self.conn, false).await?;
+        let ambulances = self
+            .fetch_available_ambulances(&self.conn, false, &emergencies)
+            .await?;
+        let targets = travel_targets(&emergencies);
+        let vehicles = travel_vehicles(&ambulances);
+        let matrix = travel_matrix(targets.clone(), vehicles.clone()).await?;
+        let routed = TravelSnapshot::new(&targets, &vehicles, &matrix);
+
         let this = self.clone();
         self.conn
             .transaction(|txn| {
-                Box::pin(async move { this.allocate_emergencies_in_transaction(txn).await })
+                Box::pin(
+                    async move { this.allocate_emergencies_in_transaction(txn, &routed).await },
+                )
             })
             .await
             .map_err(|e| {
@@ -41,22 +167,131 @@ impl EmergencyAllocationService {
             })
     }
 
+    /// Runs the matching on the current pending emergencies and available
+    /// ambulances without dispatching anything or taking any lock.
+    pub async fn preview(&self) -> Result<AllocationPreview, CustomError> {
+        let (emergencies, demand) = Self::fetch_unit_demand(&self.conn, false).await?;
+        let ambulances = self
+            .fetch_available_ambulances(&self.conn, false, &emergencies)
+            .await?;
+        let travel =
+            travel_matrix(travel_targets(&emergencies), travel_vehicles(&ambulances)).await?;
+        Ok(self.preview_plan(&emergencies, &demand, &ambulances, &travel, now_time()))
+    }
+
+    /// The preview of `plan` on rows already read; builds the report only.
+    pub(crate) fn preview_plan(
+        &self,
+        emergencies: &[emergency::Model],
+        demand: &[usize],
+        ambulances: &[ambulance::Model],
+        travel: &[Vec<Travel>],
+        now: NaiveDateTime,
+    ) -> AllocationPreview {
+        let plan = self.plan(emergencies, demand, ambulances, travel, now);
+
+        let assignments = plan
+            .iter()
+            .map(|planned| {
+                let emergency = &emergencies[planned.emergency_index];
+                let ambulance = &ambulances[planned.ambulance_index];
+                let reason = match planned.assessment.capability {
+                    CapabilityMatch::Required => format!(
+                        "{:?} is a required type for {:?} ({:?})",
+                        ambulance.r#type, emergency.incident_type, emergency.severity
+                    ),
+                    CapabilityMatch::Fallback => format!(
+                        "No required type free for {:?} ({:?}), {:?} sent as fallback",
+                        emergency.incident_type, emergency.severity, ambulance.r#type
+                    ),
+                };
+                PreviewAssignment {
+                    emergency_id: emergency.id,
+                    emergency_ic: emergency.emergency_ic.clone(),
+                    ambulance_id: ambulance.id,
+                    ambulance_ic: ambulance.ambulance_ic,
+                    capability: planned.assessment.capability.as_str().to_string(),
+                    distance_km: planned.assessment.distance_km,
+                    eta_seconds: planned.assessment.eta_seconds.round() as i32,
+                    score: planned.assessment.score,
+                    reason,
+                }
+            })
+            .collect();
+
+        let unmatched = emergencies
+            .iter()
+            .enumerate()
+            .filter_map(|(index, emergency)| {
+                let planned = plan.iter().filter(|p| p.emergency_index == index).count();
+                let missing_units = demand[index] - planned;
+                (missing_units > 0).then_some((emergency, missing_units))
+            })
+            .map(|(emergency, missing_units)| {
+                let reason = if ambulances.is_empty() {
+                    "No ambulances available".to_string()
+                } else if ambulances.iter().all(|a| {
+                    self.capabilities
+                        .classify(&emergency.incident_type, &emergency.severity, &a.r#type)
+                        .is_none()
+                }) {
+                    format!(
+                        "No available vehicle can handle {:?} ({:?}), requires one of {:?}",
+                        emergency.incident_type,
+                        emergency.severity,
+                        self.capabilities
+                            .required_types(&emergency.incident_type, &emergency.severity)
+                    )
+                } else {
+                    "All capable vehicles were assigned to other emergencies".to_string()
+                };
+                PreviewUnmatched {
+                    emergency_id: emergency.id,
+                    emergency_ic: emergency.emergency_ic.clone(),
+                    missing_units,
+                    reason,
+                }
+            })
+            .collect();
+
+        AllocationPreview {
+            strategy: self.strategy.as_str().to_string(),
+            generated_at: now,
+            pending_emergencies: emergencies.len(),
+            requested_units: demand.iter().sum(),
+            available_ambulances: ambulances.len(),
+            assignments,
+            unmatched,
+        }
+    }
+
+    /// Dispatches under the allocation lock, taking drive times from `routed`
+    /// for the rows that have not moved since it was computed.
     async fn allocate_emergencies_in_transaction(
         &self,
         txn: &DatabaseTransaction,
+        routed: &TravelSnapshot,
     ) -> Result<String, CustomError> {
-        let pending_emergencies = Self::fetch_pending_emergencies(txn).await?;
+        if !Self::try_acquire_allocation_lock(txn).await? {
+            warn!("Skipping allocation - another instance holds the allocation lock");
+            return Ok("Allocation already running on another instance".to_string());
+        }
+
+        let (pending_emergencies, demand) = Self::fetch_unit_demand(txn, true).await?;
         if pending_emergencies.is_empty() {
             println!("No pending emergencies found for allocation");
             return Ok("No pending emergencies found for allocation".to_string());
         }
 
         println!(
-            "Found {} pending emergencies for allocation",
-            pending_emergencies.len()
+            "Found {} emergencies needing {} units for allocation",
+            pending_emergencies.len(),
+            demand.iter().sum::<usize>()
         );
 
-        let available_ambulances = Self::fetch_available_ambulances(txn).await?;
+        let available_ambulances = self
+            .fetch_available_ambulances(txn, true, &pending_emergencies)
+            .await?;
         if available_ambulances.is_empty() {
             warn!("No available ambulances found for allocation");
             return Ok("No available ambulances found for allocation".to_string());
@@ -64,60 +299,68 @@ impl EmergencyAllocationService {
 
         println!("Found {} available ambulances", available_ambulances.len());
 
-        // Create a mutable copy of ambulances that we can modify
-        let mut available_ambulances = available_ambulances;
-        let emergency_count = pending_emergencies.len();
+        let now = now_time();
+        let travel = routed.matrix(
+            &travel_targets(&pending_emergencies),
+            &travel_vehicles(&available_ambulances),
+        );
+        let plan = self.plan(
+            &pending_emergencies,
+            &demand,
+            &available_ambulances,
+            &travel,
+            now,
+        );
         let mut dispatched_count = 0;
+        let mut served = HashSet::new();
 
-        for (index, emergency) in pending_emergencies.into_iter().enumerate() {
-            if available_ambulances.is_empty() {
-                println!(
-                    "No more ambulances available for allocation. Remaining pending emergencies: {}",
-                    emergency_count - index
+        for planned in &plan {
+            let emergency = &pending_emergencies[planned.emergency_index];
+            let ambulance = &available_ambulances[planned.ambulance_index];
+            if planned.assessment.capability == CapabilityMatch::Fallback {
+                warn!(
+                    "No required vehicle free for emergency {} ({:?}, {:?}), using fallback vehicle",
+                    emergency.id, emergency.incident_type, emergency.severity
                 );
-                break;
             }
 
-            let lat = emergency
-                .emergency_latitude
-                .to_string()
-                .parse::<f64>()
-                .unwrap_or(0.0);
-            let lon = emergency
-                .emergency_longitude
-                .to_string()
-                .parse::<f64>()
-                .unwrap_or(0.0);
-
-            if let Some(idx) = Self::find_closest_ambulance_index(lat, lon, &available_ambulances) {
-                let ambulance = available_ambulances[idx].clone();
-
-                match dispatch_ambulance(txn, &ambulance, &emergency).await {
-                    Ok(_) => {
-                        println!(
-                            "Successfully dispatched ambulance {} to emergency {}",
-                            ambulance.id, emergency.id
-                        );
-
-                        // Remove the dispatched ambulance from our working list
-                        available_ambulances.swap_remove(idx);
-                        dispatched_count += 1;
-
-                        println!(
-                            "Removed ambulance {} from available list. Remaining ambulances: {}",
-                            ambulance.id,
-                            available_ambulances.len()
-                        );
-                    }
-                    Err(e) => {
-                        error!("Failed to dispatch ambulance: {e}");
-                        return Err(e);
-                    }
+            let decision = self.decision_for(
+                planned,
+                emergency,
+                &available_ambulances,
+                &travel[planned.emergency_index],
+                &plan,
+                now,
+            );
+            let first_unit = served.insert(emergency.id);
+            match dispatch_ambulance(txn, ambulance, emergency, decision, first_unit).await {
+                Ok(_) => {
+                    info!(
+                        "Dispatched ambulance {} to emergency {} ({:.2} km, ETA {:.1} min)",
+                        ambulance.id,
+                        emergency.id,
+                        planned.assessment.distance_km,
+                        planned.assessment.eta_seconds / 60.0
+                    );
+                    dispatched_count += 1;
                 }
-            } else {
-                println!(
-                    "Could not find a closest ambulance for emergency {}",
-                    emergency.id
+                Err(e) => {
+                    error!("Failed to dispatch ambulance: {e}");
+                    return Err(e);
+                }
+            }
+        }
+
+        for (index, emergency) in pending_emergencies.iter().enumerate() {
+            let planned = plan.iter().filter(|p| p.emergency_index == index).count();
+            if planned < demand[index] {
+                warn!(
+                    "No capable ambulance available for {} of {} units of emergency {} ({:?}, {:?})",
+                    demand[index] - planned,
+                    demand[index],
+                    emergency.id,
+                    emergency.incident_type,
+                    emergency.severity
                 );
             }
         }
@@ -130,91 +373,402 @@ impl EmergencyAllocationService {
         ))
     }
 
-    async fn fetch_pending_emergencies(
-        txn: &DatabaseTransaction,
+    /// Takes the cluster-wide allocation lock for the lifetime of `txn`.
+    ///
+    /// Returns `false` without waiting when another replica is already allocating;
+    /// Postgres releases the lock on commit or rollback, even if the holder crashes.
+    async fn try_acquire_allocation_lock(txn: &DatabaseTransaction) -> Result<bool, CustomError> {
+        let row = txn
+            .query_one(Statement::from_sql_and_values(
+                txn.get_database_backend(),
+                "SELECT pg_try_advisory_xact_lock($1) AS locked",
+                [ALLOCATION_LOCK_KEY.into()],
+            ))
+            .await?;
+        Ok(match row {
+            Some(row) => row.try_get::<bool>("", "locked")?,
+            None => false,
+        })
+    }
+
+    /// Emergencies still short of units, with how many more each one needs.
+    ///
+    /// Waiting cases need their first units; cases already in progress may need
+    /// more when the patient count calls for several vehicles.
+    async fn fetch_unit_demand<C: ConnectionTrait>(
+        conn: &C,
+        lock: bool,
+    ) -> Result<(Vec<emergency::Model>, Vec<usize>), CustomError> {
+        let emergencies = Self::fetch_pending_emergencies(conn, lock).await?;
+        let ids: Vec<Uuid> = emergencies.iter().map(|e| e.id).collect();
+        let patients = emergency_patient::Entity::find()
+            .filter(emergency_patient::Column::EmergencyId.is_in(ids.clone()))
+            .all(conn)
+            .await?;
+        let units = emergency_ambulance::Entity::find()
+            .filter(emergency_ambulance::Column::EmergencyId.is_in(ids))
+            .filter(emergency_ambulance::Column::ReleasedAt.is_null())
+            .all(conn)
+            .await?;
+
+        Ok(emergencies
+            .into_iter()
+            .filter_map(|emergency| {
+                let patient_count = patients
+                    .iter()
+                    .filter(|p| p.emergency_id == emergency.id)
+                    .count();
+                let active = units
+                    .iter()
+                    .filter(|u| u.emergency_id == emergency.id)
+                    .count();
+                let missing =
+                    required_units(patient_count, &emergency.severity).saturating_sub(active);
+                (missing > 0).then_some((emergency, missing))
+            })
+            .unzip())
+    }
+
+    /// Confirmed pending, escalated and in-progress emergencies, most urgent
+    /// first and oldest first within the same severity. Provisional public
+    /// reports wait for confirmation.
+    ///
+    /// With `lock`, rows held by a concurrent dispatch are skipped and the
+    /// returned rows stay locked until the transaction ends.
+    async fn fetch_pending_emergencies<C: ConnectionTrait>(
+        conn: &C,
+        lock: bool,
     ) -> Result<Vec<emergency::Model>, CustomError> {
-        emergency::Entity::find()
-            .filter(emergency::Column::Status.eq(EmergencyStatusEnum::Pending))
+        let mut query = emergency::Entity::find()
+            .filter(emergency::Column::Status.is_in([
+                EmergencyStatusEnum::Pending,
+                EmergencyStatusEnum::Escalated,
+                EmergencyStatusEnum::InProgress,
+            ]))
+            .filter(emergency::Column::VerificationStatus.eq(EmergencyVerificationEnum::Verified))
             .order_by_desc(emergency::Column::Severity)
             .order_by_asc(emergency::Column::CreatedAt)
-            .limit(1000)
-            .all(txn)
-            .await
-            .map_err(|e| {
-                CustomError::new(
-                    HttpCodeW::InternalServerError,
-                    format!("Failed to fetch pending emergencies: {e}"),
+            .limit(1000);
+        if lock {
+            query = query.lock_with_behavior(LockType::Update, LockBehavior::SkipLocked);
+        }
+        let mut emergencies = query.all(conn).await.map_err(|e| {
+            CustomError::new(
+                HttpCodeW::InternalServerError,
+                format!("Failed to fetch pending emergencies: {e}"),
+            )
+        })?;
+        // Duplicates are staffed through their parent while it is open
+        let covered = covered_by_parent(conn, &emergencies).await?;
+        emergencies.retain(|e| !covered.contains(&e.id));
+        emergencies.sort_by(|a, b| {
+            severity_rank(&b.severity)
+                .cmp(&severity_rank(&a.severity))
+                .then(a.created_at.cmp(&b.created_at))
+        });
+        Ok(emergencies)
+    }
+
+    /// Available ambulances near the emergencies; `lock` behaves as in
+    /// `fetch_pending_emergencies`.
+    ///
+    /// Candidates are first looked up within `search_radius_km` of each
+    /// emergency through the geohash index. The search widens to the whole
+    /// fleet when an emergency has no vehicle in range, so remote cases are
+    /// still served, or when there are too many emergencies to list their areas.
+    async fn fetch_available_ambulances<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        lock: bool,
+        emergencies: &[emergency::Model],
+    ) -> Result<Vec<ambulance::Model>, CustomError> {
+        let radius_km = self.search_radius_km;
+        if radius_km > 0.0 && !emergencies.is_empty() && emergencies.len() <= MAX_SEARCH_AREAS {
+            let areas: Vec<BoundingBox> = emergencies
+                .iter()
+                .map(|emergency| {
+                    let (latitude, longitude) = emergency::Entity::coordinates(emergency);
+                    BoundingBox::around(latitude, longitude, radius_km)
+                })
+                .collect();
+            let nearby = self
+                .query_available_ambulances(
+                    conn,
+                    lock,
+                    Some(any_bounding_box_condition::<ambulance::Entity>(&areas)),
                 )
-            })
+                .await?;
+            let all_covered = emergencies.iter().all(|emergency| {
+                let (latitude, longitude) = emergency::Entity::coordinates(emergency);
+                nearby.iter().any(|ambulance| {
+                    let (lat, lon) = ambulance::Entity::coordinates(ambulance);
+                    calculate_distance(latitude, longitude, lat, lon) <= radius_km
+                })
+            });
+            if all_covered {
+                return Ok(nearby);
+            }
+            println!(
+                "Some emergencies have no ambulance within {radius_km} km, searching the whole fleet"
+            );
+        }
+        self.query_available_ambulances(conn, lock, None).await
     }
 
-    async fn fetch_available_ambulances(
-        txn: &DatabaseTransaction,
+    async fn query_available_ambulances<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        lock: bool,
+        area: Option<Condition>,
     ) -> Result<Vec<ambulance::Model>, CustomError> {
-        ambulance::Entity::find()
+        let mut query = ambulance::Entity::find()
             .filter(ambulance::Column::Status.eq(AmbulanceStatusEnum::Available))
-            .limit(1000)
-            .all(txn)
+            .filter(self.compliance.condition(now_time()))
+            .limit(1000);
+        if let Some(area) = area {
+            query = query.filter(area);
+        }
+        if lock {
+            query = query.lock_with_behavior(LockType::Update, LockBehavior::SkipLocked);
+        }
+        let ambulances = query.all(conn).await.map_err(|e| {
+            CustomError::new(
+                HttpCodeW::InternalServerError,
+                format!("Failed to fetch available ambulances: {e}"),
+            )
+        })?;
+        crew::retain_crewed(conn, ambulances, now_time())
             .await
             .map_err(|e| {
                 CustomError::new(
                     HttpCodeW::InternalServerError,
-                    format!("Failed to fetch available ambulances: {e}"),
+                    format!("Failed to fetch ambulance crews: {e}"),
                 )
             })
     }
 
-    fn find_closest_ambulance_index(
-        emergency_lat: f64,
-        emergency_lon: f64,
+    /// Pairs emergencies with ambulances using the configured strategy.
+    ///
+    /// `emergencies` must already be ordered by priority; the greedy strategy
+    /// serves them in that order. Each emergency asks for `demand[i]` units,
+    /// so it may appear several times in the plan.
+    fn plan(
+        &self,
+        emergencies: &[emergency::Model],
+        demand: &[usize],
         ambulances: &[ambulance::Model],
-    ) -> Option<usize> {
-        if ambulances.is_empty() {
-            return None;
+        travel: &[Vec<Travel>],
+        now: NaiveDateTime,
+    ) -> Vec<PlannedDispatch> {
+        // One slot per requested unit, pointing back at its emergency
+        let slots: Vec<usize> = demand
+            .iter()
+            .enumerate()
+            .flat_map(|(index, units)| std::iter::repeat_n(index, *units))
+            .collect();
+        match self.strategy {
+            AllocationStrategy::Greedy => {
+                self.plan_greedy(emergencies, &slots, ambulances, travel, now)
+            }
+            AllocationStrategy::Optimal => {
+                self.plan_optimal(emergencies, &slots, ambulances, travel, now)
+            }
         }
+    }
 
-        let mut closest_index = 0;
-        let mut shortest_distance = calculate_distance(
-            emergency_lat,
-            emergency_lon,
-            ambulances[0]
-                .location_latitude
-                .to_string()
-                .parse::<f64>()
-                .unwrap_or(0.0),
-            ambulances[0]
-                .location_longitude
-                .to_string()
-                .parse::<f64>()
-                .unwrap_or(0.0),
-        );
+    /// Serves each emergency in turn with the fastest capable vehicle still free.
+    ///
+    /// Vehicles of a required type always win over fallback vehicles, whatever
+    /// the ETA; vehicles outside the capability matrix are never picked.
+    fn plan_greedy(
+        &self,
+        emergencies: &[emergency::Model],
+        slots: &[usize],
+        ambulances: &[ambulance::Model],
+        travel: &[Vec<Travel>],
+        now: NaiveDateTime,
+    ) -> Vec<PlannedDispatch> {
+        let mut taken = vec![false; ambulances.len()];
+        let mut plan = Vec::new();
+
+        for &emergency_index in slots {
+            let emergency = &emergencies[emergency_index];
+            let closest = ambulances
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !taken[*i])
+                .filter_map(|(i, a)| {
+                    let travel = &travel[emergency_index][i];
+                    Some((i, self.assess(emergency, a, travel, now)?))
+                })
+                .min_by(|(_, a), (_, b)| {
+                    (a.capability == CapabilityMatch::Fallback)
+                        .cmp(&(b.capability == CapabilityMatch::Fallback))
+                        .then(a.eta_seconds.total_cmp(&b.eta_seconds))
+                });
 
-        for (i, a) in ambulances.iter().enumerate().skip(1) {
-            let lat = a
-                .location_latitude
-                .to_string()
-                .parse::<f64>()
-                .unwrap_or(0.0);
-            let lon = a
-                .location_longitude
-                .to_string()
-                .parse::<f64>()
-                .unwrap_or(0.0);
-            let distance = calculate_distance(emergency_lat, emergency_lon, lat, lon);
-
-            if distance < shortest_distance {
-                shortest_distance = distance;
-                closest_index = i;
+            if let Some((ambulance_index, assessment)) = closest {
+                taken[ambulance_index] = true;
+                plan.push(PlannedDispatch {
+                    emergency_index,
+                    ambulance_index,
+                    assessment,
+                });
             }
         }
+        plan
+    }
 
-        Some(closest_index)
+    /// Assigns all emergencies and ambulances at once by minimising the total score.
+    fn plan_optimal(
+        &self,
+        emergencies: &[emergency::Model],
+        slots: &[usize],
+        ambulances: &[ambulance::Model],
+        travel: &[Vec<Travel>],
+        now: NaiveDateTime,
+    ) -> Vec<PlannedDispatch> {
+        let assessments: Vec<Vec<Option<Assessment>>> = emergencies
+            .iter()
+            .zip(travel)
+            .map(|(e, row)| {
+                ambulances
+                    .iter()
+                    .zip(row)
+                    .map(|(a, travel)| self.assess(e, a, travel, now))
+                    .collect()
+            })
+            .collect();
+
+        let cost: Vec<Vec<f64>> = slots
+            .iter()
+            .map(|&emergency_index| {
+                assessments[emergency_index]
+                    .iter()
+                    .map(|cell| cell.map_or(INFEASIBLE, |a| a.score))
+                    .collect()
+            })
+            .collect();
+
+        solve(&cost)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(slot, ambulance_index)| {
+                let ambulance_index = ambulance_index?;
+                let emergency_index = slots[slot];
+                Some(PlannedDispatch {
+                    emergency_index,
+                    ambulance_index,
+                    assessment: assessments[emergency_index][ambulance_index]?,
+                })
+            })
+            .collect()
+    }
+
+    /// Capability, travel and score of a vehicle for an emergency, `None` if it cannot respond.
+    fn assess(
+        &self,
+        emergency: &emergency::Model,
+        ambulance: &ambulance::Model,
+        travel: &Travel,
+        now: NaiveDateTime,
+    ) -> Option<Assessment> {
+        let capability = self.capabilities.classify(
+            &emergency.incident_type,
+            &emergency.severity,
+            &ambulance.r#type,
+        )?;
+        let waited = (now - emergency.created_at).num_seconds() as f64 / 60.0;
+        let score = pair_cost(
+            urgency_weight(&emergency.severity, waited),
+            travel.eta_minutes(),
+            capability == CapabilityMatch::Fallback,
+        );
+        Some(Assessment {
+            capability,
+            distance_km: travel.distance_km,
+            eta_seconds: travel.eta_seconds,
+            score,
+        })
+    }
+
+    /// Audit row for a planned dispatch, listing every capable vehicle best first.
+    fn decision_for(
+        &self,
+        planned: &PlannedDispatch,
+        emergency: &emergency::Model,
+        ambulances: &[ambulance::Model],
+        travel: &[Travel],
+        plan: &[PlannedDispatch],
+        now: NaiveDateTime,
+    ) -> dispatch_decision::ActiveModel {
+        let candidate = |index: usize, assessment: &Assessment| DispatchCandidate {
+            ambulance_id: ambulances[index].id,
+            ambulance_ic: ambulances[index].ambulance_ic,
+            ambulance_type: ambulances[index].r#type.clone(),
+            capability: assessment.capability.as_str().to_string(),
+            distance_km: assessment.distance_km,
+            eta_seconds: Some(assessment.eta_seconds.round() as i32),
+            score: assessment.score,
+            chosen: index == planned.ambulance_index,
+            assigned_elsewhere: plan.iter().any(|p| {
+                p.ambulance_index == index && p.emergency_index != planned.emergency_index
+            }),
+        };
+        let candidates = ambulances
+            .iter()
+            .enumerate()
+            .filter_map(|(index, ambulance)| {
+                let assessment = self.assess(emergency, ambulance, &travel[index], now)?;
+                Some(candidate(index, &assessment))
+            })
+            .collect();
+        decision_record(
+            self.strategy,
+            emergency.id,
+            &candidate(planned.ambulance_index, &planned.assessment),
+            candidates,
+            now,
+        )
     }
 }
+
+/// Audit row for sending `chosen` to an emergency. Lists it with the best of
+/// the other vehicles considered, `MAX_RECORDED_CANDIDATES` in all, best first;
+/// the chosen one stays even when the optimiser picked it below that cut.
+pub(crate) fn decision_record(
+    strategy: AllocationStrategy,
+    emergency_id: Uuid,
+    chosen: &DispatchCandidate,
+    mut candidates: Vec<DispatchCandidate>,
+    now: NaiveDateTime,
+) -> dispatch_decision::ActiveModel {
+    candidates.retain(|c| !c.chosen);
+    candidates.sort_by(|a, b| a.score.total_cmp(&b.score));
+    candidates.truncate(MAX_RECORDED_CANDIDATES - 1);
+    let at = candidates.partition_point(|c| c.score <= chosen.score);
+    candidates.insert(at, chosen.clone());
+
+    dispatch_decision::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        emergency_id: Set(emergency_id),
+        ambulance_id: Set(chosen.ambulance_id),
+        strategy: Set(strategy.as_str().to_string()),
+        capability: Set(chosen.capability.clone()),
+        distance_km: Set(chosen.distance_km),
+        score: Set(chosen.score),
+        eta_seconds: Set(chosen.eta_seconds),
+        candidates: Set(serde_json::to_value(candidates).unwrap_or_default()),
+        created_at: Set(now),
+    }
+}
+
 async fn dispatch_ambulance(
     txn: &DatabaseTransaction,
     ambulance: &ambulance::Model,
     emergency: &emergency::Model,
+    decision: dispatch_decision::ActiveModel,
+    first_unit: bool,
 ) -> Result<String, CustomError> {
     println!(
         "Starting dispatch_ambulance function for emergency: {} and ambulance: {}",
@@ -231,41 +785,83 @@ async fn dispatch_ambulance(
         ambulance.id, ambulance.status
     );
 
-    let mut emergency_active_model: emergency::ActiveModel = emergency.clone().into();
-    emergency_active_model.status = Set(EmergencyStatusEnum::InProgress);
-    emergency_active_model.ambulance_id = Set(Some(ambulance.id));
-    emergency_active_model.updated_at = Set(now_time());
-
-    println!(
-        "Attempting to update emergency with status: {:?} and ambulance_id: {:?}",
-        EmergencyStatusEnum::InProgress,
-        ambulance.id
-    );
-
-    match emergency_active_model.update(txn).await {
-        Ok(updated) => {
-            println!(
-                "Emergency update SUCCESS - ID: {}, New Status: {:?}, Ambulance ID: {:?}",
-                emergency.id,
-                updated.status.clone(),
-                updated.ambulance_id.unwrap_or_default()
+    let mut log = ChangeLog::allocator();
+    // Later units of the same run leave the emergency row alone: it already moved
+    // to InProgress and got its lead unit when the first one was dispatched
+    if first_unit {
+        let mut emergency_active_model: emergency::ActiveModel = emergency.clone().into();
+        if emergency.status != EmergencyStatusEnum::InProgress {
+            log.record(
+                ChangeField::Status,
+                &emergency.status,
+                &EmergencyStatusEnum::InProgress,
             );
+            emergency_active_model.status = Set(EmergencyStatusEnum::InProgress);
         }
-        Err(e) => {
-            error!(
-                "Emergency update FAILED - ID: {}, Error: {}",
-                emergency.id, e
-            );
-            return Err(CustomError::new(
-                HttpCodeW::InternalServerError,
-                format!(
-                    "Failed to update emergency status for {}: {e}",
-                    emergency.id
-                ),
-            ));
+        apply_milestone(
+            &mut emergency_active_model,
+            Milestone::Dispatched,
+            now_time(),
+        );
+        if emergency.ambulance_id.is_none() {
+            log.record(ChangeField::Ambulance, &None, &Some(ambulance.id));
+            emergency_active_model.ambulance_id = Set(Some(ambulance.id));
+            emergency_active_model.eta_seconds = Set(*decision.eta_seconds.as_ref());
+        }
+        emergency_active_model.updated_at = Set(now_time());
+
+        println!(
+            "Attempting to update emergency with status: {:?} and ambulance_id: {:?}",
+            EmergencyStatusEnum::InProgress,
+            ambulance.id
+        );
+
+        match emergency_active_model.update(txn).await {
+            Ok(updated) => {
+                println!(
+                    "Emergency update SUCCESS - ID: {}, New Status: {:?}, Ambulance ID: {:?}",
+                    emergency.id,
+                    updated.status.clone(),
+                    updated.ambulance_id.unwrap_or_default()
+                );
+            }
+            Err(e) => {
+                error!(
+                    "Emergency update FAILED - ID: {}, Error: {}",
+                    emergency.id, e
+                );
+                return Err(CustomError::new(
+                    HttpCodeW::InternalServerError,
+                    format!(
+                        "Failed to update emergency status for {}: {e}",
+                        emergency.id
+                    ),
+                ));
+            }
         }
     }
 
+    let eta_seconds = *decision.eta_seconds.as_ref();
+    let units_before = active_units(txn, emergency.id).await?;
+    if let Err(e) = link_unit(txn, emergency.id, ambulance.id, eta_seconds).await {
+        error!(
+            "Unit link FAILED - Emergency ID: {}, Ambulance ID: {}, Error: {}",
+            emergency.id, ambulance.id, e
+        );
+        return Err(CustomError::new(
+            HttpCodeW::InternalServerError,
+            format!(
+                "Failed to link ambulance {} to emergency {}: {e}",
+                ambulance.id, emergency.id
+            ),
+        ));
+    }
+
+    let mut units_after = units_before.clone();
+    units_after.push(ambulance.id);
+    log.record(ChangeField::Units, &units_before, &units_after);
+    log.save(txn, emergency.id).await?;
+
     let mut ambulance_active_model: ambulance::ActiveModel = ambulance.clone().into();
     ambulance_active_model.status = Set(AmbulanceStatusEnum::Dispatched);
     ambulance_active_model.updated_at = Set(Local::now().naive_utc());
@@ -297,9 +893,206 @@ async fn dispatch_ambulance(
             ));
         }
     }
+    if let Err(e) = decision.insert(txn).await {
+        error!(
+            "Dispatch decision insert FAILED - Emergency ID: {}, Error: {}",
+            emergency.id, e
+        );
+        return Err(CustomError::new(
+            HttpCodeW::InternalServerError,
+            format!(
+                "Failed to record dispatch decision for {}: {e}",
+                emergency.id
+            ),
+        ));
+    }
     println!(
         "dispatch_ambulance function COMPLETED SUCCESSFULLY for emergency: {} and ambulance: {}",
         emergency.id, ambulance.id
     );
     Ok("You are done!".to_string())
 }
+
+/// How a manual assignment changes the units of a case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ManualAssignment {
+    /// Ambulance the dispatcher assigned.
+    pub ambulance_id: Uuid,
+    pub forced: bool,
+    /// Unit taken off the case, the lead unit when `force` replaces it.
+    pub released: Option<Uuid>,
+    /// Lead unit of the case once the ambulance is on it.
+    pub lead: Option<Uuid>,
+}
+
+impl ManualAssignment {
+    /// Audit row of the override, naming the unit it released.
+    pub fn override_record(
+        &self,
+        emergency_id: Uuid,
+        reason: &str,
+        dispatcher: &str,
+        now: NaiveDateTime,
+    ) -> dispatch_override::ActiveModel {
+        dispatch_override::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            emergency_id: Set(emergency_id),
+            ambulance_id: Set(self.ambulance_id),
+            previous_ambulance_id: Set(self.released),
+            reason: Set(reason.to_string()),
+            forced: Set(self.forced),
+            dispatcher: Set(dispatcher.to_string()),
+            created_at: Set(now),
+        }
+    }
+}
+
+/// `force` releases the lead unit and puts the ambulance in its place.
+/// Without it the ambulance joins the units already on the case and only
+/// leads a case that had no unit yet.
+pub fn manual_assignment(lead: Option<Uuid>, ambulance_id: Uuid, force: bool) -> ManualAssignment {
+    let (released, lead) = if force {
+        (
+            lead.filter(|lead| *lead != ambulance_id),
+            Some(ambulance_id),
+        )
+    } else {
+        (None, lead.or(Some(ambulance_id)))
+    };
+    ManualAssignment {
+        ambulance_id,
+        forced: force,
+        released,
+        lead,
+    }
+}
+
+/// How the units of a case change when one of them leaves it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StandDown {
+    /// Units still on the case, in the order they were assigned.
+    pub remaining: Vec<Uuid>,
+    /// Lead unit of the case once the unit is gone.
+    pub lead: Option<Uuid>,
+    /// The case goes back to `Pending`: no unit is left and none reached it.
+    pub requeue: bool,
+    /// The allocator should send more units to the case.
+    pub needs_units: bool,
+}
+
+/// `leaving` stands down from a case worked by `units` and led by `lead`,
+/// which needs `required` units and is in `status`. The earliest remaining
+/// unit takes over the lead.
+pub fn stand_down(
+    units: &[Uuid],
+    lead: Option<Uuid>,
+    leaving: Uuid,
+    required: usize,
+    status: &EmergencyStatusEnum,
+) -> StandDown {
+    let remaining: Vec<Uuid> = units.iter().copied().filter(|u| *u != leaving).collect();
+    let lead = lead
+        .filter(|lead| *lead != leaving)
+        .or_else(|| remaining.first().copied());
+    let requeue = remaining.is_empty() && *status == EmergencyStatusEnum::InProgress;
+    // The allocator only staffs cases that are waiting or not reached yet
+    let needs_units = remaining.len() < required
+        && matches!(
+            status,
+            EmergencyStatusEnum::Pending
+                | EmergencyStatusEnum::Escalated
+                | EmergencyStatusEnum::InProgress
+        );
+    StandDown {
+        remaining,
+        lead,
+        requeue,
+        needs_units,
+    }
+}
+
+/// Records `ambulance_id` as an active unit of the emergency, reopening the
+/// link if the same vehicle had been released from it before.
+pub(crate) async fn link_unit<C: ConnectionTrait>(
+    conn: &C,
+    emergency_id: Uuid,
+    ambulance_id: Uuid,
+    eta_seconds: Option<i32>,
+) -> Result<(), DbErr> {
+    emergency_ambulance::Entity::insert(emergency_ambulance::ActiveModel {
+        emergency_id: Set(emergency_id),
+        ambulance_id: Set(ambulance_id),
+        assigned_at: Set(now_time()),
+        released_at: Set(None),
+        eta_seconds: Set(eta_seconds),
+    })
+    .on_conflict(
+        OnConflict::columns([
+            emergency_ambulance::Column::EmergencyId,
+            emergency_ambulance::Column::AmbulanceId,
+        ])
+        .update_columns([
+            emergency_ambulance::Column::AssignedAt,
+            emergency_ambulance::Column::ReleasedAt,
+            emergency_ambulance::Column::EtaSeconds,
+        ])
+        .to_owned(),
+    )
+    .exec(conn)
+    .await?;
+    Ok(())
+}
+
+/// Ambulances currently working an emergency, in the order they were assigned.
+pub(crate) async fn active_units<C: ConnectionTrait>(
+    conn: &C,
+    emergency_id: Uuid,
+) -> Result<Vec<Uuid>, DbErr> {
+    let units = emergency_ambulance::Entity::find()
+        .filter(emergency_ambulance::Column::EmergencyId.eq(emergency_id))
+        .filter(emergency_ambulance::Column::ReleasedAt.is_null())
+        .order_by_asc(emergency_ambulance::Column::AssignedAt)
+        .all(conn)
+        .await?;
+    Ok(units.into_iter().map(|u| u.ambulance_id).collect())
+}
+
+/// Emergencies as routing targets: id and position.
+fn travel_targets(emergencies: &[emergency::Model]) -> Vec<(Uuid, (f64, f64))> {
+    emergencies
+        .iter()
+        .map(|e| (e.id, emergency::Entity::coordinates(e)))
+        .collect()
+}
+
+/// Ambulances as routing sources: id, position and vehicle type.
+fn travel_vehicles(ambulances: &[ambulance::Model]) -> Vec<(Uuid, (f64, f64), AmbulanceTypeEnum)> {
+    ambulances
+        .iter()
+        .map(|a| (a.id, ambulance::Entity::coordinates(a), a.r#type.clone()))
+        .collect()
+}
+
+/// Drive-time estimates from every ambulance to every emergency, `[emergency][ambulance]`.
+///
+/// Routing is CPU-bound, so it runs on a blocking thread.
+async fn travel_matrix(
+    targets: Vec<(Uuid, (f64, f64))>,
+    vehicles: Vec<(Uuid, (f64, f64), AmbulanceTypeEnum)>,
+) -> Result<Vec<Vec<Travel>>, CustomError> {
+    tokio::task::spawn_blocking(move || {
+        let targets: Vec<(f64, f64)> = targets.iter().map(|(_, at)| *at).collect();
+        let vehicles: Vec<((f64, f64), AmbulanceTypeEnum)> = vehicles
+            .into_iter()
+            .map(|(_, at, vehicle)| (at, vehicle))
+            .collect();
+        estimate_matrix(&targets, &vehicles)
+    })
+    .await
+    .map_err(|e| {
+        CustomError::new(
+            HttpCodeW::InternalServerError,
+            format!("Routing failed: {e}"),
+        )
+    })
+}
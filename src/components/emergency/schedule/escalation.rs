@@ -0,0 +1,281 @@
+use chrono::NaiveDateTime;
+use log::{error, warn};
+use sea_orm::prelude::Uuid;
+use sea_orm::sea_query::{LockBehavior, LockType};
+use sea_orm::*;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::components::emergency::duplicates::covered_by_parent;
+use crate::components::emergency::enums::severity_rank;
+use crate::components::emergency::history::{
+    ChangeField, ChangeLog, ChangeSource, ESCALATION_ACTOR, HistoryEntry, history_entries,
+};
+use crate::components::emergency::intake::run_auto_confirm;
+use crate::components::emergency::lifecycle::ensure_transition;
+use crate::components::emergency::schedule::wake_allocator;
+use crate::entity::emergency;
+use crate::entity::sea_orm_active_enums::{
+    EmergencyIncidentEnum, EmergencySeverityEnum, EmergencyStatusEnum, EmergencyVerificationEnum,
+};
+use crate::http_response::error_handler::CustomError;
+use crate::utils::helpers::{env_or, now_time};
+use tokio::time::Duration;
+
+/// Environment variable with per-severity thresholds in minutes, e.g. `{"HIGH": 10}`.
+pub const ESCALATION_THRESHOLDS_ENV: &str = "ESCALATION_THRESHOLDS_MINUTES";
+/// Environment variable with the escalation job period in seconds.
+pub const ESCALATION_INTERVAL_ENV: &str = "ESCALATION_INTERVAL_SECS";
+const DEFAULT_ESCALATION_INTERVAL_SECS: u64 = 60;
+
+/// Statuses of an emergency still waiting for a vehicle.
+const WAITING_STATUSES: [EmergencyStatusEnum; 3] = [
+    EmergencyStatusEnum::Pending,
+    EmergencyStatusEnum::WaitingForResponse,
+    EmergencyStatusEnum::Escalated,
+];
+
+/// How long an unattended emergency may wait, per severity, before it is escalated.
+#[derive(Debug, Clone)]
+pub struct EscalationPolicy {
+    thresholds: Vec<(EmergencySeverityEnum, i64)>,
+}
+
+impl EscalationPolicy {
+    /// Loads the defaults, overridden per severity by `ESCALATION_THRESHOLDS_MINUTES`.
+    pub fn from_env() -> Self {
+        let mut policy = Self::default();
+        let Ok(raw) = std::env::var(ESCALATION_THRESHOLDS_ENV) else {
+            return policy;
+        };
+        match serde_json::from_str::<HashMap<EmergencySeverityEnum, i64>>(&raw) {
+            Ok(overrides) => {
+                for (severity, minutes) in overrides {
+                    policy.set_threshold(severity, minutes);
+                }
+            }
+            Err(e) => error!("Invalid {ESCALATION_THRESHOLDS_ENV}, using defaults: {e}"),
+        }
+        policy
+    }
+
+    pub fn set_threshold(&mut self, severity: EmergencySeverityEnum, minutes: i64) {
+        self.thresholds.retain(|(s, _)| *s != severity);
+        self.thresholds.push((severity, minutes));
+    }
+
+    /// Minutes a case of this severity may wait, `None` if it is never escalated.
+    pub fn threshold_minutes(&self, severity: &EmergencySeverityEnum) -> Option<i64> {
+        self.thresholds
+            .iter()
+            .find(|(s, _)| s == severity)
+            .map(|(_, minutes)| *minutes)
+    }
+
+    /// Minutes the emergency has been waiting since it was reported or last escalated,
+    /// and its threshold, when that wait exceeds the threshold.
+    pub fn overdue_by(
+        &self,
+        emergency: &emergency::Model,
+        now: NaiveDateTime,
+    ) -> Option<(i64, i64)> {
+        let threshold = self.threshold_minutes(&emergency.severity)?;
+        let since = last_escalated_at(emergency).unwrap_or(emergency.created_at);
+        let waited = (now - since).num_minutes();
+        (waited >= threshold).then_some((waited, threshold))
+    }
+}
+
+impl Default for EscalationPolicy {
+    fn default() -> Self {
+        use EmergencySeverityEnum::*;
+        EscalationPolicy {
+            thresholds: vec![
+                (Extreme, 2),
+                (Critical, 5),
+                (Severe, 8),
+                (Unstable, 8),
+                (High, 10),
+                (Medium, 20),
+                (Unknown, 20),
+                (Low, 30),
+                (Stable, 30),
+            ],
+        }
+    }
+}
+
+/// Next severity step for an overdue case, `None` once it is already the most urgent.
+pub fn raised_severity(severity: &EmergencySeverityEnum) -> Option<EmergencySeverityEnum> {
+    use EmergencySeverityEnum::*;
+    match severity {
+        Low | Stable => Some(Medium),
+        Medium | Unknown => Some(High),
+        High => Some(Severe),
+        Severe | Unstable => Some(Critical),
+        Critical => Some(Extreme),
+        Extreme | Deceased => None,
+    }
+}
+
+/// Time of the latest escalation recorded in the emergency's history, if any.
+pub fn last_escalated_at(emergency: &emergency::Model) -> Option<NaiveDateTime> {
+    escalation_entries(emergency).map(|e| e.at).max()
+}
+
+fn escalation_entries(emergency: &emergency::Model) -> impl Iterator<Item = HistoryEntry> {
+    history_entries(emergency)
+        .into_iter()
+        .filter(|entry| entry.source == ChangeSource::Escalation)
+}
+
+/// An unattended emergency past its threshold, as listed by `GET /emergency/overdue`.
+#[derive(Debug, Serialize)]
+pub struct OverdueEmergency {
+    pub emergency_id: Uuid,
+    pub emergency_ic: String,
+    pub status: EmergencyStatusEnum,
+    pub severity: EmergencySeverityEnum,
+    pub incident_type: EmergencyIncidentEnum,
+    pub created_at: NaiveDateTime,
+    /// Minutes since the emergency was reported or last escalated.
+    pub waiting_minutes: i64,
+    pub threshold_minutes: i64,
+    pub escalations: usize,
+    pub last_escalated_at: Option<NaiveDateTime>,
+}
+
+pub struct EscalationService {
+    conn: DatabaseConnection,
+    policy: EscalationPolicy,
+}
+
+impl EscalationService {
+    pub fn new(conn: &DatabaseConnection) -> Self {
+        Self {
+            conn: conn.clone(),
+            policy: EscalationPolicy::from_env(),
+        }
+    }
+
+    /// Raises the severity of every overdue emergency by one step, or moves it to
+    /// `Escalated` when it is already at the top. Returns how many were escalated.
+    pub async fn run_escalation_pass(&self) -> Result<usize, CustomError> {
+        let txn = self.conn.begin().await?;
+        let now = now_time();
+        let waiting = emergency::Entity::find()
+            .filter(emergency::Column::Status.is_in(WAITING_STATUSES))
+            .filter(emergency::Column::AmbulanceId.is_null())
+            .filter(emergency::Column::VerificationStatus.eq(EmergencyVerificationEnum::Verified))
+            .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked)
+            .all(&txn)
+            .await?;
+        let covered = covered_by_parent(&txn, &waiting).await?;
+
+        let mut escalated = 0;
+        for emergency in waiting {
+            if covered.contains(&emergency.id) {
+                continue;
+            }
+            let Some((waited, threshold)) = self.policy.overdue_by(&emergency, now) else {
+                continue;
+            };
+            let (to_severity, to_status) = match raised_severity(&emergency.severity) {
+                Some(severity) => (severity, emergency.status.clone()),
+                None if emergency.status == EmergencyStatusEnum::Escalated => continue,
+                None => {
+                    ensure_transition(&emergency.status, &EmergencyStatusEnum::Escalated)?;
+                    (emergency.severity.clone(), EmergencyStatusEnum::Escalated)
+                }
+            };
+            warn!(
+                "Escalating emergency {} after {waited} min (threshold {threshold} min): {:?}/{:?} -> {:?}/{:?}",
+                emergency.emergency_ic,
+                emergency.severity,
+                emergency.status,
+                to_severity,
+                to_status
+            );
+
+            let mut log = ChangeLog::new(ESCALATION_ACTOR, ChangeSource::Escalation)
+                .with_reason(format!("waited {waited} min, threshold {threshold} min"));
+            log.record(ChangeField::Severity, &emergency.severity, &to_severity);
+            log.record(ChangeField::Status, &emergency.status, &to_status);
+            log.save(&txn, emergency.id).await?;
+
+            let mut active_model: emergency::ActiveModel = emergency.into();
+            active_model.severity = Set(to_severity);
+            active_model.status = Set(to_status);
+            active_model.updated_at = Set(now);
+            active_model.update(&txn).await?;
+            escalated += 1;
+        }
+
+        txn.commit().await?;
+        if escalated > 0 {
+            wake_allocator();
+        }
+        Ok(escalated)
+    }
+
+    /// Unattended emergencies waiting longer than the threshold of their current
+    /// severity, most urgent and longest waiting first.
+    pub async fn find_overdue(&self) -> Result<Vec<OverdueEmergency>, CustomError> {
+        let now = now_time();
+        let waiting = emergency::Entity::find()
+            .filter(emergency::Column::Status.is_in(WAITING_STATUSES))
+            .filter(emergency::Column::AmbulanceId.is_null())
+            .filter(emergency::Column::VerificationStatus.eq(EmergencyVerificationEnum::Verified))
+            .all(&self.conn)
+            .await?;
+        let covered = covered_by_parent(&self.conn, &waiting).await?;
+
+        let mut overdue: Vec<OverdueEmergency> = waiting
+            .into_iter()
+            .filter(|emergency| !covered.contains(&emergency.id))
+            .filter_map(|emergency| {
+                // Same clock as the job, so a case just escalated drops off the list
+                let (waiting_minutes, threshold) = self.policy.overdue_by(&emergency, now)?;
+                Some(OverdueEmergency {
+                    escalations: escalation_entries(&emergency).count(),
+                    last_escalated_at: last_escalated_at(&emergency),
+                    emergency_id: emergency.id,
+                    emergency_ic: emergency.emergency_ic,
+                    status: emergency.status,
+                    severity: emergency.severity,
+                    incident_type: emergency.incident_type,
+                    created_at: emergency.created_at,
+                    waiting_minutes,
+                    threshold_minutes: threshold,
+                })
+            })
+            .collect();
+        overdue.sort_by(|a, b| {
+            severity_rank(&b.severity)
+                .cmp(&severity_rank(&a.severity))
+                .then(b.waiting_minutes.cmp(&a.waiting_minutes))
+        });
+        Ok(overdue)
+    }
+}
+
+pub async fn start_escalation_job(
+    db_conn: &DatabaseConnection,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let interval = Duration::from_secs(env_or(
+        ESCALATION_INTERVAL_ENV,
+        DEFAULT_ESCALATION_INTERVAL_SECS,
+    ));
+    println!("Emergency escalation job started (every {interval:?})");
+
+    loop {
+        tokio::time::sleep(interval).await;
+        // Confirmed reports are escalated in the same pass
+        run_auto_confirm(db_conn).await;
+        match EscalationService::new(db_conn).run_escalation_pass().await {
+            Ok(0) => {}
+            Ok(count) => println!("Escalated {count} overdue emergencies"),
+            Err(e) => error!("Emergency escalation pass failed: {e}"),
+        }
+    }
+}
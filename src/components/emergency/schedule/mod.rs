@@ -1,4 +1,11 @@
-mod emergency_allocation;
+pub(crate) mod assignment;
+pub(crate) mod capability;
+pub(crate) mod emergency_allocation;
+pub(crate) mod escalation;
 pub mod shceduler;
 
+pub use assignment::AllocationStrategy;
+pub use emergency_allocation::{AllocationPreviewQuery, EmergencyAllocationService};
+pub(crate) use emergency_allocation::{active_units, link_unit, manual_assignment, stand_down};
+pub use escalation::{EscalationService, start_escalation_job};
 pub use shceduler::*;
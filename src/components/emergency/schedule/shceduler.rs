@@ -1,14 +1,36 @@
 use crate::components::emergency::schedule::emergency_allocation::EmergencyAllocationService;
+use crate::utils::helpers::env_or;
 use log::{error, warn};
 use sea_orm::DatabaseConnection;
 use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::Notify;
 use tokio::time::Duration;
-// Create a static flag to track if a process is running
+// Create a static flag to track if a process is running in this instance;
+// runs on other replicas are excluded by the advisory lock in the allocator
 static ALLOCATION_RUNNING: AtomicBool = AtomicBool::new(false);
+// Wakes the scheduler loop when an emergency is reported or an ambulance becomes free
+static ALLOCATION_WAKEUP: Notify = Notify::const_new();
+
+/// Environment variable with the safety-net sweep period in seconds.
+pub const ALLOCATION_SWEEP_INTERVAL_ENV: &str = "ALLOCATION_SWEEP_INTERVAL_SECS";
+const DEFAULT_SWEEP_INTERVAL_SECS: u64 = 5 * 60;
+
+/// Asks the scheduler to run an allocation pass as soon as possible.
+///
+/// Cheap and non-blocking; calls made while a pass is running are coalesced
+/// into a single follow-up pass.
+pub fn wake_allocator() {
+    ALLOCATION_WAKEUP.notify_one();
+}
+
 pub async fn start_scheduler(
     db_conn: &DatabaseConnection,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Emergency allocation scheduler started");
+    let sweep_interval = Duration::from_secs(env_or(
+        ALLOCATION_SWEEP_INTERVAL_ENV,
+        DEFAULT_SWEEP_INTERVAL_SECS,
+    ));
+    println!("Emergency allocation scheduler started (sweep every {sweep_interval:?})");
 
     loop {
         let now = chrono::Local::now();
@@ -34,7 +56,13 @@ pub async fn start_scheduler(
             warn!("Skipping allocation - previous process still running");
         }
 
-        println!("Sleeping for 1 hour...");
-        tokio::time::sleep(Duration::from_secs(60 * 60)).await;
+        // Sleep until something changes, or until the next sweep picks up
+        // anything the events missed (e.g. rows edited directly in the database)
+        if tokio::time::timeout(sweep_interval, ALLOCATION_WAKEUP.notified())
+            .await
+            .is_ok()
+        {
+            println!("Allocation woken up by a dispatch event");
+        }
     }
 }
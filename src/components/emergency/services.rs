@@ -1,20 +1,48 @@
+use crate::components::ambulance::compliance::CompliancePolicy;
+use crate::components::ambulance::crew;
+use crate::components::ambulance::lifecycle::{
+    can_be_reassigned, emergency_status_for, emergency_sync_path, reverted_emergency_status,
+};
+use crate::components::emergency::duplicates::{DuplicatePolicy, parent_after_merge};
+use crate::components::emergency::enums::severity_rank;
+use crate::components::emergency::history::{
+    ChangeField, ChangeLog, ChangeSource, EmergencyTimeline, PUBLIC_ACTOR,
+};
+use crate::components::emergency::lifecycle::{
+    Milestone, apply_milestone, ensure_transition, is_terminal,
+};
+use crate::components::emergency::schedule::assignment::required_units;
+use crate::components::emergency::schedule::{
+    active_units, link_unit, manual_assignment, stand_down, wake_allocator,
+};
 use crate::components::patient::PatientService;
 use crate::entity::ambulance::AmbulanceId;
 use crate::entity::emergency::Column::{EmergencyIc, Id};
-use crate::entity::emergency::{ActiveModel, EmergencyRequestBody, Entity, Model};
+use crate::entity::emergency::{
+    ActiveModel, EmergencyAssignPayload, EmergencyConfirmPayload, EmergencyListQuery,
+    EmergencyListSort, EmergencyMergePayload, EmergencyRejectPayload, EmergencyRequestBody,
+    EmergencyStatusPayload, Entity, Model,
+};
 use crate::entity::sea_orm_active_enums::{
-    AmbulanceStatusEnum, EmergencySeverityEnum, EmergencyStatusEnum,
+    AmbulanceStatusEnum, EmergencySeverityEnum, EmergencyStatusEnum, EmergencyVerificationEnum,
+};
+use crate::entity::{
+    ambulance, dispatch_decision, emergency, emergency_ambulance, emergency_merge,
+    emergency_patient, patient,
 };
-use crate::entity::{ambulance, emergency};
 use crate::http_response::error_handler::CustomError;
 use crate::http_response::HttpCodeW;
 use crate::shared::{PaginatedResponse, PaginationInfo};
+use crate::utils::eta;
 use crate::utils::helpers::{check_if_is_duplicate_key_from_data_base, generate_ic, now_time};
+use crate::utils::spatial::{BoundingBox, Geolocated, bounding_box_condition};
 use chrono::NaiveDateTime;
 use percent_encoding::percent_decode_str;
-use sea_orm::{ActiveModelTrait, ColumnTrait, NotSet, PaginatorTrait};
-use sea_orm::{DatabaseConnection, EntityTrait};
-use sea_orm::{QueryFilter, Set};
+use rust_decimal::prelude::ToPrimitive;
+use sea_orm::{ActiveModelTrait, ColumnTrait, NotSet, PaginatorTrait, TransactionTrait};
+use sea_orm::{Condition, ConnectionTrait, DatabaseConnection, EntityTrait, ModelTrait};
+use sea_orm::sea_query::{CaseStatement, Expr, LockBehavior, LockType, SimpleExpr};
+use sea_orm::{Iterable, Order, QueryFilter, QueryOrder, QuerySelect, Set};
 use uuid::Uuid;
 // Adjust the path if needed
 
@@ -62,45 +90,46 @@ impl EmergencyService {
                 ambulance_json.unwrap_or(serde_json::json!({})),
             );
             merged.insert("patients".to_string(), patients_json);
+            let duplicates = emergency::Entity::find()
+                .filter(emergency::Column::ParentEmergencyId.eq(emergency.id))
+                .order_by_asc(emergency::Column::CreatedAt)
+                .all(&self.conn)
+                .await?;
+            merged.insert(
+                "duplicates".to_string(),
+                serde_json::to_value(duplicates).unwrap_or(serde_json::json!([])),
+            );
             Ok(Some(serde_json::Value::Object(merged)))
         } else {
             Ok(None)
         }
     }
 
+    /// Lists emergencies matching `query`, one page at a time.
     pub async fn find_all(
-        &self,         // Changed to &self as we're not modifying the service state
-        page: u64,     // Use u64 for pagination
-        per_page: u64, // Use u64 for pagination
-        filter: Option<String>,
+        &self,
+        query: EmergencyListQuery,
     ) -> Result<PaginatedResponse<Vec<Model>>, CustomError> {
-        let mut query = Entity::find();
-
-        if let Some(filter_str) = filter {
-            match filter_str.split_once('=') {
-                Some(("ic", encoded_name)) => {
-                    let emergency_ic = percent_decode_str(encoded_name)
-                        .decode_utf8()
-                        .map(|ic| ic.to_string())
-                        .unwrap_or_else(|_| encoded_name.to_string());
-
-                    query = query.filter(EmergencyIc.eq(emergency_ic));
-                }
-                Some(("id", encoded_name)) => {
-                    let ambulance_id = percent_decode_str(encoded_name)
-                        .decode_utf8()
-                        .map(|id| id.to_string())
-                        .unwrap_or_else(|_| encoded_name.to_string());
-                    let emergency_id = Uuid::parse_str(&ambulance_id).map_err(|_| {
-                        CustomError::new(HttpCodeW::BadRequest, "Invalid UUID".to_string())
-                    })?;
-                    query = query.filter(Id.eq(emergency_id));
-                }
-                _ => {}
-            }
+        if !(1..=MAX_PER_PAGE).contains(&query.per_page) || query.page < 0 {
+            return Err(CustomError::new(
+                HttpCodeW::BadRequest,
+                format!("per_page must be between 1 and {MAX_PER_PAGE} and page at least 0"),
+            ));
         }
+        let page = query.page as u64;
+        let per_page = query.per_page as u64;
+        let mut select = Entity::find().filter(Self::list_condition(&query)?);
+        select = match query.sort {
+            EmergencyListSort::Newest => select.order_by_desc(emergency::Column::CreatedAt),
+            EmergencyListSort::Oldest => select.order_by_asc(emergency::Column::CreatedAt),
+            EmergencyListSort::Severity => select
+                .order_by(severity_rank_expr(), Order::Desc)
+                .order_by_asc(emergency::Column::CreatedAt),
+        };
+        // Ties broken by id so pages do not overlap
+        let select = select.order_by_asc(Id);
 
-        let paginator = query.paginate(&self.conn, per_page);
+        let paginator = select.paginate(&self.conn, per_page);
         let total_items = paginator.num_items().await?;
         let total_pages = paginator.num_pages().await?;
 
@@ -126,18 +155,62 @@ impl EmergencyService {
     pub async fn create_emergency(
         &self,
         emergency_data: EmergencyRequestBody,
+        actor: &str,
     ) -> Result<Model, CustomError> {
-        self.create_emergency_internal(emergency_data).await
+        self.create_emergency_internal(
+            emergency_data,
+            actor,
+            ChangeSource::Api,
+            EmergencyVerificationEnum::Verified,
+            None,
+        )
+        .await
+    }
+
+    /// Records a report from the public intake. It stays provisional, out of
+    /// the allocator's reach, until a dispatcher or the auto-confirm rule
+    /// confirms it.
+    pub async fn create_public_report(
+        &self,
+        emergency_data: EmergencyRequestBody,
+        callback_phone: String,
+    ) -> Result<Model, CustomError> {
+        self.create_emergency_internal(
+            emergency_data,
+            PUBLIC_ACTOR,
+            ChangeSource::Intake,
+            EmergencyVerificationEnum::Provisional,
+            Some(callback_phone),
+        )
+        .await
     }
 
     async fn create_emergency_internal(
         &self,
         emergency_data: EmergencyRequestBody,
+        actor: &str,
+        source: ChangeSource,
+        verification: EmergencyVerificationEnum,
+        callback_phone: Option<String>,
     ) -> Result<Model, CustomError> {
         let now = now_time();
         let mut attempts = 0;
         const MAX_ATTEMPTS: usize = 5;
 
+        // Another call about the same incident is linked to the first report
+        // instead of becoming a case of its own
+        let parent_emergency_id = DuplicatePolicy::from_env()
+            .find_parent(
+                &self.conn,
+                &emergency_data.incident_type,
+                &verification,
+                emergency_data.emergency_latitude.to_f64().unwrap_or(0.0),
+                emergency_data.emergency_longitude.to_f64().unwrap_or(0.0),
+                now,
+            )
+            .await?
+            .map(|parent| parent.id);
+
         loop {
             if attempts >= MAX_ATTEMPTS {
                 return Err(CustomError::new(
@@ -147,17 +220,31 @@ impl EmergencyService {
             }
 
             let emergency_ic = generate_ic();
-            let active_model =
-                Self::generate_model(emergency_data.clone(), now, emergency_ic.to_string());
+            let active_model = Self::generate_model(
+                emergency_data.clone(),
+                now,
+                emergency_ic.to_string(),
+                parent_emergency_id,
+                verification.clone(),
+                callback_phone.clone(),
+            );
             let result = active_model.insert(&self.conn).await;
             match result {
                 Ok(model) => {
+                    let mut log = ChangeLog::new(actor, source);
                     // Associate patients if provided
                     if let Some(patients) = emergency_data.patients.as_ref() {
                         self.patient_service
                             .associate_patients_with_emergency(model.id, patients, &self.conn)
                             .await?;
+                        let patient_ids = Self::patient_ids(&self.conn, model.id).await?;
+                        log.record(ChangeField::Patients, &Vec::new(), &patient_ids);
+                    }
+                    if parent_emergency_id.is_some() {
+                        log = log.with_reason("duplicate report detected");
+                        log.record(ChangeField::Parent, &None, &parent_emergency_id);
                     }
+                    log.save(&self.conn, model.id).await?;
                     return Ok(model);
                 }
                 Err(e) => {
@@ -178,56 +265,1075 @@ impl EmergencyService {
         }
     }
 
-    pub async fn schedule_emergency(self) -> Result<(), CustomError> {
-        let available_ambulances = ambulance::Entity::find()
-            .filter(ambulance::Column::Status.eq(AmbulanceStatusEnum::Available)) // Assuming AmbulanceStatusEnum::Available exists
+    /// Moves an emergency to a new lifecycle status.
+    ///
+    /// The change is validated against the lifecycle rules, `resolved_at` is only
+    /// stamped when the case is resolved, and the assigned ambulance is released
+    /// back to `Available` once the case reaches a terminal status.
+    pub async fn update_status(
+        &self,
+        emergency_ic: &str,
+        payload: EmergencyStatusPayload,
+        actor: &str,
+    ) -> Result<Model, CustomError> {
+        let txn = self.conn.begin().await?;
+
+        // Row lock so the allocator cannot dispatch this case while its status changes
+        let emergency = Entity::find()
+            .filter(EmergencyIc.eq(emergency_ic))
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+            .ok_or_else(|| {
+                CustomError::new(
+                    HttpCodeW::NotFound,
+                    format!("Emergency with ic '{emergency_ic}' not found"),
+                )
+            })?;
+
+        ensure_transition(&emergency.status, &payload.status)?;
+
+        let now = now_time();
+        let ambulance_id = emergency.ambulance_id;
+        let mut log = ChangeLog::new(actor, ChangeSource::Api);
+        log.record(ChangeField::Status, &emergency.status, &payload.status);
+        if payload.notes.is_some() {
+            log.record(ChangeField::Notes, &emergency.notes, &payload.notes);
+        }
+        let mut active_model: ActiveModel = emergency.into();
+        active_model.status = Set(payload.status.clone());
+        active_model.updated_at = Set(now);
+        if let Some(milestone) = Milestone::of_status(&payload.status) {
+            apply_milestone(&mut active_model, milestone, now);
+        }
+        if payload.notes.is_some() {
+            active_model.notes = Set(payload.notes);
+        }
+        let updated = active_model.update(&txn).await?;
+
+        if is_terminal(&updated.status) {
+            let units = active_units(&txn, updated.id).await?;
+            log.record(ChangeField::Units, &units, &Vec::new());
+        }
+        let released = is_terminal(&updated.status)
+            && Self::release_units(&txn, updated.id, ambulance_id).await?;
+        log.save(&txn, updated.id).await?;
+
+        txn.commit().await?;
+        if released {
+            wake_allocator();
+        }
+        Ok(updated)
+    }
+
+    /// Confirms a provisional public report so the allocator picks it up.
+    /// Provisional reports linked to it as duplicates are confirmed with it.
+    pub async fn confirm_report(
+        &self,
+        emergency_ic: &str,
+        payload: EmergencyConfirmPayload,
+        actor: &str,
+        source: ChangeSource,
+    ) -> Result<Model, CustomError> {
+        let txn = self.conn.begin().await?;
+        let emergency = Self::find_provisional_for_update(&txn, emergency_ic).await?;
+
+        let now = now_time();
+        let mut log = ChangeLog::new(actor, source);
+        if let Some(reason) = payload.reason {
+            log = log.with_reason(reason);
+        }
+        log.record(
+            ChangeField::Verification,
+            &emergency.verification_status,
+            &EmergencyVerificationEnum::Verified,
+        );
+        let mut active_model: ActiveModel = emergency.clone().into();
+        active_model.verification_status = Set(EmergencyVerificationEnum::Verified);
+        active_model.updated_at = Set(now);
+        if let Some(severity) = payload.severity {
+            log.record(ChangeField::Severity, &emergency.severity, &severity);
+            active_model.severity = Set(severity);
+        }
+        let updated = active_model.update(&txn).await?;
+        log.save(&txn, updated.id).await?;
+
+        let duplicates = Entity::find()
+            .filter(emergency::Column::ParentEmergencyId.eq(updated.id))
+            .filter(emergency::Column::VerificationStatus.eq(EmergencyVerificationEnum::Provisional))
+            .all(&txn)
+            .await?;
+        for duplicate in duplicates {
+            let mut duplicate_log = ChangeLog::new(actor, source).with_reason(format!(
+                "confirmed with {}",
+                updated.emergency_ic
+            ));
+            duplicate_log.record(
+                ChangeField::Verification,
+                &duplicate.verification_status,
+                &EmergencyVerificationEnum::Verified,
+            );
+            let duplicate_id = duplicate.id;
+            let mut duplicate: ActiveModel = duplicate.into();
+            duplicate.verification_status = Set(EmergencyVerificationEnum::Verified);
+            duplicate.updated_at = Set(now);
+            duplicate.update(&txn).await?;
+            duplicate_log.save(&txn, duplicate_id).await?;
+        }
+
+        txn.commit().await?;
+        wake_allocator();
+        Ok(updated)
+    }
+
+    /// Rejects a provisional public report as false or abusive and closes it.
+    pub async fn reject_report(
+        &self,
+        emergency_ic: &str,
+        payload: EmergencyRejectPayload,
+        actor: &str,
+    ) -> Result<Model, CustomError> {
+        let reason = payload.reason.trim();
+        if reason.is_empty() {
+            return Err(CustomError::new(
+                HttpCodeW::BadRequest,
+                "reason is required to reject a report".to_string(),
+            ));
+        }
+
+        let txn = self.conn.begin().await?;
+        let emergency = Self::find_provisional_for_update(&txn, emergency_ic).await?;
+        ensure_transition(&emergency.status, &EmergencyStatusEnum::Cancelled)?;
+
+        let mut log = ChangeLog::new(actor, ChangeSource::Api).with_reason(reason);
+        log.record(
+            ChangeField::Verification,
+            &emergency.verification_status,
+            &EmergencyVerificationEnum::Rejected,
+        );
+        log.record(
+            ChangeField::Status,
+            &emergency.status,
+            &EmergencyStatusEnum::Cancelled,
+        );
+        let mut active_model: ActiveModel = emergency.into();
+        active_model.verification_status = Set(EmergencyVerificationEnum::Rejected);
+        active_model.status = Set(EmergencyStatusEnum::Cancelled);
+        active_model.updated_at = Set(now_time());
+        let updated = active_model.update(&txn).await?;
+        log.save(&txn, updated.id).await?;
+
+        txn.commit().await?;
+        Ok(updated)
+    }
+
+    /// Open provisional reports, oldest first, for the dispatcher's callback queue.
+    pub async fn find_provisional(&self) -> Result<Vec<Model>, CustomError> {
+        Ok(Entity::find()
+            .filter(emergency::Column::VerificationStatus.eq(EmergencyVerificationEnum::Provisional))
+            .filter(emergency::Column::Status.is_not_in([
+                EmergencyStatusEnum::Resolved,
+                EmergencyStatusEnum::Cancelled,
+                EmergencyStatusEnum::Failed,
+            ]))
+            .order_by_asc(emergency::Column::CreatedAt)
             .all(&self.conn)
-            .await
-            .map_err(|e| {
+            .await?)
+    }
+
+    /// Locks a provisional report, 404 if it does not exist and 409 once it
+    /// has been confirmed or rejected.
+    async fn find_provisional_for_update<C: ConnectionTrait>(
+        conn: &C,
+        emergency_ic: &str,
+    ) -> Result<Model, CustomError> {
+        let emergency = Entity::find()
+            .filter(EmergencyIc.eq(emergency_ic))
+            .lock_exclusive()
+            .one(conn)
+            .await?
+            .ok_or_else(|| {
                 CustomError::new(
-                    HttpCodeW::InternalServerError,
-                    format!("Failed to fetch available ambulances: {e}"),
+                    HttpCodeW::NotFound,
+                    format!("Emergency with ic '{emergency_ic}' not found"),
                 )
             })?;
+        if emergency.verification_status != EmergencyVerificationEnum::Provisional {
+            return Err(CustomError::new(
+                HttpCodeW::Conflict,
+                format!(
+                    "Emergency {emergency_ic} is not awaiting verification ({:?})",
+                    emergency.verification_status
+                ),
+            ));
+        }
+        Ok(emergency)
+    }
 
-        if available_ambulances.is_empty() {
-            println!("No available ambulances to schedule the emergency.");
-            return Ok(());
+    /// Assigns a specific ambulance to an emergency on behalf of a human dispatcher.
+    ///
+    /// Without `force` the ambulance must be `Available` and joins the units
+    /// already on the case, leading it only when it had none. With `force` the
+    /// lead unit is released and replaced, and a busy ambulance may be taken from
+    /// a case it has not reached yet (`Dispatched` or `EnRouteToScene`), which
+    /// goes back to `Pending` when no other unit is left on it. The override
+    /// is recorded in `dispatch_override`.
+    pub async fn assign_ambulance(
+        &self,
+        emergency_ic: &str,
+        payload: EmergencyAssignPayload,
+        dispatcher: &str,
+    ) -> Result<Model, CustomError> {
+        let reason = payload.reason.trim();
+        if reason.is_empty() {
+            return Err(CustomError::new(
+                HttpCodeW::BadRequest,
+                "reason is required for a manual assignment".to_string(),
+            ));
         }
 
-        let assigned_ambulance = &available_ambulances[0];
+        let txn = self.conn.begin().await?;
+        let emergency = Entity::find()
+            .filter(EmergencyIc.eq(emergency_ic))
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+            .ok_or_else(|| {
+                CustomError::new(
+                    HttpCodeW::NotFound,
+                    format!("Emergency with ic '{emergency_ic}' not found"),
+                )
+            })?;
+        if is_terminal(&emergency.status) {
+            return Err(CustomError::new(
+                HttpCodeW::UnprocessableEntity,
+                format!(
+                    "Emergency {emergency_ic} is closed ({:?})",
+                    emergency.status
+                ),
+            ));
+        }
+        if emergency.verification_status != EmergencyVerificationEnum::Verified {
+            return Err(CustomError::new(
+                HttpCodeW::Conflict,
+                format!("Emergency {emergency_ic} must be confirmed before a unit is assigned"),
+            ));
+        }
 
-        let mut ambulance_active_model: ambulance::ActiveModel = assigned_ambulance.clone().into(); // Convert to ActiveModel
-        ambulance_active_model.status = Set(AmbulanceStatusEnum::Dispatched); // Assuming AmbulanceStatusEnum::EnRoute exists
-        ambulance_active_model.updated_at = Set(now_time());
-        ambulance_active_model
-            .update(&self.conn)
-            .await // Save the changes to the database
-            .map_err(|e| {
+        let ambulance = ambulance::Entity::find_by_id(payload.ambulance_id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+            .ok_or_else(|| {
                 CustomError::new(
-                    HttpCodeW::InternalServerError,
-                    format!("Failed to update ambulance status: {e}"),
+                    HttpCodeW::NotFound,
+                    format!("Ambulance {} not found", payload.ambulance_id),
                 )
             })?;
+        let units_before = active_units(&txn, emergency.id).await?;
+        if emergency.ambulance_id == Some(ambulance.id) || units_before.contains(&ambulance.id) {
+            return Err(CustomError::new(
+                HttpCodeW::Conflict,
+                format!(
+                    "Ambulance {} is already assigned to emergency {emergency_ic}",
+                    ambulance.ambulance_ic
+                ),
+            ));
+        }
+        if !can_be_dispatched(&ambulance.status) {
+            return Err(CustomError::new(
+                HttpCodeW::UnprocessableEntity,
+                format!(
+                    "Ambulance {} is {:?} and cannot be dispatched",
+                    ambulance.ambulance_ic, ambulance.status
+                ),
+            ));
+        }
+        if !payload.force {
+            CompliancePolicy::from_env().ensure_roadworthy(&ambulance, now_time())?;
+            crew::ensure_crewed(&txn, &ambulance, now_time()).await?;
+        }
+        if !payload.force && ambulance.status != AmbulanceStatusEnum::Available {
+            return Err(CustomError::new(
+                HttpCodeW::Conflict,
+                format!(
+                    "Ambulance {} is {:?}; set force to take it anyway",
+                    ambulance.ambulance_ic, ambulance.status
+                ),
+            ));
+        }
+
+        let now = now_time();
+        let mut requeued = false;
+        if ambulance.status != AmbulanceStatusEnum::Available
+            && let Some(link) = emergency_ambulance::Entity::find()
+                .filter(emergency_ambulance::Column::AmbulanceId.eq(ambulance.id))
+                .filter(emergency_ambulance::Column::EmergencyId.ne(emergency.id))
+                .filter(emergency_ambulance::Column::ReleasedAt.is_null())
+                .one(&txn)
+                .await?
+            && let Some(other) = Entity::find_by_id(link.emergency_id)
+                .lock_exclusive()
+                .one(&txn)
+                .await?
+            && !is_terminal(&other.status)
+        {
+            // Each unit reaches the scene on its own; only the lead moves the case
+            if !can_be_reassigned(&ambulance.status) {
+                return Err(CustomError::new(
+                    HttpCodeW::Conflict,
+                    format!(
+                        "Ambulance {} is already working emergency {} ({:?})",
+                        ambulance.ambulance_ic, other.emergency_ic, ambulance.status
+                    ),
+                ));
+            }
+            let other_units = active_units(&txn, other.id).await?;
+            Self::close_unit(&txn, other.id, ambulance.id).await?;
+            // The vehicle has not reached those patients yet; without another unit
+            // on the way the case goes back to waiting
+            let remaining = active_units(&txn, other.id).await?;
+            let mut other_log = ChangeLog::new(dispatcher, ChangeSource::Api).with_reason(format!(
+                "ambulance {} reassigned to emergency {emergency_ic}",
+                ambulance.ambulance_ic
+            ));
+            other_log.record(ChangeField::Units, &other_units, &remaining);
+            other_log.record(
+                ChangeField::Ambulance,
+                &other.ambulance_id,
+                &remaining.first().copied(),
+            );
+            if remaining.is_empty() {
+                other_log.record(
+                    ChangeField::Status,
+                    &other.status,
+                    &EmergencyStatusEnum::Pending,
+                );
+            }
+            other_log.save(&txn, other.id).await?;
+            let mut other_active: ActiveModel = other.into();
+            other_active.ambulance_id = Set(remaining.first().copied());
+            if remaining.is_empty() {
+                other_active.status = Set(EmergencyStatusEnum::Pending);
+            }
+            other_active.updated_at = Set(now);
+            other_active.update(&txn).await?;
+            requeued = true;
+        }
+
+        let plan = manual_assignment(emergency.ambulance_id, ambulance.id, payload.force);
+        let previous_ambulance_id = plan.released;
+        if let Some(previous) = previous_ambulance_id {
+            Self::release_ambulance(&txn, previous).await?;
+            Self::close_unit(&txn, emergency.id, previous).await?;
+        }
+        let eta_seconds = eta::estimate(
+            ambulance::Entity::coordinates(&ambulance),
+            &ambulance.r#type,
+            emergency::Entity::coordinates(&emergency),
+        )
+        .eta_seconds_rounded();
+        link_unit(&txn, emergency.id, ambulance.id, Some(eta_seconds)).await?;
+
+        let status = emergency.status.clone();
+        let emergency_lead = emergency.ambulance_id;
+        let mut log = ChangeLog::new(dispatcher, ChangeSource::Api).with_reason(reason);
+        let units_after = active_units(&txn, emergency.id).await?;
+        log.record(ChangeField::Units, &units_before, &units_after);
+        let mut active_model: ActiveModel = emergency.into();
+        if matches!(
+            status,
+            EmergencyStatusEnum::Pending
+                | EmergencyStatusEnum::WaitingForResponse
+                | EmergencyStatusEnum::OnHold
+                | EmergencyStatusEnum::Escalated
+        ) {
+            ensure_transition(&status, &EmergencyStatusEnum::InProgress)?;
+            log.record(ChangeField::Status, &status, &EmergencyStatusEnum::InProgress);
+            active_model.status = Set(EmergencyStatusEnum::InProgress);
+        }
+        apply_milestone(&mut active_model, Milestone::Dispatched, now);
+        if plan.lead != emergency_lead {
+            log.record(ChangeField::Ambulance, &emergency_lead, &plan.lead);
+            active_model.ambulance_id = Set(plan.lead);
+            active_model.eta_seconds = Set(Some(eta_seconds));
+        }
+        active_model.updated_at = Set(now);
+        let updated = active_model.update(&txn).await?;
+        log.save(&txn, updated.id).await?;
+
+        let mut ambulance_active: ambulance::ActiveModel = ambulance.into();
+        ambulance_active.status = Set(AmbulanceStatusEnum::Dispatched);
+        ambulance_active.updated_at = Set(now);
+        ambulance_active.update(&txn).await?;
+
+        plan.override_record(updated.id, reason, dispatcher, now)
+            .insert(&txn)
+            .await?;
+
+        txn.commit().await?;
+        if requeued || previous_ambulance_id.is_some() {
+            wake_allocator();
+        }
+        Ok(updated)
+    }
+
+    /// Open emergency an ambulance is currently linked to.
+    pub async fn find_active_for_unit(
+        &self,
+        ambulance_id: Uuid,
+    ) -> Result<Option<Model>, CustomError> {
+        Self::active_for_unit(&self.conn, ambulance_id).await
+    }
+
+    async fn active_for_unit<C: ConnectionTrait>(
+        conn: &C,
+        ambulance_id: Uuid,
+    ) -> Result<Option<Model>, CustomError> {
+        let linked = emergency_ambulance::Entity::find()
+            .filter(emergency_ambulance::Column::AmbulanceId.eq(ambulance_id))
+            .filter(emergency_ambulance::Column::ReleasedAt.is_null())
+            .find_also_related(emergency::Entity)
+            .one(conn)
+            .await?;
+        Ok(linked
+            .and_then(|(_, emergency)| emergency)
+            .filter(|emergency| !is_terminal(&emergency.status)))
+    }
+
+    /// Sets the hospital the patient is taken to and records who chose it.
+    pub async fn set_destination<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        emergency: &Model,
+        hospital_id: Uuid,
+        actor: &str,
+        reason: String,
+    ) -> Result<(), CustomError> {
+        let mut log = ChangeLog::new(actor, ChangeSource::Api).with_reason(reason);
+        log.record(
+            ChangeField::Hospital,
+            &emergency.hospital_id,
+            &Some(hospital_id),
+        );
+        if emergency.hospital_id != Some(hospital_id) {
+            let mut active_model: ActiveModel = emergency.clone().into();
+            active_model.hospital_id = Set(Some(hospital_id));
+            active_model.updated_at = Set(now_time());
+            active_model.update(conn).await?;
+        }
+        log.save(conn, emergency.id).await?;
+        Ok(())
+    }
+
+    /// Stamps the milestone a unit's new status marks on the emergency it is
+    /// working; the first unit to get there sets the time. A unit starting
+    /// transport also sets the emergency's destination hospital if it has none.
+    ///
+    /// The lead unit also moves the emergency forward to the matching status,
+    /// e.g. `AtScene` on the ambulance puts the case `AtScene`, stepping through
+    /// any status in between. A case already further along is left as it is.
+    ///
+    /// Runs on `conn` so the caller can commit it with the unit's own change.
+    pub async fn record_unit_progress<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        ambulance: &ambulance::Model,
+        actor: &str,
+        source: ChangeSource,
+    ) -> Result<(), CustomError> {
+        let Some(emergency) = Self::active_for_unit(conn, ambulance.id).await? else {
+            return Ok(());
+        };
+        let Some(emergency) = Entity::find_by_id(emergency.id)
+            .lock_exclusive()
+            .one(conn)
+            .await?
+        else {
+            return Ok(());
+        };
+        let now = now_time();
+        let hospital_id = emergency.hospital_id;
+        let milestone = Milestone::of_unit_status(&ambulance.status);
+
+        // Only the lead unit moves the case along; the others just stamp milestones
+        let path = emergency_status_for(&ambulance.status)
+            .filter(|_| emergency.ambulance_id == Some(ambulance.id))
+            .and_then(|target| emergency_sync_path(&emergency.status, &target))
+            .unwrap_or_default();
+        let mut log = ChangeLog::new(actor, source).with_reason(format!(
+            "unit {} reported {:?}",
+            ambulance.ambulance_ic, ambulance.status
+        ));
+        if let Some(to) = path.last() {
+            log.record(ChangeField::Status, &emergency.status, to);
+        }
+
+        let emergency_id = emergency.id;
+        let mut active_model: ActiveModel = emergency.into();
+        if let Some(milestone) = milestone {
+            apply_milestone(&mut active_model, milestone, now);
+        }
+        for status in &path {
+            if let Some(milestone) = Milestone::of_status(status) {
+                apply_milestone(&mut active_model, milestone, now);
+            }
+        }
+        if let Some(to) = path.last() {
+            active_model.status = Set(to.clone());
+            active_model.updated_at = Set(now);
+        }
+        if milestone == Some(Milestone::Transporting) && hospital_id.is_none() {
+            active_model.hospital_id = Set(Some(ambulance.hospital_id));
+        }
+        if active_model.is_changed() {
+            active_model.update(conn).await?;
+        }
+        log.save(conn, emergency_id).await?;
+        Ok(())
+    }
+
+    /// Takes a unit that left its mission, now in `ambulance.status`, off the
+    /// emergency it was working: its link is closed, patients it claimed but
+    /// never carried go back to the pool, the earliest remaining unit takes
+    /// the lead, and a case left with no unit before any reached it goes back
+    /// to `Pending`. Returns whether the case needs more units.
+    ///
+    /// Runs on `conn` so the caller can commit it with the unit's own change.
+    pub async fn stand_down_unit<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        ambulance: &ambulance::Model,
+        from: &AmbulanceStatusEnum,
+        actor: &str,
+        source: ChangeSource,
+    ) -> Result<bool, CustomError> {
+        let emergency = Self::active_for_unit(conn, ambulance.id).await?;
+        let emergency = match emergency {
+            Some(emergency) => Entity::find_by_id(emergency.id)
+                .lock_exclusive()
+                .one(conn)
+                .await?,
+            None => None,
+        };
+        let Some(emergency) = emergency else {
+            // Links left open on a closed case must not follow the unit around
+            emergency_ambulance::Entity::update_many()
+                .col_expr(
+                    emergency_ambulance::Column::ReleasedAt,
+                    Expr::value(now_time()),
+                )
+                .filter(emergency_ambulance::Column::AmbulanceId.eq(ambulance.id))
+                .filter(emergency_ambulance::Column::ReleasedAt.is_null())
+                .exec(conn)
+                .await?;
+            return Ok(false);
+        };
+
+        let units = active_units(conn, emergency.id).await?;
+        Self::close_unit(conn, emergency.id, ambulance.id).await?;
+        if !matches!(
+            from,
+            AmbulanceStatusEnum::TransportingPatient
+                | AmbulanceStatusEnum::EnRouteToHospital
+                | AmbulanceStatusEnum::AtHospital
+        ) {
+            emergency_patient::Entity::update_many()
+                .col_expr(
+                    emergency_patient::Column::AmbulanceId,
+                    Expr::value(None::<Uuid>),
+                )
+                .filter(emergency_patient::Column::EmergencyId.eq(emergency.id))
+                .filter(emergency_patient::Column::AmbulanceId.eq(ambulance.id))
+                .exec(conn)
+                .await?;
+        }
+        let patients = Self::patient_ids(conn, emergency.id).await?.len();
+        let plan = stand_down(
+            &units,
+            emergency.ambulance_id,
+            ambulance.id,
+            required_units(patients, &emergency.severity),
+            &emergency.status,
+        );
 
+        let mut log = ChangeLog::new(actor, source).with_reason(format!(
+            "unit {} stood down ({:?})",
+            ambulance.ambulance_ic, ambulance.status
+        ));
+        log.record(ChangeField::Units, &units, &plan.remaining);
+        log.record(ChangeField::Ambulance, &emergency.ambulance_id, &plan.lead);
+        let emergency_id = emergency.id;
+        let mut active_model: ActiveModel = emergency.clone().into();
+        if plan.lead != emergency.ambulance_id {
+            active_model.ambulance_id = Set(plan.lead);
+        }
+        if plan.requeue {
+            log.record(
+                ChangeField::Status,
+                &emergency.status,
+                &EmergencyStatusEnum::Pending,
+            );
+            active_model.status = Set(EmergencyStatusEnum::Pending);
+        }
+        if active_model.is_changed() {
+            active_model.updated_at = Set(now_time());
+            active_model.update(conn).await?;
+        }
+        log.save(conn, emergency_id).await?;
+        Ok(plan.needs_units)
+    }
+
+    /// Undoes what a unit's wrong automatic change to `to` did to its
+    /// emergency, the unit being back in its previous status. The lead unit
+    /// steps the case back to the status that one matches, and the milestone
+    /// `to` stamped since `since` is cleared unless another unit on the case
+    /// reports it too. Both go on the timeline.
+    pub async fn revert_unit_progress<C: ConnectionTrait>(
+        &self,
+        conn: &C,
+        ambulance: &ambulance::Model,
+        to: &AmbulanceStatusEnum,
+        since: NaiveDateTime,
+        actor: &str,
+        source: ChangeSource,
+    ) -> Result<(), CustomError> {
+        let Some(emergency) = Self::active_for_unit(conn, ambulance.id).await? else {
+            return Ok(());
+        };
+        let Some(emergency) = Entity::find_by_id(emergency.id)
+            .lock_exclusive()
+            .one(conn)
+            .await?
+        else {
+            return Ok(());
+        };
+        let from = &ambulance.status;
+        let mut log = ChangeLog::new(actor, source).with_reason(format!(
+            "unit {} reverted from {to:?} to {from:?}",
+            ambulance.ambulance_ic
+        ));
+        let mut active_model: ActiveModel = emergency.clone().into();
+        if emergency.ambulance_id == Some(ambulance.id)
+            && let Some(back) = reverted_emergency_status(&emergency.status, from, to)
+        {
+            log.record(ChangeField::Status, &emergency.status, &back);
+            active_model.status = Set(back);
+        }
+        if let Some(milestone) = Milestone::of_unit_status(to) {
+            let recorded = match emergency.get(milestone.column()) {
+                sea_orm::Value::ChronoDateTime(Some(at)) => Some(*at),
+                _ => None,
+            };
+            if recorded.is_some_and(|at| at >= since) {
+                let others = emergency_ambulance::Entity::find()
+                    .filter(emergency_ambulance::Column::EmergencyId.eq(emergency.id))
+                    .filter(emergency_ambulance::Column::ReleasedAt.is_null())
+                    .filter(emergency_ambulance::Column::AmbulanceId.ne(ambulance.id))
+                    .find_also_related(ambulance::Entity)
+                    .all(conn)
+                    .await?;
+                let held = others
+                    .iter()
+                    .filter_map(|(_, unit)| unit.as_ref())
+                    .any(|unit| Milestone::of_unit_status(&unit.status) == Some(milestone));
+                if !held {
+                    log.record(ChangeField::Milestone, &recorded, &None);
+                    active_model.set(milestone.column(), None::<NaiveDateTime>.into());
+                }
+            }
+        }
+        if active_model.is_changed() {
+            active_model.updated_at = Set(now_time());
+            active_model.update(conn).await?;
+        }
+        log.save(conn, emergency.id).await?;
+        Ok(())
+    }
+
+    /// Dispatch decisions recorded for an emergency, oldest first.
+    pub async fn find_dispatch_decisions(
+        &self,
+        emergency_ic: &str,
+    ) -> Result<Vec<dispatch_decision::Model>, CustomError> {
+        let emergency = self.find_model_by_ic(emergency_ic).await?;
+        let decisions = dispatch_decision::Entity::find()
+            .filter(dispatch_decision::Column::EmergencyId.eq(emergency.id))
+            .order_by_asc(dispatch_decision::Column::CreatedAt)
+            .all(&self.conn)
+            .await?;
+        Ok(decisions)
+    }
+
+    /// Folds duplicate reports into the emergency they describe.
+    ///
+    /// Each duplicate's patients and active units move to the parent, the
+    /// duplicate is cancelled and linked to it, and the parent keeps the highest
+    /// severity of the group. Every merge is recorded in `emergency_merge` with a
+    /// snapshot of the duplicate as it was.
+    pub async fn merge_emergencies(
+        &self,
+        emergency_ic: &str,
+        payload: EmergencyMergePayload,
+        dispatcher: &str,
+    ) -> Result<Vec<emergency_merge::Model>, CustomError> {
+        if payload.duplicates.is_empty() {
+            return Err(CustomError::new(
+                HttpCodeW::BadRequest,
+                "duplicates must list at least one emergency ic".to_string(),
+            ));
+        }
+
+        let txn = self.conn.begin().await?;
+        let parent = Self::lock_by_ic(&txn, emergency_ic).await?;
+        if is_terminal(&parent.status) {
+            return Err(CustomError::new(
+                HttpCodeW::UnprocessableEntity,
+                format!("Emergency {emergency_ic} is closed ({:?})", parent.status),
+            ));
+        }
+
+        let now = now_time();
+        let patients_before = Self::patient_ids(&txn, parent.id).await?;
+        let units_before = active_units(&txn, parent.id).await?;
+        let merge_reason = format!("merged {}", payload.duplicates.join(", "));
+        let mut parent_lead = parent.ambulance_id;
+        let mut parent_eta = parent.eta_seconds;
+        let mut severity = parent.severity.clone();
+        let mut parent_link = parent.parent_emergency_id;
+        let mut merges = Vec::with_capacity(payload.duplicates.len());
+        for duplicate_ic in &payload.duplicates {
+            let duplicate = Self::lock_by_ic(&txn, duplicate_ic).await?;
+            if duplicate.id == parent.id {
+                return Err(CustomError::new(
+                    HttpCodeW::BadRequest,
+                    format!("Emergency {duplicate_ic} cannot be merged into itself"),
+                ));
+            }
+            let already_merged = emergency_merge::Entity::find()
+                .filter(emergency_merge::Column::MergedEmergencyId.eq(duplicate.id))
+                .one(&txn)
+                .await?
+                .is_some();
+            if already_merged {
+                return Err(CustomError::new(
+                    HttpCodeW::Conflict,
+                    format!("Emergency {duplicate_ic} has already been merged"),
+                ));
+            }
+            ensure_transition(&duplicate.status, &EmergencyStatusEnum::Cancelled)?;
+
+            // Patients already on the parent keep their row there
+            let on_parent: Vec<Uuid> = emergency_patient::Entity::find()
+                .filter(emergency_patient::Column::EmergencyId.eq(parent.id))
+                .all(&txn)
+                .await?
+                .into_iter()
+                .map(|row| row.patient_id)
+                .collect();
+            let rows = emergency_patient::Entity::find()
+                .filter(emergency_patient::Column::EmergencyId.eq(duplicate.id))
+                .all(&txn)
+                .await?;
+            let mut patients_moved = 0;
+            for row in rows {
+                if on_parent.contains(&row.patient_id) {
+                    row.delete(&txn).await?;
+                    continue;
+                }
+                // `emergency_id` is part of the key, so the row is moved in place
+                emergency_patient::Entity::update_many()
+                    .col_expr(emergency_patient::Column::EmergencyId, Expr::value(parent.id))
+                    .filter(emergency_patient::Column::EmergencyId.eq(duplicate.id))
+                    .filter(emergency_patient::Column::PatientId.eq(row.patient_id))
+                    .exec(&txn)
+                    .await?;
+                patients_moved += 1;
+            }
+
+            let units = active_units(&txn, duplicate.id).await?;
+            for ambulance_id in &units {
+                Self::close_unit(&txn, duplicate.id, *ambulance_id).await?;
+                link_unit(&txn, parent.id, *ambulance_id, duplicate.eta_seconds).await?;
+            }
+            if parent_lead.is_none() && !units.is_empty() {
+                parent_lead = duplicate.ambulance_id.or(units.first().copied());
+                parent_eta = duplicate.eta_seconds;
+            }
+            if severity_rank(&duplicate.severity) > severity_rank(&severity) {
+                severity = duplicate.severity.clone();
+            }
+
+            // Reports linked to the duplicate follow it to the parent, so they
+            // stay covered instead of pointing at a cancelled incident
+            let children = Entity::find()
+                .filter(emergency::Column::ParentEmergencyId.eq(duplicate.id))
+                .filter(Id.ne(parent.id))
+                .lock_exclusive()
+                .all(&txn)
+                .await?;
+            for child in children {
+                let new_parent =
+                    parent_after_merge(child.id, child.parent_emergency_id, duplicate.id, parent.id);
+                let mut child_log = ChangeLog::new(dispatcher, ChangeSource::Api)
+                    .with_reason(format!("{duplicate_ic} merged into {emergency_ic}"));
+                child_log.record(ChangeField::Parent, &child.parent_emergency_id, &new_parent);
+                child_log.save(&txn, child.id).await?;
+                let mut child_active: ActiveModel = child.into();
+                child_active.parent_emergency_id = Set(new_parent);
+                child_active.updated_at = Set(now);
+                child_active.update(&txn).await?;
+            }
+            parent_link = parent_after_merge(parent.id, parent_link, duplicate.id, parent.id);
+
+            let mut log = ChangeLog::new(dispatcher, ChangeSource::Api)
+                .with_reason(format!("merged into {emergency_ic}"));
+            log.record(ChangeField::Status, &duplicate.status, &EmergencyStatusEnum::Cancelled);
+            log.record(
+                ChangeField::Parent,
+                &duplicate.parent_emergency_id,
+                &Some(parent.id),
+            );
+            log.record(ChangeField::Ambulance, &duplicate.ambulance_id, &None);
+            log.record(ChangeField::Units, &units, &Vec::new());
+            log.save(&txn, duplicate.id).await?;
+
+            let snapshot = serde_json::to_value(&duplicate).unwrap_or_default();
+            let duplicate_id = duplicate.id;
+            let mut duplicate_active: ActiveModel = duplicate.into();
+            duplicate_active.status = Set(EmergencyStatusEnum::Cancelled);
+            duplicate_active.parent_emergency_id = Set(Some(parent.id));
+            duplicate_active.ambulance_id = Set(None);
+            duplicate_active.updated_at = Set(now);
+            duplicate_active.update(&txn).await?;
+
+            let merge = emergency_merge::ActiveModel {
+                id: Set(Uuid::new_v4()),
+                parent_emergency_id: Set(parent.id),
+                merged_emergency_id: Set(duplicate_id),
+                patients_moved: Set(patients_moved),
+                units_moved: Set(units.len() as i32),
+                reason: Set(payload.reason.clone()),
+                merged_by: Set(dispatcher.to_string()),
+                snapshot: Set(snapshot),
+                merged_at: Set(now),
+            }
+            .insert(&txn)
+            .await?;
+            merges.push(merge);
+        }
+
+        let mut log = ChangeLog::new(dispatcher, ChangeSource::Api).with_reason(merge_reason);
+        log.record(
+            ChangeField::Patients,
+            &patients_before,
+            &Self::patient_ids(&txn, parent.id).await?,
+        );
+        log.record(
+            ChangeField::Units,
+            &units_before,
+            &active_units(&txn, parent.id).await?,
+        );
+        log.record(ChangeField::Ambulance, &parent.ambulance_id, &parent_lead);
+        log.record(ChangeField::Severity, &parent.severity, &severity);
+        log.record(ChangeField::Parent, &parent.parent_emergency_id, &parent_link);
+        log.save(&txn, parent.id).await?;
+
+        let mut parent_active: ActiveModel = parent.into();
+        parent_active.severity = Set(severity);
+        parent_active.parent_emergency_id = Set(parent_link);
+        parent_active.ambulance_id = Set(parent_lead);
+        parent_active.eta_seconds = Set(parent_eta);
+        parent_active.updated_at = Set(now);
+        parent_active.update(&txn).await?;
+
+        txn.commit().await?;
+        // The parent may now need more units for the patients it took over
+        wake_allocator();
+        Ok(merges)
+    }
+
+    /// Every recorded change of an emergency, oldest first.
+    pub async fn find_timeline(&self, emergency_ic: &str) -> Result<EmergencyTimeline, CustomError> {
+        let emergency = self.find_model_by_ic(emergency_ic).await?;
+        Ok(EmergencyTimeline::from(&emergency))
+    }
+
+    /// Merges the emergency took part in, as parent or as duplicate, oldest first.
+    pub async fn find_merges(
+        &self,
+        emergency_ic: &str,
+    ) -> Result<Vec<emergency_merge::Model>, CustomError> {
+        let emergency = self.find_model_by_ic(emergency_ic).await?;
+        let merges = emergency_merge::Entity::find()
+            .filter(
+                Condition::any()
+                    .add(emergency_merge::Column::ParentEmergencyId.eq(emergency.id))
+                    .add(emergency_merge::Column::MergedEmergencyId.eq(emergency.id)),
+            )
+            .order_by_asc(emergency_merge::Column::MergedAt)
+            .all(&self.conn)
+            .await?;
+        Ok(merges)
+    }
+
+    /// Detaches an emergency wrongly detected as a duplicate, so it is staffed
+    /// and escalated on its own again.
+    pub async fn unlink_duplicate(
+        &self,
+        emergency_ic: &str,
+        actor: &str,
+    ) -> Result<Model, CustomError> {
+        let txn = self.conn.begin().await?;
+        let emergency = Self::lock_by_ic(&txn, emergency_ic).await?;
+        if emergency.parent_emergency_id.is_none() {
+            return Err(CustomError::new(
+                HttpCodeW::Conflict,
+                format!("Emergency {emergency_ic} is not linked to another incident"),
+            ));
+        }
+        if is_terminal(&emergency.status) {
+            return Err(CustomError::new(
+                HttpCodeW::UnprocessableEntity,
+                format!(
+                    "Emergency {emergency_ic} is closed ({:?})",
+                    emergency.status
+                ),
+            ));
+        }
+        let mut log = ChangeLog::new(actor, ChangeSource::Api).with_reason("not a duplicate");
+        log.record(ChangeField::Parent, &emergency.parent_emergency_id, &None);
+        log.save(&txn, emergency.id).await?;
+        let mut active_model: ActiveModel = emergency.into();
+        active_model.parent_emergency_id = Set(None);
+        active_model.updated_at = Set(now_time());
+        let updated = active_model.update(&txn).await?;
+        txn.commit().await?;
+        wake_allocator();
+        Ok(updated)
+    }
+
+    /// Loads an emergency by ic and locks its row until the transaction ends.
+    async fn lock_by_ic<C: ConnectionTrait>(
+        conn: &C,
+        emergency_ic: &str,
+    ) -> Result<Model, CustomError> {
+        Entity::find()
+            .filter(EmergencyIc.eq(emergency_ic))
+            .lock_exclusive()
+            .one(conn)
+            .await?
+            .ok_or_else(|| {
+                CustomError::new(
+                    HttpCodeW::NotFound,
+                    format!("Emergency with ic '{emergency_ic}' not found"),
+                )
+            })
+    }
+
+    async fn find_model_by_ic(&self, emergency_ic: &str) -> Result<Model, CustomError> {
+        Entity::find()
+            .filter(EmergencyIc.eq(emergency_ic))
+            .one(&self.conn)
+            .await?
+            .ok_or_else(|| {
+                CustomError::new(
+                    HttpCodeW::NotFound,
+                    format!("Emergency with ic '{emergency_ic}' not found"),
+                )
+            })
+    }
+
+    /// Sends every active unit of a closed emergency back to `Available`.
+    /// Returns whether any vehicle was freed.
+    async fn release_units<C: ConnectionTrait>(
+        conn: &C,
+        emergency_id: Uuid,
+        lead: Option<Uuid>,
+    ) -> Result<bool, CustomError> {
+        let mut units = active_units(conn, emergency_id).await?;
+        if let Some(lead) = lead
+            && !units.contains(&lead)
+        {
+            units.push(lead);
+        }
+        for ambulance_id in &units {
+            Self::release_ambulance(conn, *ambulance_id).await?;
+        }
+        emergency_ambulance::Entity::update_many()
+            .col_expr(
+                emergency_ambulance::Column::ReleasedAt,
+                Expr::value(now_time()),
+            )
+            .filter(emergency_ambulance::Column::EmergencyId.eq(emergency_id))
+            .filter(emergency_ambulance::Column::ReleasedAt.is_null())
+            .exec(conn)
+            .await?;
+        Ok(!units.is_empty())
+    }
+
+    /// Ids of the patients on an emergency.
+    async fn patient_ids<C: ConnectionTrait>(
+        conn: &C,
+        emergency_id: Uuid,
+    ) -> Result<Vec<Uuid>, CustomError> {
+        let rows = emergency_patient::Entity::find()
+            .filter(emergency_patient::Column::EmergencyId.eq(emergency_id))
+            .all(conn)
+            .await?;
+        Ok(rows.into_iter().map(|row| row.patient_id).collect())
+    }
+
+    /// Marks one unit as no longer working the emergency, without touching the vehicle.
+    async fn close_unit<C: ConnectionTrait>(
+        conn: &C,
+        emergency_id: Uuid,
+        ambulance_id: Uuid,
+    ) -> Result<(), CustomError> {
+        emergency_ambulance::Entity::update_many()
+            .col_expr(
+                emergency_ambulance::Column::ReleasedAt,
+                Expr::value(now_time()),
+            )
+            .filter(emergency_ambulance::Column::EmergencyId.eq(emergency_id))
+            .filter(emergency_ambulance::Column::AmbulanceId.eq(ambulance_id))
+            .filter(emergency_ambulance::Column::ReleasedAt.is_null())
+            .exec(conn)
+            .await?;
+        Ok(())
+    }
+
+    async fn release_ambulance<C: ConnectionTrait>(
+        conn: &C,
+        ambulance_id: Uuid,
+    ) -> Result<(), CustomError> {
+        let Some(ambulance) = ambulance::Entity::find_by_id(ambulance_id).one(conn).await? else {
+            return Ok(());
+        };
+        let mut ambulance_active: ambulance::ActiveModel = ambulance.into();
+        ambulance_active.status = Set(AmbulanceStatusEnum::Available);
+        ambulance_active.passengers = Set(None);
+        ambulance_active.updated_at = Set(now_time());
+        ambulance_active.update(conn).await.map_err(|e| {
+            CustomError::new(
+                HttpCodeW::InternalServerError,
+                format!("Failed to release ambulance {ambulance_id}: {e}"),
+            )
+        })?;
         Ok(())
     }
 
     /// Returns the passengers JSON for the ambulance currently assigned to an emergency, if any.
+    ///
+    /// Only the patients this unit carries are returned. A unit that has not
+    /// taken anyone on board yet claims unassigned patients of the emergency,
+    /// up to its capacity, so several units can share a mass-casualty incident.
     pub async fn get_passengers_json_for_ambulance(
         &self,
         ambulance_id: AmbulanceId,
     ) -> Result<Option<serde_json::Value>, CustomError> {
-        use crate::entity::emergency;
-        // Find the emergency where this ambulance is assigned
-        let mut query = emergency::Entity::find(); // Start with the base query
-
-        // Apply the filter based on the AmbulanceId variant
-        query = match ambulance_id {
-            AmbulanceId::Uuid(uuid_value) => {
-                // Assuming emergency::Column::AmbulanceId is of type Uuid
-                query.filter(emergency::Column::AmbulanceId.eq(Some(uuid_value)))
-            }
+        let ambulance_uuid = match ambulance_id {
+            AmbulanceId::Uuid(uuid_value) => uuid_value,
             AmbulanceId::Integer(_) => {
                 return Err(CustomError::new(
                     HttpCodeW::BadRequest,
@@ -235,35 +1341,169 @@ impl EmergencyService {
                 ))
             }
         };
-        let emergency_entity = query.one(&self.conn).await?;
-
-        if let Some(emergency) = emergency_entity {
-            // Update emergency.updated_at
-            let mut emergency_active: ActiveModel = emergency.clone().into();
-            emergency_active.updated_at = Set(now_time());
-            let _ = emergency_active.update(&self.conn).await;
+        // Find the emergency this unit is currently working
+        let Some(link) = emergency_ambulance::Entity::find()
+            .filter(emergency_ambulance::Column::AmbulanceId.eq(ambulance_uuid))
+            .filter(emergency_ambulance::Column::ReleasedAt.is_null())
+            .find_also_related(emergency::Entity)
+            .filter(emergency::Column::Status.is_not_in([
+                EmergencyStatusEnum::Resolved,
+                EmergencyStatusEnum::Cancelled,
+                EmergencyStatusEnum::Failed,
+            ]))
+            .one(&self.conn)
+            .await?
+        else {
+            return Ok(None);
+        };
+        let Some(emergency) = link.1 else {
+            return Ok(None);
+        };
 
-            // Fetch all patients for this emergency
-            let patients = self
-                .patient_service
-                .find_patients_by_emergency_id(emergency.id)
+        let txn = self.conn.begin().await?;
+        let on_board = emergency_patient::Entity::find()
+            .filter(emergency_patient::Column::EmergencyId.eq(emergency.id))
+            .filter(emergency_patient::Column::AmbulanceId.eq(ambulance_uuid))
+            .count(&txn)
+            .await?;
+        if on_board == 0 {
+            let capacity = ambulance::Entity::find_by_id(ambulance_uuid)
+                .one(&txn)
+                .await?
+                .and_then(|a| a.capacity)
+                .filter(|c| *c > 0)
+                .unwrap_or(DEFAULT_UNIT_CAPACITY);
+            let unclaimed = emergency_patient::Entity::find()
+                .filter(emergency_patient::Column::EmergencyId.eq(emergency.id))
+                .filter(emergency_patient::Column::AmbulanceId.is_null())
+                .limit(capacity as u64)
+                .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked)
+                .all(&txn)
                 .await?;
-            let passengers_json = serde_json::to_value(&patients).map_err(|e| {
-                CustomError::new(
-                    HttpCodeW::InternalServerError,
-                    format!("Serialization error: {e}"),
-                )
-            })?;
-            Ok(Some(passengers_json))
-        } else {
-            Ok(None)
+            for row in unclaimed {
+                let mut row_active: emergency_patient::ActiveModel = row.into();
+                row_active.ambulance_id = Set(Some(ambulance_uuid));
+                row_active.update(&txn).await?;
+            }
+        }
+
+        // Update emergency.updated_at
+        let mut emergency_active: ActiveModel = emergency.clone().into();
+        emergency_active.updated_at = Set(now_time());
+        emergency_active.update(&txn).await?;
+        txn.commit().await?;
+
+        let patients = emergency_patient::Entity::find()
+            .filter(emergency_patient::Column::EmergencyId.eq(emergency.id))
+            .filter(emergency_patient::Column::AmbulanceId.eq(ambulance_uuid))
+            .find_also_related(patient::Entity)
+            .all(&self.conn)
+            .await?;
+        let patients: Vec<patient::Model> = patients.into_iter().filter_map(|(_, p)| p).collect();
+        let passengers_json = serde_json::to_value(&patients).map_err(|e| {
+            CustomError::new(
+                HttpCodeW::InternalServerError,
+                format!("Serialization error: {e}"),
+            )
+        })?;
+        Ok(Some(passengers_json))
+    }
+
+    /// Filter of `GET /emergency`, 400 when the query contradicts itself.
+    pub fn list_condition(query: &EmergencyListQuery) -> Result<Condition, CustomError> {
+        let bad_request = |message: String| CustomError::new(HttpCodeW::BadRequest, message);
+        let mut condition = Condition::all();
+
+        if let Some(filter) = &query.filter {
+            match filter.split_once('=') {
+                Some(("ic", encoded_ic)) => {
+                    let emergency_ic = percent_decode_str(encoded_ic)
+                        .decode_utf8()
+                        .map(|ic| ic.to_string())
+                        .unwrap_or_else(|_| encoded_ic.to_string());
+                    condition = condition.add(EmergencyIc.eq(emergency_ic));
+                }
+                Some(("id", encoded_id)) => {
+                    let emergency_id = percent_decode_str(encoded_id)
+                        .decode_utf8()
+                        .ok()
+                        .and_then(|id| Uuid::parse_str(&id).ok())
+                        .ok_or_else(|| bad_request("Invalid UUID".to_string()))?;
+                    condition = condition.add(Id.eq(emergency_id));
+                }
+                _ => {
+                    return Err(bad_request(format!(
+                        "Unsupported filter '{filter}', expected ic=... or id=..."
+                    )));
+                }
+            }
+        }
+        if let Some(ic) = &query.ic {
+            condition = condition.add(EmergencyIc.eq(ic.clone()));
+        }
+        if let Some(statuses) = &query.status {
+            condition = condition.add(emergency::Column::Status.is_in(statuses.clone()));
+        }
+        if let Some(severities) = &query.severity {
+            condition = condition.add(emergency::Column::Severity.is_in(severities.clone()));
         }
+        if let Some(incidents) = &query.incident_type {
+            condition = condition.add(emergency::Column::IncidentType.is_in(incidents.clone()));
+        }
+        if let Some(verifications) = &query.verification_status {
+            condition = condition
+                .add(emergency::Column::VerificationStatus.is_in(verifications.clone()));
+        }
+        match query.assigned {
+            Some(true) => condition = condition.add(emergency::Column::AmbulanceId.is_not_null()),
+            Some(false) => condition = condition.add(emergency::Column::AmbulanceId.is_null()),
+            None => {}
+        }
+        if let Some(hospital_id) = query.hospital_id {
+            condition = condition.add(emergency::Column::HospitalId.eq(hospital_id));
+        }
+        if let (Some(from), Some(to)) = (query.created_from, query.created_to)
+            && from >= to
+        {
+            return Err(bad_request("created_from must be before created_to".to_string()));
+        }
+        if let Some(from) = query.created_from {
+            condition = condition.add(emergency::Column::CreatedAt.gte(from));
+        }
+        if let Some(to) = query.created_to {
+            condition = condition.add(emergency::Column::CreatedAt.lt(to));
+        }
+        match (query.min_lat, query.min_lon, query.max_lat, query.max_lon) {
+            (Some(min_latitude), Some(min_longitude), Some(max_latitude), Some(max_longitude)) => {
+                if min_latitude >= max_latitude || min_longitude >= max_longitude {
+                    return Err(bad_request(
+                        "min_lat/min_lon must be below max_lat/max_lon".to_string(),
+                    ));
+                }
+                condition = condition.add(bounding_box_condition::<Entity>(&BoundingBox {
+                    min_latitude,
+                    min_longitude,
+                    max_latitude,
+                    max_longitude,
+                }));
+            }
+            (None, None, None, None) => {}
+            _ => {
+                return Err(bad_request(
+                    "min_lat, min_lon, max_lat and max_lon go together".to_string(),
+                ));
+            }
+        }
+        Ok(condition)
     }
 
     fn generate_model(
         emergency_data: EmergencyRequestBody,
         now: NaiveDateTime,
         emergency_ic: String,
+        parent_emergency_id: Option<Uuid>,
+        verification_status: EmergencyVerificationEnum,
+        callback_phone: Option<String>,
     ) -> ActiveModel {
         ActiveModel {
             id: NotSet,
@@ -273,15 +1513,55 @@ impl EmergencyService {
             reported_by: Set(Some(1)),
             hospital_id: Set(None), // Explicitly set to NULL when creating
             notes: Set(emergency_data.notes),
-            resolved_at: Set(Option::from(now)),
+            resolved_at: Set(None),
             modification_attempts: Set(None),
             ambulance_id: NotSet,
             emergency_latitude: Set(emergency_data.emergency_latitude),
             emergency_longitude: Set(emergency_data.emergency_longitude),
             status: Set(EmergencyStatusEnum::Pending),
-            severity: Set(EmergencySeverityEnum::Unknown),
+            severity: Set(emergency_data
+                .severity
+                .unwrap_or(EmergencySeverityEnum::Unknown)),
             incident_type: Set(emergency_data.incident_type),
             description: Set(emergency_data.description),
+            geohash: NotSet,
+            eta_seconds: Set(None),
+            parent_emergency_id: Set(parent_emergency_id),
+            dispatched_at: Set(None),
+            en_route_at: Set(None),
+            at_scene_at: Set(None),
+            transporting_at: Set(None),
+            at_hospital_at: Set(None),
+            verification_status: Set(verification_status),
+            callback_phone: Set(callback_phone),
         }
     }
 }
+
+/// Largest page of `GET /emergency`.
+const MAX_PER_PAGE: i64 = 100;
+
+/// `severity_rank` as SQL, so a page can be sorted by urgency in the database.
+fn severity_rank_expr() -> SimpleExpr {
+    EmergencySeverityEnum::iter()
+        .fold(CaseStatement::new(), |case, severity| {
+            let rank = i32::from(severity_rank(&severity));
+            case.case(emergency::Column::Severity.eq(severity), rank)
+        })
+        .finally(0)
+        .into()
+}
+
+/// Patients a unit takes on board when its capacity is not recorded.
+const DEFAULT_UNIT_CAPACITY: i32 = 2;
+
+/// Statuses a dispatcher may pull a vehicle out of; off-road vehicles never qualify.
+fn can_be_dispatched(status: &AmbulanceStatusEnum) -> bool {
+    !matches!(
+        status,
+        AmbulanceStatusEnum::Maintenance
+            | AmbulanceStatusEnum::UnderRepair
+            | AmbulanceStatusEnum::OutOfService
+            | AmbulanceStatusEnum::Unavailable
+    )
+}
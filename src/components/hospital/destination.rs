@@ -0,0 +1,350 @@
+use rust_decimal::prelude::ToPrimitive;
+use sea_orm::prelude::Uuid;
+use sea_orm::*;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::components::hospital::diversion::active_diversions;
+use crate::entity::sea_orm_active_enums::{
+    AmbulanceTypeEnum, DepartmentNameEnum, EmergencyIncidentEnum, EmergencySeverityEnum,
+    EmergencyStatusEnum,
+};
+use crate::entity::{department, emergency, hospital, hospital_diversion};
+use crate::utils::eta::{Travel, estimate_matrix};
+use crate::utils::helpers::now_time;
+
+/// Minutes added to a hospital without the department the incident needs.
+const MISSING_DEPARTMENT_PENALTY_MINUTES: f64 = 30.0;
+/// Minutes added per trauma level a hospital falls short of the need.
+const TRAUMA_LEVEL_PENALTY_MINUTES: f64 = 15.0;
+/// Minutes added to a hospital whose capacity is not recorded.
+const UNKNOWN_CAPACITY_PENALTY_MINUTES: f64 = 5.0;
+/// Minutes added to a hospital without free capacity. It stays listed as a
+/// last resort rather than leaving the crew without an answer.
+const FULL_PENALTY_MINUTES: f64 = 60.0;
+/// Trauma level assumed for a hospital without a designation.
+const UNRATED_TRAUMA_LEVEL: u8 = 5;
+/// Candidates returned by a recommendation.
+const MAX_RECOMMENDATIONS: usize = 5;
+
+/// Incidents whose patients are mostly injured rather than ill.
+fn is_trauma(incident: &EmergencyIncidentEnum) -> bool {
+    use EmergencyIncidentEnum::*;
+    matches!(
+        incident,
+        CarAccident
+            | MotorcycleAccident
+            | PedestrianAccident
+            | TrainAccident
+            | AirplaneCrash
+            | ShipAccident
+            | SevereBurns
+            | Electrocution
+            | FallInjury
+            | Fracture
+            | Bleeding
+            | Explosion
+            | IndustrialAccident
+            | Shooting
+            | Stabbing
+            | Assault
+            | Earthquake
+            | BuildingCollapse
+            | BridgeCollapse
+    )
+}
+
+/// Department a patient of this incident should be admitted to, if any in particular.
+pub fn required_department(incident: &EmergencyIncidentEnum) -> Option<DepartmentNameEnum> {
+    use EmergencyIncidentEnum::*;
+    let department = match incident {
+        HeartAttack => DepartmentNameEnum::Cardiology,
+        Stroke | Seizure => DepartmentNameEnum::Neurology,
+        Childbirth => DepartmentNameEnum::ObstetricsGynecology,
+        _ if is_trauma(incident) => DepartmentNameEnum::Surgery,
+        DiabeticEmergency
+        | AllergicReaction
+        | BreathingProblem
+        | Poisoning
+        | Drowning
+        | GasLeak
+        | ChemicalSpill
+        | RadiationExposure
+        | BiologicalHazard
+        | Pandemic
+        | InfectiousDiseaseOutbreak => DepartmentNameEnum::InternalMedicine,
+        _ => return None,
+    };
+    Some(department)
+}
+
+/// Lowest trauma designation (1 is the highest) able to take the patient,
+/// `None` when the incident is not a trauma case.
+pub fn required_trauma_level(
+    incident: &EmergencyIncidentEnum,
+    severity: &EmergencySeverityEnum,
+) -> Option<u8> {
+    use EmergencySeverityEnum::*;
+    if !is_trauma(incident) {
+        return None;
+    }
+    let level = match severity {
+        Critical | Severe | Extreme | Unstable => 1,
+        High => 2,
+        _ => 3,
+    };
+    Some(level)
+}
+
+/// Reads `hospital.trauma_level`, written by hand as `Level II`, `II`, `2`...
+pub fn parse_trauma_level(value: &str) -> Option<u8> {
+    let value = value.trim().to_ascii_uppercase();
+    let value = value.strip_prefix("LEVEL").unwrap_or(&value).trim();
+    let level = match value {
+        "I" | "1" => 1,
+        "II" | "2" => 2,
+        "III" | "3" => 3,
+        "IV" | "4" => 4,
+        "V" | "5" => 5,
+        _ => return None,
+    };
+    Some(level)
+}
+
+/// What the destination of an emergency has to offer.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DestinationNeeds {
+    pub department: Option<DepartmentNameEnum>,
+    pub trauma_level: Option<u8>,
+}
+
+impl DestinationNeeds {
+    pub fn of(incident: &EmergencyIncidentEnum, severity: &EmergencySeverityEnum) -> Self {
+        DestinationNeeds {
+            department: required_department(incident),
+            trauma_level: required_trauma_level(incident, severity),
+        }
+    }
+}
+
+/// A hospital the patient could be taken to, with how well it fits.
+#[derive(Debug, Clone, Serialize)]
+pub struct DestinationCandidate {
+    pub hospital_id: Uuid,
+    pub hospital_name: String,
+    pub distance_km: f64,
+    pub eta_seconds: i32,
+    pub capacity: Option<i32>,
+    /// Open emergencies already bound for this hospital.
+    pub inbound: i32,
+    pub available_capacity: Option<i32>,
+    pub trauma_level: Option<u8>,
+    pub has_department: bool,
+    /// The whole emergency department is on diversion. Such hospitals are
+    /// left out of recommendations.
+    pub diverted: bool,
+    /// Drive time in minutes plus penalties; lower is better.
+    pub score: f64,
+    /// Why the hospital is a worse fit than its drive time suggests.
+    pub warnings: Vec<String>,
+}
+
+/// Scores one hospital for the given needs. `diversions` are the ones in
+/// force at the hospital; a diverted department weighs like a missing one.
+pub fn assess(
+    needs: &DestinationNeeds,
+    hospital: &hospital::Model,
+    travel: &Travel,
+    inbound: i32,
+    departments: &[DepartmentNameEnum],
+    diversions: &[hospital_diversion::Model],
+) -> DestinationCandidate {
+    let mut score = travel.eta_minutes();
+    let mut warnings = Vec::new();
+
+    let diverted = diversions.iter().any(|d| d.department.is_none());
+    let mut department_diverted = false;
+    for diversion in diversions {
+        if diversion.department.is_some() && diversion.department != needs.department {
+            continue;
+        }
+        department_diverted |= diversion.department.is_some();
+        warnings.push(format!("on diversion: {}", diversion.reason));
+    }
+
+    let has_department = needs
+        .department
+        .as_ref()
+        .is_none_or(|needed| departments.contains(needed));
+    if !has_department {
+        score += MISSING_DEPARTMENT_PENALTY_MINUTES;
+        warnings.push(format!(
+            "no {} department",
+            serde_json::to_value(&needs.department)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default()
+        ));
+    } else if department_diverted {
+        score += MISSING_DEPARTMENT_PENALTY_MINUTES;
+    }
+
+    let trauma_level = hospital
+        .trauma_level
+        .as_deref()
+        .and_then(parse_trauma_level);
+    if let Some(needed) = needs.trauma_level {
+        let shortfall = trauma_level
+            .unwrap_or(UNRATED_TRAUMA_LEVEL)
+            .saturating_sub(needed);
+        if shortfall > 0 {
+            score += f64::from(shortfall) * TRAUMA_LEVEL_PENALTY_MINUTES;
+            warnings.push(format!("trauma level {needed} or better needed"));
+        }
+    }
+
+    let available_capacity = hospital.capacity.map(|capacity| capacity - inbound);
+    match available_capacity {
+        None => {
+            score += UNKNOWN_CAPACITY_PENALTY_MINUTES;
+            warnings.push("capacity unknown".to_string());
+        }
+        Some(available) if available <= 0 => {
+            score += FULL_PENALTY_MINUTES;
+            warnings.push("no free capacity".to_string());
+        }
+        Some(_) => {}
+    }
+
+    DestinationCandidate {
+        hospital_id: hospital.id,
+        hospital_name: hospital.name.clone(),
+        distance_km: travel.distance_km,
+        eta_seconds: travel.eta_seconds_rounded(),
+        capacity: hospital.capacity,
+        inbound,
+        available_capacity,
+        trauma_level,
+        has_department,
+        diverted,
+        score,
+        warnings,
+    }
+}
+
+/// Best candidates first; ties go to the closer hospital.
+pub fn rank(mut candidates: Vec<DestinationCandidate>) -> Vec<DestinationCandidate> {
+    candidates.sort_by(|a, b| {
+        a.score
+            .total_cmp(&b.score)
+            .then(a.distance_km.total_cmp(&b.distance_km))
+    });
+    candidates
+}
+
+/// Ranked destinations for an emergency, as returned by
+/// `GET /ambulance/{uuid}/destinations`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DestinationRecommendation {
+    pub emergency_id: Uuid,
+    pub emergency_ic: String,
+    pub needs: DestinationNeeds,
+    pub candidates: Vec<DestinationCandidate>,
+}
+
+pub struct DestinationService {
+    conn: DatabaseConnection,
+}
+
+impl DestinationService {
+    pub fn new(conn: &DatabaseConnection) -> Self {
+        Self { conn: conn.clone() }
+    }
+
+    /// Ranks the located hospitals for a patient of `emergency` carried by a
+    /// vehicle of type `vehicle` at `origin`.
+    pub async fn recommend(
+        &self,
+        emergency: &emergency::Model,
+        origin: (f64, f64),
+        vehicle: &AmbulanceTypeEnum,
+    ) -> Result<DestinationRecommendation, DbErr> {
+        let needs = DestinationNeeds::of(&emergency.incident_type, &emergency.severity);
+        let located: Vec<(hospital::Model, (f64, f64))> = hospital::Entity::find()
+            .filter(hospital::Column::Latitude.is_not_null())
+            .filter(hospital::Column::Longitude.is_not_null())
+            .all(&self.conn)
+            .await?
+            .into_iter()
+            .filter_map(|h| {
+                let position = (h.latitude?.to_f64()?, h.longitude?.to_f64()?);
+                Some((h, position))
+            })
+            .collect();
+        let ids: Vec<Uuid> = located.iter().map(|(h, _)| h.id).collect();
+
+        let mut departments: HashMap<Uuid, Vec<DepartmentNameEnum>> = HashMap::new();
+        for row in department::Entity::find()
+            .filter(department::Column::HospitalId.is_in(ids.clone()))
+            .all(&self.conn)
+            .await?
+        {
+            departments
+                .entry(row.hospital_id)
+                .or_default()
+                .push(row.name);
+        }
+
+        let diversions = active_diversions(&self.conn, ids.clone(), now_time()).await?;
+
+        let mut inbound: HashMap<Uuid, i32> = HashMap::new();
+        for open in emergency::Entity::find()
+            .filter(emergency::Column::HospitalId.is_in(ids))
+            .filter(emergency::Column::Id.ne(emergency.id))
+            .filter(emergency::Column::Status.is_not_in([
+                EmergencyStatusEnum::Resolved,
+                EmergencyStatusEnum::Cancelled,
+                EmergencyStatusEnum::Failed,
+            ]))
+            .all(&self.conn)
+            .await?
+        {
+            if let Some(hospital_id) = open.hospital_id {
+                *inbound.entry(hospital_id).or_default() += 1;
+            }
+        }
+
+        let targets: Vec<(f64, f64)> = located.iter().map(|(_, position)| *position).collect();
+        let travel = estimate_matrix(&targets, &[(origin, vehicle.clone())]);
+        let candidates = located
+            .iter()
+            .zip(&travel)
+            .map(|((hospital, _), row)| {
+                assess(
+                    &needs,
+                    hospital,
+                    &row[0],
+                    inbound.get(&hospital.id).copied().unwrap_or_default(),
+                    departments
+                        .get(&hospital.id)
+                        .map(Vec::as_slice)
+                        .unwrap_or_default(),
+                    diversions
+                        .get(&hospital.id)
+                        .map(Vec::as_slice)
+                        .unwrap_or_default(),
+                )
+            })
+            .filter(|candidate| !candidate.diverted)
+            .collect();
+
+        let mut candidates = rank(candidates);
+        candidates.truncate(MAX_RECOMMENDATIONS);
+        Ok(DestinationRecommendation {
+            emergency_id: emergency.id,
+            emergency_ic: emergency.emergency_ic.clone(),
+            needs,
+            candidates,
+        })
+    }
+}
@@ -0,0 +1,201 @@
+use chrono::NaiveDateTime;
+use sea_orm::prelude::Uuid;
+use sea_orm::*;
+use std::collections::HashMap;
+
+use crate::entity::hospital_diversion::{
+    ActiveModel, Column, DiversionPayload, DiversionQuery, Entity, HospitalDiversion, Model,
+};
+use crate::entity::{department, hospital};
+use crate::http_response::HttpCodeW;
+use crate::http_response::error_handler::CustomError;
+use crate::utils::helpers::now_time;
+
+/// Condition matching the diversions in force at `at`.
+fn active_at(at: NaiveDateTime) -> Condition {
+    Condition::all()
+        .add(Column::LiftedAt.is_null())
+        .add(Column::StartsAt.lte(at))
+        .add(
+            Condition::any()
+                .add(Column::EndsAt.is_null())
+                .add(Column::EndsAt.gt(at)),
+        )
+}
+
+/// Diversions in force at `at` for the given hospitals, by hospital.
+pub async fn active_diversions<C: ConnectionTrait>(
+    conn: &C,
+    hospital_ids: Vec<Uuid>,
+    at: NaiveDateTime,
+) -> Result<HashMap<Uuid, Vec<Model>>, DbErr> {
+    let mut by_hospital: HashMap<Uuid, Vec<Model>> = HashMap::new();
+    for diversion in Entity::find()
+        .filter(Column::HospitalId.is_in(hospital_ids))
+        .filter(active_at(at))
+        .all(conn)
+        .await?
+    {
+        by_hospital
+            .entry(diversion.hospital_id)
+            .or_default()
+            .push(diversion);
+    }
+    Ok(by_hospital)
+}
+
+pub struct DiversionService {
+    conn: DatabaseConnection,
+}
+
+impl DiversionService {
+    pub fn new(conn: &DatabaseConnection) -> Self {
+        Self { conn: conn.clone() }
+    }
+
+    /// Declares a diversion of a hospital or of one of its departments.
+    pub async fn declare(
+        &self,
+        hospital_id: Uuid,
+        payload: DiversionPayload,
+        actor: &str,
+    ) -> Result<Model, CustomError> {
+        let now = now_time();
+        let starts_at = payload.starts_at.unwrap_or(now);
+        if payload.reason.trim().is_empty() {
+            return Err(CustomError::new(
+                HttpCodeW::BadRequest,
+                "reason is required".to_string(),
+            ));
+        }
+        if payload.ends_at.is_some_and(|ends_at| ends_at <= starts_at) {
+            return Err(CustomError::new(
+                HttpCodeW::BadRequest,
+                "ends_at must be after starts_at".to_string(),
+            ));
+        }
+        if payload.ends_at.is_some_and(|ends_at| ends_at <= now) {
+            return Err(CustomError::new(
+                HttpCodeW::BadRequest,
+                "ends_at must be in the future".to_string(),
+            ));
+        }
+        if hospital::Entity::find_by_id(hospital_id)
+            .one(&self.conn)
+            .await?
+            .is_none()
+        {
+            return Err(CustomError::new(
+                HttpCodeW::NotFound,
+                "Hospital not found".to_string(),
+            ));
+        }
+        if let Some(name) = &payload.department {
+            let exists = department::Entity::find()
+                .filter(department::Column::HospitalId.eq(hospital_id))
+                .filter(department::Column::Name.eq(name.clone()))
+                .one(&self.conn)
+                .await?
+                .is_some();
+            if !exists {
+                return Err(CustomError::new(
+                    HttpCodeW::UnprocessableEntity,
+                    "The hospital has no such department".to_string(),
+                ));
+            }
+        }
+
+        let diversion = ActiveModel {
+            id: Set(Uuid::new_v4()),
+            hospital_id: Set(hospital_id),
+            department: Set(payload.department),
+            reason: Set(payload.reason.trim().to_string()),
+            starts_at: Set(starts_at),
+            ends_at: Set(payload.ends_at),
+            declared_by: Set(actor.to_string()),
+            lifted_at: Set(None),
+            lifted_by: Set(None),
+            created_at: Set(now),
+        }
+        .insert(&self.conn)
+        .await?;
+        Ok(diversion)
+    }
+
+    /// Ends a diversion now. Lifting one that already ended is a conflict.
+    pub async fn lift(&self, diversion_id: Uuid, actor: &str) -> Result<Model, CustomError> {
+        let now = now_time();
+        let diversion = Entity::find_by_id(diversion_id)
+            .one(&self.conn)
+            .await?
+            .ok_or(CustomError::new(
+                HttpCodeW::NotFound,
+                "Diversion not found".to_string(),
+            ))?;
+        if diversion.lifted_at.is_some() || diversion.ends_at.is_some_and(|ends_at| ends_at <= now)
+        {
+            return Err(CustomError::new(
+                HttpCodeW::Conflict,
+                "Diversion has already ended".to_string(),
+            ));
+        }
+        let mut active_model: ActiveModel = diversion.into();
+        active_model.lifted_at = Set(Some(now));
+        active_model.lifted_by = Set(Some(actor.to_string()));
+        Ok(active_model.update(&self.conn).await?)
+    }
+
+    /// Every diversion of a hospital, past and upcoming, latest first.
+    pub async fn find_for_hospital(
+        &self,
+        hospital_id: Uuid,
+    ) -> Result<Vec<HospitalDiversion>, CustomError> {
+        let hospital = hospital::Entity::find_by_id(hospital_id)
+            .one(&self.conn)
+            .await?
+            .ok_or(CustomError::new(
+                HttpCodeW::NotFound,
+                "Hospital not found".to_string(),
+            ))?;
+        let now = now_time();
+        let diversions = Entity::find()
+            .filter(Column::HospitalId.eq(hospital_id))
+            .order_by_desc(Column::StartsAt)
+            .all(&self.conn)
+            .await?
+            .into_iter()
+            .map(|diversion| HospitalDiversion {
+                active: diversion.is_active_at(now),
+                diversion,
+                hospital_name: hospital.name.clone(),
+            })
+            .collect();
+        Ok(diversions)
+    }
+
+    /// Diversions in force at the queried time, soonest to end first.
+    pub async fn find_active(
+        &self,
+        query: DiversionQuery,
+    ) -> Result<Vec<HospitalDiversion>, CustomError> {
+        let at = query.at.unwrap_or_else(now_time);
+        let mut select = Entity::find()
+            .find_also_related(hospital::Entity)
+            .filter(active_at(at));
+        if let Some(hospital_id) = query.hospital_id {
+            select = select.filter(Column::HospitalId.eq(hospital_id));
+        }
+        let mut diversions: Vec<HospitalDiversion> = select
+            .all(&self.conn)
+            .await?
+            .into_iter()
+            .map(|(diversion, hospital)| HospitalDiversion {
+                active: diversion.is_active_at(at),
+                diversion,
+                hospital_name: hospital.map(|h| h.name).unwrap_or_default(),
+            })
+            .collect();
+        diversions.sort_by_key(|d| (d.diversion.ends_at.is_none(), d.diversion.ends_at));
+        Ok(diversions)
+    }
+}
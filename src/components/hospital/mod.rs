@@ -1,3 +1,5 @@
+pub(crate) mod destination;
+pub(crate) mod diversion;
 pub mod routes;
 mod services;
 
@@ -1,10 +1,15 @@
+use crate::components::ambulance::compliance::ComplianceService;
 use crate::components::hospital::HospitalService;
+use crate::components::hospital::diversion::DiversionService;
 use crate::entity::hospital::HospitalRequestBody;
+use crate::entity::hospital_diversion::{DiversionPayload, DiversionQuery};
 use crate::http_response::error_handler::CustomError;
 use crate::http_response::http_response_builder;
+use crate::security::subject::Subject;
 use crate::shared::PaginationParams;
 use actix_web::{HttpResponse, get, post, web};
 use sea_orm::DatabaseConnection;
+use uuid::Uuid;
 
 #[post("/hospital")]
 async fn create(
@@ -45,7 +50,75 @@ pub async fn find_all(
     let response = http_response_builder::ok(hospital);
     Ok(HttpResponse::Ok().json(response))
 }
+#[get("/hospital/diversions")]
+pub async fn find_active_diversions(
+    query: web::Query<DiversionQuery>,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service = DiversionService::new(db_conn.get_ref());
+    let diversions = service.find_active(query.into_inner()).await?;
+    let response = http_response_builder::ok(diversions);
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[post("/hospital/{hospital_id}/diversions")]
+pub async fn declare_diversion(
+    hospital_id: web::Path<Uuid>,
+    payload: web::Json<DiversionPayload>,
+    subject: Subject,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service = DiversionService::new(db_conn.get_ref());
+    let diversion = service
+        .declare(hospital_id.into_inner(), payload.into_inner(), &subject.sub)
+        .await?;
+    let response = http_response_builder::ok(diversion);
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[get("/hospital/{hospital_id}/diversions")]
+pub async fn find_diversions(
+    hospital_id: web::Path<Uuid>,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service = DiversionService::new(db_conn.get_ref());
+    let diversions = service.find_for_hospital(hospital_id.into_inner()).await?;
+    let response = http_response_builder::ok(diversions);
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[post("/hospital/diversions/{diversion_id}/lift")]
+pub async fn lift_diversion(
+    diversion_id: web::Path<Uuid>,
+    subject: Subject,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service = DiversionService::new(db_conn.get_ref());
+    let diversion = service
+        .lift(diversion_id.into_inner(), &subject.sub)
+        .await?;
+    let response = http_response_builder::ok(diversion);
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[get("/hospital/{hospital_id}/fleet-compliance")]
+pub async fn find_fleet_compliance(
+    hospital_id: web::Path<Uuid>,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service = ComplianceService::new(db_conn.get_ref());
+    let report = service.report(hospital_id.into_inner()).await?;
+    let response = http_response_builder::ok(report);
+    Ok(HttpResponse::Ok().json(response))
+}
+
 pub fn init_routes(config: &mut web::ServiceConfig) {
+    // Before `find`, which would take `diversions` for a hospital name
+    config.service(find_active_diversions);
+    config.service(declare_diversion);
+    config.service(find_diversions);
+    config.service(lift_diversion);
+    config.service(find_fleet_compliance);
     config.service(find);
     config.service(find_all);
     config.service(create);
@@ -244,6 +244,7 @@ impl PatientService {
         let junction = emergency_patient::ActiveModel {
             emergency_id: Set(emergency_id),
             patient_id: Set(created_patient.patient.id),
+            ambulance_id: Set(None),
         };
         junction.insert(transaction).await.map_err(|e| {
             CustomError::new(
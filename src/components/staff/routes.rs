@@ -1,10 +1,12 @@
 use super::services::StaffService;
 use crate::entity::staff::StaffRequestBody;
+use crate::entity::staff_schedule::SchedulePayload;
 use crate::http_response::error_handler::CustomError;
 use crate::http_response::http_response_builder;
 use actix_web::{get, post, web, HttpResponse};
 use sea_orm::DatabaseConnection;
 use std::collections::HashMap;
+use uuid::Uuid;
 
 #[post("/staff")]
 async fn create(
@@ -43,7 +45,34 @@ pub async fn find_all(
     let response = http_response_builder::ok(person);
     Ok(HttpResponse::Ok().json(response))
 }
+#[post("/staff/{staff_id}/schedule")]
+pub async fn add_schedule(
+    staff_id: web::Path<Uuid>,
+    payload: web::Json<SchedulePayload>,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service_instance = StaffService::new(db_conn.get_ref());
+    let schedule = service_instance
+        .add_schedule(staff_id.into_inner(), payload.into_inner())
+        .await?;
+    let response = http_response_builder::ok(schedule);
+    Ok(HttpResponse::Ok().json(response))
+}
+#[get("/staff/{staff_id}/schedule")]
+pub async fn find_schedule(
+    staff_id: web::Path<Uuid>,
+    db_conn: web::Data<DatabaseConnection>,
+) -> Result<HttpResponse, CustomError> {
+    let service_instance = StaffService::new(db_conn.get_ref());
+    let schedule = service_instance
+        .upcoming_schedule(staff_id.into_inner())
+        .await?;
+    let response = http_response_builder::ok(schedule);
+    Ok(HttpResponse::Ok().json(response))
+}
 pub fn init_routes(config: &mut web::ServiceConfig) {
     config.service(create);
     config.service(find_all);
+    config.service(add_schedule);
+    config.service(find_schedule);
 }
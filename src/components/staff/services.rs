@@ -1,19 +1,22 @@
 use crate::components::department::DepartmentService;
 use crate::components::hospital::HospitalService;
 use crate::components::person::PersonService;
+use crate::components::ambulance::crew::shift_window;
 use crate::entity::person;
 use crate::entity::person::PersonRequestBody;
 use crate::entity::sea_orm_active_enums::StaffRoleEnum;
+use crate::entity::staff_schedule;
+use crate::entity::staff_schedule::SchedulePayload;
 use crate::entity::staff::{
     ActiveModel, Column, Entity, Model, Relation, StaffRequestBody, StaffWithPerson,
 };
 use crate::http_response::error_handler::CustomError;
 use crate::http_response::HttpCodeW;
 use crate::shared::{PaginatedResponse, PaginationInfo};
-use crate::utils::helpers::{check_if_is_duplicate_key_from_data_base, generate_ic};
-use chrono::{Local, NaiveDateTime};
+use crate::utils::helpers::{check_if_is_duplicate_key_from_data_base, generate_ic, now_time};
+use chrono::{Duration, Local, NaiveDateTime};
 use sea_orm::{ActiveModelTrait, Condition, QuerySelect, RelationTrait};
-use sea_orm::{ColumnTrait, QueryFilter, Set};
+use sea_orm::{ColumnTrait, QueryFilter, QueryOrder, Set};
 use sea_orm::{DatabaseConnection, EntityTrait};
 use uuid::Uuid;
 
@@ -285,6 +288,74 @@ impl StaffService {
         }
     }
 
+    /// Adds a shift to a staff member's schedule, refusing one that overlaps
+    /// a shift they already have.
+    pub async fn add_schedule(
+        &self,
+        staff_id: Uuid,
+        payload: SchedulePayload,
+    ) -> Result<staff_schedule::Model, CustomError> {
+        let staff = Entity::find_by_id(staff_id)
+            .one(&self.conn)
+            .await?
+            .ok_or_else(|| CustomError::new(HttpCodeW::NotFound, "Staff not found".to_string()))?;
+        let (starts_at, ends_at) =
+            shift_window(payload.schedule_date, payload.start_time, payload.end_time);
+        let neighbours = staff_schedule::Entity::find()
+            .filter(staff_schedule::Column::StaffId.eq(staff_id))
+            .filter(staff_schedule::Column::ScheduleDate.between(
+                payload.schedule_date - Duration::days(1),
+                payload.schedule_date + Duration::days(1),
+            ))
+            .all(&self.conn)
+            .await?;
+        if neighbours.iter().any(|shift| {
+            let (start, end) = shift_window(shift.schedule_date, shift.start_time, shift.end_time);
+            start < ends_at && starts_at < end
+        }) {
+            return Err(CustomError::new(
+                HttpCodeW::Conflict,
+                "The shift overlaps another shift of this staff member".to_string(),
+            ));
+        }
+
+        let now = now_time();
+        let schedule = staff_schedule::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            staff_schedule_ic: Set(generate_ic().to_string()),
+            staff_id: Set(staff.id),
+            department_id: Set(payload.department_id.unwrap_or(staff.department_id)),
+            hospital_id: Set(staff.hospital_id),
+            schedule_date: Set(payload.schedule_date),
+            start_time: Set(payload.start_time),
+            end_time: Set(payload.end_time),
+            created_at: Set(now),
+            updated_at: Set(now),
+        }
+        .insert(&self.conn)
+        .await?;
+        Ok(schedule)
+    }
+
+    /// Shifts of a staff member that have not ended yet, earliest first.
+    pub async fn upcoming_schedule(
+        &self,
+        staff_id: Uuid,
+    ) -> Result<Vec<staff_schedule::Model>, CustomError> {
+        let now = now_time();
+        let shifts = staff_schedule::Entity::find()
+            .filter(staff_schedule::Column::StaffId.eq(staff_id))
+            .filter(staff_schedule::Column::ScheduleDate.gte(now.date() - Duration::days(1)))
+            .order_by_asc(staff_schedule::Column::ScheduleDate)
+            .order_by_asc(staff_schedule::Column::StartTime)
+            .all(&self.conn)
+            .await?;
+        Ok(shifts
+            .into_iter()
+            .filter(|shift| shift_window(shift.schedule_date, shift.start_time, shift.end_time).1 > now)
+            .collect())
+    }
+
     fn generate_model(
         p0: Option<StaffRequestBody>,
         p1: NaiveDateTime,
@@ -4,6 +4,8 @@ use super::sea_orm_active_enums::AmbulanceCarDetailsMakeEnum;
 use super::sea_orm_active_enums::AmbulanceCarDetailsModelEnum;
 use super::sea_orm_active_enums::AmbulanceStatusEnum;
 use super::sea_orm_active_enums::AmbulanceTypeEnum;
+use crate::utils::spatial::geohash_of_decimal;
+use sea_orm::ActiveValue::Set;
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -28,6 +30,8 @@ pub struct Model {
     pub last_service_date: Option<DateTime>,
     pub next_service_date: Option<DateTime>,
     pub mileage: Option<i32>,
+    /// Odometer reading at the last service.
+    pub last_service_mileage: Option<i32>,
     pub fuel_type: Option<String>,
     pub registration_number: Option<String>,
     pub insurance_provider: Option<String>,
@@ -43,10 +47,14 @@ pub struct Model {
     pub location_latitude: Decimal,
     #[sea_orm(column_type = "Decimal(Some((9, 6)))")]
     pub location_longitude: Decimal,
+    /// Time of the fix or manual edit behind the current position; older
+    /// telemetry does not move the ambulance back.
+    pub location_updated_at: Option<DateTime>,
     pub r#type: AmbulanceTypeEnum,
     pub status: AmbulanceStatusEnum,
     pub car_details_make: AmbulanceCarDetailsMakeEnum,
     pub car_details_model: AmbulanceCarDetailsModelEnum,
+    pub geohash: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -65,7 +73,24 @@ impl Related<super::emergency::Entity> for Entity {
     }
 }
 
-impl ActiveModelBehavior for ActiveModel {}
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    /// Keeps `geohash` in step with the coordinates whenever they are written.
+    async fn before_save<C>(mut self, _db: &C, _insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if (self.location_latitude.is_set() || self.location_longitude.is_set())
+            && let (Some(latitude), Some(longitude)) = (
+                self.location_latitude.try_as_ref(),
+                self.location_longitude.try_as_ref(),
+            )
+        {
+            self.geohash = Set(geohash_of_decimal(*latitude, *longitude));
+        }
+        Ok(self)
+    }
+}
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 // Payload structure for creating or updating an ambulance entity
@@ -85,9 +110,17 @@ pub struct AmbulancePayload {
     pub driver_license: Option<String>,
     #[serde(rename = "hospitalName")]
     pub hospital_name: Option<String>,
+    /// Destination chosen by id when transporting; wins over `hospitalName`.
+    #[serde(rename = "hospitalId")]
+    pub hospital_id: Option<Uuid>,
+    /// Take the top recommended destination when transporting without one.
+    #[serde(rename = "autoDestination", default)]
+    pub auto_destination: bool,
     pub last_service_date: Option<DateTime>,
     pub next_service_date: Option<DateTime>,
     pub mileage: Option<i32>,
+    /// Odometer reading at the last service.
+    pub last_service_mileage: Option<i32>,
     #[serde(rename = "fuelType")]
     pub fuel_type: Option<String>,
     pub registration_number: Option<String>,
@@ -122,3 +155,42 @@ pub struct StatusDto {
     pub value: String,
     pub label: String,
 }
+
+/// Query of `GET /ambulance/status`.
+#[derive(Debug, Deserialize)]
+pub struct AmbulanceStatusQuery {
+    /// Current status; only the statuses reachable from it are listed.
+    pub from: Option<AmbulanceStatusEnum>,
+}
+
+/// Query of `GET /ambulance/nearby`.
+#[derive(Debug, Deserialize)]
+pub struct NearbyAmbulanceQuery {
+    pub lat: f64,
+    pub lon: f64,
+    pub radius_km: Option<f64>,
+    pub status: Option<AmbulanceStatusEnum>,
+}
+
+/// An ambulance found by a radius search, with its distance from the centre.
+#[derive(Debug, Serialize)]
+pub struct NearbyAmbulance {
+    #[serde(flatten)]
+    pub ambulance: Model,
+    pub distance_km: f64,
+}
+
+/// Query of `GET /ambulance/coverage`, also read from the `data_config` of a
+/// coverage dashboard card. The area defaults to the box around every
+/// ambulance and hospital.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CoverageQuery {
+    pub min_lat: Option<f64>,
+    pub min_lon: Option<f64>,
+    pub max_lat: Option<f64>,
+    pub max_lon: Option<f64>,
+    pub cell_km: Option<f64>,
+    pub target_minutes: Option<f64>,
+    /// Most repositioning moves to suggest.
+    pub max_moves: Option<usize>,
+}
@@ -0,0 +1,83 @@
+//! SeaORM Entity for the ambulance_crew table
+
+use super::sea_orm_active_enums::{CrewRoleEnum, StaffRoleEnum};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A staff member rostered on an ambulance for one of their shifts.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "ambulance_crew")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub ambulance_id: Uuid,
+    pub staff_id: Uuid,
+    /// Shift worked on the ambulance; each shift is on one ambulance only.
+    #[sea_orm(unique)]
+    pub staff_schedule_id: Uuid,
+    pub role: CrewRoleEnum,
+    /// `sub` of the user who made the assignment.
+    pub assigned_by: String,
+    pub created_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::ambulance::Entity",
+        from = "Column::AmbulanceId",
+        to = "super::ambulance::Column::Id"
+    )]
+    Ambulance,
+    #[sea_orm(
+        belongs_to = "super::staff_schedule::Entity",
+        from = "Column::StaffScheduleId",
+        to = "super::staff_schedule::Column::Id"
+    )]
+    StaffSchedule,
+}
+
+impl Related<super::ambulance::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Ambulance.def()
+    }
+}
+
+impl Related<super::staff_schedule::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::StaffSchedule.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// Body of `POST /ambulance/{id}/crew`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CrewAssignmentPayload {
+    pub staff_schedule_id: Uuid,
+    pub role: CrewRoleEnum,
+}
+
+/// A crew member as shown with an ambulance.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct CrewMember {
+    pub crew_id: Uuid,
+    pub staff_id: Uuid,
+    pub name: String,
+    pub staff_role: StaffRoleEnum,
+    pub role: CrewRoleEnum,
+    pub shift_start: DateTime,
+    pub shift_end: DateTime,
+    /// Whether the shift is running now.
+    pub on_duty: bool,
+}
+
+/// An ambulance with its current and upcoming crew.
+#[derive(Debug, Serialize, Clone)]
+pub struct AmbulanceWithCrew {
+    #[serde(flatten)]
+    pub ambulance: super::ambulance::Model,
+    pub crew: Vec<CrewMember>,
+    /// Roles still missing from the crew on duty for the minimum of its type.
+    pub missing_crew: Vec<CrewRoleEnum>,
+}
@@ -0,0 +1,103 @@
+//! SeaORM Entity for the ambulance_location table
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// One GPS fix reported by the device of an ambulance. Rows are never
+/// updated; they are only removed once older than the retention period.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "ambulance_location")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub ambulance_id: Uuid,
+    /// Time of the fix on the device.
+    pub recorded_at: DateTime,
+    #[sea_orm(column_type = "Decimal(Some((9, 6)))")]
+    pub latitude: Decimal,
+    #[sea_orm(column_type = "Decimal(Some((9, 6)))")]
+    pub longitude: Decimal,
+    #[sea_orm(column_type = "Double", nullable)]
+    pub speed_kmh: Option<f64>,
+    /// Degrees clockwise from north.
+    #[sea_orm(column_type = "Double", nullable)]
+    pub heading: Option<f64>,
+    #[sea_orm(column_type = "Double", nullable)]
+    pub accuracy_m: Option<f64>,
+    pub received_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::ambulance::Entity",
+        from = "Column::AmbulanceId",
+        to = "super::ambulance::Column::Id"
+    )]
+    Ambulance,
+}
+
+impl Related<super::ambulance::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Ambulance.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// One point of a telemetry batch.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TelemetryPoint {
+    pub latitude: Decimal,
+    pub longitude: Decimal,
+    pub speed_kmh: Option<f64>,
+    pub heading: Option<f64>,
+    pub accuracy_m: Option<f64>,
+    pub recorded_at: DateTime,
+}
+
+/// Body of `POST /ambulance/{id}/telemetry`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TelemetryBatch {
+    pub points: Vec<TelemetryPoint>,
+}
+
+/// A point of a batch that was not stored, by its index in the batch.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct RejectedPoint {
+    pub index: usize,
+    pub reason: String,
+}
+
+/// Outcome of a telemetry batch.
+#[derive(Debug, Serialize, Clone)]
+pub struct TelemetryReceipt {
+    pub stored: u64,
+    /// Points already received, e.g. from a resent batch.
+    pub duplicates: u64,
+    pub rejected: Vec<RejectedPoint>,
+    /// Whether the batch held a fix newer than the current position.
+    pub position_updated: bool,
+    /// Status change applied or proposed because the new position crossed a geofence.
+    pub geofence_event: Option<super::geofence_event::Model>,
+}
+
+/// Query of `GET /ambulance/{id}/track`. Defaults to the last hour.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrackQuery {
+    pub from: Option<DateTime>,
+    pub to: Option<DateTime>,
+}
+
+/// Path an ambulance drove over a time range, oldest point first.
+#[derive(Debug, Serialize, Clone)]
+pub struct TrackReplay {
+    pub ambulance_id: Uuid,
+    pub from: DateTime,
+    pub to: DateTime,
+    /// Straight-line distance between consecutive points.
+    pub distance_km: f64,
+    /// Set when the range holds more points than one replay returns.
+    pub truncated: bool,
+    pub points: Vec<Model>,
+}
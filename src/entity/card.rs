@@ -1,5 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.11
 
+use super::ambulance::CoverageQuery;
 use super::sea_orm_active_enums::CardSizeEnum;
 use super::sea_orm_active_enums::CardTypeEnum;
 use sea_orm::entity::prelude::*;
@@ -56,3 +57,12 @@ pub struct CardPayload {
     pub card_type: Option<CardTypeEnum>,
     pub size: Option<CardSizeEnum>,
 }
+
+/// Live data behind a card, read from `data_config`, e.g.
+/// `{"source": "coverage", "cell_km": 3}`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum CardDataSource {
+    /// Ambulance coverage report, configured like `GET /ambulance/coverage`.
+    Coverage(CoverageQuery),
+}
@@ -0,0 +1,68 @@
+//! SeaORM Entity for the dispatch_decision audit table
+
+use super::sea_orm_active_enums::AmbulanceTypeEnum;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Why the allocator sent a given ambulance to an emergency.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "dispatch_decision")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub emergency_id: Uuid,
+    pub ambulance_id: Uuid,
+    pub strategy: String,
+    pub capability: String,
+    #[sea_orm(column_type = "Double")]
+    pub distance_km: f64,
+    #[sea_orm(column_type = "Double")]
+    pub score: f64,
+    pub eta_seconds: Option<i32>,
+    /// Ranked `DispatchCandidate` list, best first.
+    #[sea_orm(column_type = "JsonBinary")]
+    pub candidates: Json,
+    pub created_at: DateTime,
+}
+
+/// One vehicle the allocator considered for an emergency.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DispatchCandidate {
+    pub ambulance_id: Uuid,
+    pub ambulance_ic: i32,
+    pub ambulance_type: AmbulanceTypeEnum,
+    pub capability: String,
+    pub distance_km: f64,
+    /// Absent in records made before ETAs were estimated.
+    #[serde(default)]
+    pub eta_seconds: Option<i32>,
+    /// Weighted cost, lower is better.
+    pub score: f64,
+    pub chosen: bool,
+    /// Sent to another emergency in the same allocation run.
+    pub assigned_elsewhere: bool,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::emergency::Entity",
+        from = "Column::EmergencyId",
+        to = "super::emergency::Column::Id"
+    )]
+    Emergency,
+    #[sea_orm(
+        belongs_to = "super::ambulance::Entity",
+        from = "Column::AmbulanceId",
+        to = "super::ambulance::Column::Id"
+    )]
+    Ambulance,
+}
+
+impl Related<super::emergency::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Emergency.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,46 @@
+//! SeaORM Entity for the dispatch_override table
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// An ambulance assigned to an emergency by a human dispatcher.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "dispatch_override")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub emergency_id: Uuid,
+    pub ambulance_id: Uuid,
+    /// Vehicle that was on the case before the override, released by it.
+    pub previous_ambulance_id: Option<Uuid>,
+    #[sea_orm(column_type = "Text")]
+    pub reason: String,
+    pub forced: bool,
+    /// `sub` of the dispatcher who made the assignment.
+    pub dispatcher: String,
+    pub created_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::emergency::Entity",
+        from = "Column::EmergencyId",
+        to = "super::emergency::Column::Id"
+    )]
+    Emergency,
+    #[sea_orm(
+        belongs_to = "super::ambulance::Entity",
+        from = "Column::AmbulanceId",
+        to = "super::ambulance::Column::Id"
+    )]
+    Ambulance,
+}
+
+impl Related<super::emergency::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Emergency.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
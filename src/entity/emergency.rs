@@ -2,8 +2,12 @@
 use super::sea_orm_active_enums::EmergencyIncidentEnum;
 use super::sea_orm_active_enums::EmergencySeverityEnum;
 use super::sea_orm_active_enums::EmergencyStatusEnum;
+use super::sea_orm_active_enums::EmergencyVerificationEnum;
 use crate::entity::patient::PatientRequestBody;
+use crate::utils::helpers::comma_separated;
+use crate::utils::spatial::geohash_of_decimal;
 use chrono::NaiveDateTime;
+use sea_orm::ActiveValue::Set;
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -31,6 +35,23 @@ pub struct Model {
     pub severity: EmergencySeverityEnum,
     pub incident_type: EmergencyIncidentEnum,
     pub description: Option<String>,
+    pub geohash: Option<String>,
+    /// Drive-time estimate of the lead unit when it was dispatched.
+    pub eta_seconds: Option<i32>,
+    /// Incident this one was detected as a duplicate of, or merged into.
+    pub parent_emergency_id: Option<Uuid>,
+    /// First unit assigned.
+    pub dispatched_at: Option<NaiveDateTime>,
+    /// First unit reported driving to the scene.
+    pub en_route_at: Option<NaiveDateTime>,
+    pub at_scene_at: Option<NaiveDateTime>,
+    /// Patient transport to hospital started.
+    pub transporting_at: Option<NaiveDateTime>,
+    pub at_hospital_at: Option<NaiveDateTime>,
+    /// Public reports stay `Provisional`, out of the allocator, until confirmed.
+    pub verification_status: EmergencyVerificationEnum,
+    /// Number to call back the citizen who reported the emergency.
+    pub callback_phone: Option<String>,
 }
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
@@ -57,7 +78,24 @@ impl Related<super::ambulance::Entity> for Entity {
     }
 }
 
-impl ActiveModelBehavior for ActiveModel {}
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    /// Keeps `geohash` in step with the coordinates whenever they are written.
+    async fn before_save<C>(mut self, _db: &C, _insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if (self.emergency_latitude.is_set() || self.emergency_longitude.is_set())
+            && let (Some(latitude), Some(longitude)) = (
+                self.emergency_latitude.try_as_ref(),
+                self.emergency_longitude.try_as_ref(),
+            )
+        {
+            self.geohash = Set(geohash_of_decimal(*latitude, *longitude));
+        }
+        Ok(self)
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct EmergencyRequestBody {
@@ -68,6 +106,122 @@ pub struct EmergencyRequestBody {
     pub emergency_longitude: Decimal,
     #[serde(rename = "incidentType")]
     pub incident_type: EmergencyIncidentEnum,
+    pub severity: Option<EmergencySeverityEnum>,
     pub description: Option<String>,
     pub patients: Option<Vec<PatientRequestBody>>,
 }
+
+/// Body of the unauthenticated `POST /public/emergency`, sent by citizen apps
+/// and kiosks.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PublicReportPayload {
+    pub latitude: Decimal,
+    pub longitude: Decimal,
+    #[serde(rename = "incidentType")]
+    pub incident_type: EmergencyIncidentEnum,
+    /// Number the dispatcher calls back to confirm the report.
+    #[serde(rename = "callbackPhone")]
+    pub callback_phone: String,
+    pub description: Option<String>,
+}
+
+/// What a public reporter gets back; the case itself stays internal.
+#[derive(Debug, Serialize, Clone)]
+pub struct PublicReportReceipt {
+    pub emergency_ic: String,
+    pub status: EmergencyStatusEnum,
+    pub verification_status: EmergencyVerificationEnum,
+    pub created_at: DateTime,
+}
+
+/// Body of `POST /emergency/{ic}/confirm`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct EmergencyConfirmPayload {
+    /// Severity assessed on the callback; public reports arrive as `Unknown`.
+    pub severity: Option<EmergencySeverityEnum>,
+    pub reason: Option<String>,
+}
+
+/// Body of `POST /emergency/{ic}/reject`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EmergencyRejectPayload {
+    pub reason: String,
+}
+
+/// Order of `GET /emergency`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmergencyListSort {
+    /// Most recently reported first.
+    #[default]
+    Newest,
+    /// Longest waiting first.
+    Oldest,
+    /// Most urgent first, oldest first within the same severity.
+    Severity,
+}
+
+/// Query of `GET /emergency`. Sets are comma-separated, e.g.
+/// `status=Pending,Escalated`; a bounding box needs all four corners.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmergencyListQuery {
+    #[serde(default = "first_page")]
+    pub page: i64,
+    #[serde(default = "default_per_page")]
+    pub per_page: i64,
+    /// Former `ic=...` / `id=...` filter, still accepted.
+    pub filter: Option<String>,
+    pub ic: Option<String>,
+    #[serde(default, deserialize_with = "comma_separated")]
+    pub status: Option<Vec<EmergencyStatusEnum>>,
+    #[serde(default, deserialize_with = "comma_separated")]
+    pub severity: Option<Vec<EmergencySeverityEnum>>,
+    #[serde(default, deserialize_with = "comma_separated")]
+    pub incident_type: Option<Vec<EmergencyIncidentEnum>>,
+    #[serde(default, deserialize_with = "comma_separated")]
+    pub verification_status: Option<Vec<EmergencyVerificationEnum>>,
+    /// `true` for cases with a lead unit, `false` for those still without one.
+    pub assigned: Option<bool>,
+    pub hospital_id: Option<Uuid>,
+    pub created_from: Option<NaiveDateTime>,
+    pub created_to: Option<NaiveDateTime>,
+    pub min_lat: Option<f64>,
+    pub min_lon: Option<f64>,
+    pub max_lat: Option<f64>,
+    pub max_lon: Option<f64>,
+    #[serde(default)]
+    pub sort: EmergencyListSort,
+}
+
+fn first_page() -> i64 {
+    1
+}
+
+fn default_per_page() -> i64 {
+    10
+}
+
+/// Body of `POST /emergency/{ic}/assign`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EmergencyAssignPayload {
+    pub ambulance_id: Uuid,
+    pub reason: String,
+    /// Allows replacing the vehicle already on the case, or taking one that is busy.
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Body of `POST /emergency/{ic}/merge`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EmergencyMergePayload {
+    /// Ics of the duplicates to fold into the emergency.
+    pub duplicates: Vec<String>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EmergencyStatusPayload {
+    pub status: EmergencyStatusEnum,
+    pub notes: Option<String>,
+}
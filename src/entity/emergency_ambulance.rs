@@ -0,0 +1,48 @@
+//! SeaORM Entity for emergency_ambulance join table
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A unit sent to an emergency. `released_at` is set once it leaves the case.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "emergency_ambulance")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub emergency_id: Uuid,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub ambulance_id: Uuid,
+    pub assigned_at: DateTime,
+    pub released_at: Option<DateTime>,
+    /// Drive-time estimate when the unit was sent.
+    pub eta_seconds: Option<i32>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::emergency::Entity",
+        from = "Column::EmergencyId",
+        to = "super::emergency::Column::Id"
+    )]
+    Emergency,
+    #[sea_orm(
+        belongs_to = "super::ambulance::Entity",
+        from = "Column::AmbulanceId",
+        to = "super::ambulance::Column::Id"
+    )]
+    Ambulance,
+}
+
+impl Related<super::emergency::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Emergency.def()
+    }
+}
+
+impl Related<super::ambulance::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Ambulance.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,42 @@
+//! SeaORM Entity for the emergency_merge history table
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A duplicate emergency folded into the incident it was reported as.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "emergency_merge")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub parent_emergency_id: Uuid,
+    pub merged_emergency_id: Uuid,
+    pub patients_moved: i32,
+    pub units_moved: i32,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub reason: Option<String>,
+    /// `sub` of the dispatcher who merged the cases.
+    pub merged_by: String,
+    /// The duplicate as it was just before the merge.
+    #[sea_orm(column_type = "JsonBinary")]
+    pub snapshot: Json,
+    pub merged_at: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::emergency::Entity",
+        from = "Column::ParentEmergencyId",
+        to = "super::emergency::Column::Id"
+    )]
+    Parent,
+    #[sea_orm(
+        belongs_to = "super::emergency::Entity",
+        from = "Column::MergedEmergencyId",
+        to = "super::emergency::Column::Id"
+    )]
+    Merged,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
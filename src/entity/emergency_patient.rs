@@ -10,6 +10,8 @@ pub struct Model {
     pub emergency_id: Uuid,
     #[sea_orm(primary_key, auto_increment = false)]
     pub patient_id: Uuid,
+    /// Unit carrying this patient, once one has taken them on board.
+    pub ambulance_id: Option<Uuid>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,56 @@
+//! SeaORM Entity for the geofence_event table
+
+use super::sea_orm_active_enums::{AmbulanceStatusEnum, GeofenceKindEnum, GeofenceOutcomeEnum};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A status change of an ambulance triggered by it entering or leaving a
+/// geofence, applied or proposed, and what the crew did about it.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "geofence_event")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub ambulance_id: Uuid,
+    /// Emergency whose scene was crossed.
+    pub emergency_id: Option<Uuid>,
+    /// Hospital whose fence was crossed.
+    pub hospital_id: Option<Uuid>,
+    pub fence: GeofenceKindEnum,
+    /// `true` when the unit entered the fence, `false` when it left.
+    pub entered: bool,
+    pub from_status: AmbulanceStatusEnum,
+    pub to_status: AmbulanceStatusEnum,
+    pub outcome: GeofenceOutcomeEnum,
+    /// Fix that crossed the fence.
+    #[sea_orm(column_type = "Decimal(Some((9, 6)))")]
+    pub latitude: Decimal,
+    #[sea_orm(column_type = "Decimal(Some((9, 6)))")]
+    pub longitude: Decimal,
+    /// Distance of the fix from the centre of the fence.
+    #[sea_orm(column_type = "Double")]
+    pub distance_m: f64,
+    pub recorded_at: DateTime,
+    pub created_at: DateTime,
+    pub resolved_at: Option<DateTime>,
+    /// `sub` of the user who accepted, dismissed or reverted the change.
+    pub resolved_by: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::ambulance::Entity",
+        from = "Column::AmbulanceId",
+        to = "super::ambulance::Column::Id"
+    )]
+    Ambulance,
+}
+
+impl Related<super::ambulance::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Ambulance.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,83 @@
+//! SeaORM Entity for the hospital_diversion table
+
+use super::sea_orm_active_enums::DepartmentNameEnum;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A period during which a hospital, or one of its departments, asks
+/// ambulances to take patients elsewhere.
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "hospital_diversion")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub hospital_id: Uuid,
+    /// Department on diversion, `None` for the whole emergency department.
+    pub department: Option<DepartmentNameEnum>,
+    #[sea_orm(column_type = "Text")]
+    pub reason: String,
+    pub starts_at: DateTime,
+    /// Open-ended until lifted when `None`.
+    pub ends_at: Option<DateTime>,
+    /// `sub` of the user who declared the diversion.
+    pub declared_by: String,
+    pub lifted_at: Option<DateTime>,
+    pub lifted_by: Option<String>,
+    pub created_at: DateTime,
+}
+
+impl Model {
+    /// Whether the diversion is in force at `at`.
+    pub fn is_active_at(&self, at: DateTime) -> bool {
+        self.lifted_at.is_none()
+            && self.starts_at <= at
+            && self.ends_at.is_none_or(|ends_at| ends_at > at)
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::hospital::Entity",
+        from = "Column::HospitalId",
+        to = "super::hospital::Column::Id"
+    )]
+    Hospital,
+}
+
+impl Related<super::hospital::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Hospital.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// Body of `POST /hospital/{id}/diversions`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiversionPayload {
+    /// Omit to divert the whole emergency department.
+    pub department: Option<DepartmentNameEnum>,
+    pub reason: String,
+    /// Defaults to now.
+    pub starts_at: Option<DateTime>,
+    pub ends_at: Option<DateTime>,
+}
+
+/// Query of `GET /hospital/diversions`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiversionQuery {
+    pub hospital_id: Option<Uuid>,
+    /// Point in time to check, defaults to now.
+    pub at: Option<DateTime>,
+}
+
+/// A diversion with the name of its hospital, for dashboards.
+#[derive(Debug, Serialize)]
+pub struct HospitalDiversion {
+    #[serde(flatten)]
+    pub diversion: Model,
+    pub hospital_name: String,
+    /// In force at the time asked about.
+    pub active: bool,
+}
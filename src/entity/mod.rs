@@ -4,6 +4,8 @@ pub mod prelude;
 
 pub mod admission;
 pub mod ambulance;
+pub mod ambulance_crew;
+pub mod ambulance_location;
 pub mod amenities;
 pub mod appointment;
 pub mod bed;
@@ -12,10 +14,16 @@ pub mod card;
 pub mod customers;
 pub mod dashboard;
 pub mod department;
+pub mod dispatch_decision;
+pub mod dispatch_override;
 pub mod emergency;
+pub mod emergency_ambulance;
+pub mod emergency_merge;
 pub mod emergency_patient;
+pub mod geofence_event;
 pub mod guard;
 pub mod hospital;
+pub mod hospital_diversion;
 pub mod inventory;
 pub mod medical_record;
 pub mod patient;
@@ -129,7 +129,7 @@ pub enum AmbulanceStatusEnum {
     UnderRepair,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
 #[sea_orm(
     rs_type = "String",
     db_type = "Enum",
@@ -288,6 +288,19 @@ pub enum CardSizeEnum {
     Large,
 }
 
+/// Seat a staff member fills on an ambulance crew.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "crew_role_enum")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CrewRoleEnum {
+    #[sea_orm(string_value = "DRIVER")]
+    Driver,
+    #[sea_orm(string_value = "PARAMEDIC")]
+    Paramedic,
+    #[sea_orm(string_value = "PHYSICIAN")]
+    Physician,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
 #[sea_orm(
     rs_type = "String",
@@ -416,13 +429,15 @@ pub enum EmergencyIncidentEnum {
     BridgeCollapse,
     #[sea_orm(string_value = "DAM_FAILURE")]
     DamFailure,
+    #[sea_orm(string_value = "CHILDBIRTH")]
+    Childbirth,
     #[sea_orm(string_value = "UNKNOWN")]
     Unknown,
     #[sea_orm(string_value = "OTHER")]
     Other,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
 #[sea_orm(
     rs_type = "String",
     db_type = "Enum",
@@ -452,7 +467,7 @@ pub enum EmergencySeverityEnum {
     Deceased,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
 #[sea_orm(
     rs_type = "String",
     db_type = "Enum",
@@ -486,6 +501,61 @@ pub enum EmergencyStatusEnum {
     #[sea_orm(string_value = "TREATED_AT_HOME")]
     TreatedAtHome,
 }
+
+/// Whether an emergency is a confirmed case or an unverified public report.
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(
+    rs_type = "String",
+    db_type = "Enum",
+    enum_name = "emergency_verification_enum"
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum EmergencyVerificationEnum {
+    #[sea_orm(string_value = "VERIFIED")]
+    Verified,
+    #[sea_orm(string_value = "PROVISIONAL")]
+    Provisional,
+    #[sea_orm(string_value = "REJECTED")]
+    Rejected,
+}
+
+/// Kind of area a geofence is drawn around.
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "geofence_kind_enum")]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum GeofenceKindEnum {
+    /// Location of the emergency the unit is working.
+    #[sea_orm(string_value = "SCENE")]
+    Scene,
+    #[sea_orm(string_value = "HOSPITAL")]
+    Hospital,
+}
+
+/// What became of a status change triggered by a geofence.
+#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(
+    rs_type = "String",
+    db_type = "Enum",
+    enum_name = "geofence_outcome_enum"
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum GeofenceOutcomeEnum {
+    /// Applied automatically.
+    #[sea_orm(string_value = "APPLIED")]
+    Applied,
+    /// Waiting for the crew to accept it.
+    #[sea_orm(string_value = "PROPOSED")]
+    Proposed,
+    #[sea_orm(string_value = "ACCEPTED")]
+    Accepted,
+    /// Proposal turned down by the crew or superseded by a newer one.
+    #[sea_orm(string_value = "DISMISSED")]
+    Dismissed,
+    /// Applied change the crew undid.
+    #[sea_orm(string_value = "REVERTED")]
+    Reverted,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "guard_area_enum")]
 pub enum GuardAreaEnum {
@@ -1,23 +1,49 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.11
 
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+/// A shift of a staff member. A shift whose `end_time` is not after its
+/// `start_time` ends on the following day.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
 #[sea_orm(table_name = "staff_schedule")]
 pub struct Model {
     pub created_at: DateTime,
     pub updated_at: DateTime,
     #[sea_orm(primary_key, auto_increment = false)]
     pub id: Uuid,
-    pub staff_id: i32,
-    pub department_id: i32,
-    pub hospital_id: i32,
+    pub staff_schedule_ic: String,
+    pub staff_id: Uuid,
+    pub department_id: Uuid,
+    pub hospital_id: Uuid,
     pub schedule_date: Date,
     pub start_time: Time,
     pub end_time: Time,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::staff::Entity",
+        from = "Column::StaffId",
+        to = "super::staff::Column::Id"
+    )]
+    Staff,
+}
+
+impl Related<super::staff::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Staff.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
+
+/// Body of `POST /staff/{id}/schedule`. The department defaults to the staff member's.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SchedulePayload {
+    pub department_id: Option<Uuid>,
+    pub schedule_date: Date,
+    pub start_time: Time,
+    pub end_time: Time,
+}
@@ -14,6 +14,7 @@ pub enum HttpCodeW {
     NotFound = 404,
     Conflict = 409,
     UnprocessableEntity = 422,
+    TooManyRequests = 429,
 
     // Server Errors
     InternalServerError = 500,
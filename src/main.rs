@@ -1,5 +1,6 @@
+use crate::components::ambulance::{start_compliance_job, start_location_retention_job};
 use crate::components::config::ConfigService;
-use crate::components::emergency::start_scheduler;
+use crate::components::emergency::{start_escalation_job, start_scheduler};
 use crate::open_api::init;
 use crate::security::jwt::JwtAuth;
 use actix_cors::Cors;
@@ -46,12 +47,31 @@ async fn main() -> std::io::Result<()> {
             )
         })
         .init();
+    utils::eta::init_road_graph();
     let scheduler_conn = conn.clone();
     tokio::spawn(async move {
         if let Err(e) = start_scheduler(&scheduler_conn).await {
             error!("Scheduler crashed: {e:?}");
         }
     });
+    let escalation_conn = conn.clone();
+    tokio::spawn(async move {
+        if let Err(e) = start_escalation_job(&escalation_conn).await {
+            error!("Escalation job crashed: {e:?}");
+        }
+    });
+    let retention_conn = conn.clone();
+    tokio::spawn(async move {
+        if let Err(e) = start_location_retention_job(&retention_conn).await {
+            error!("Location retention job crashed: {e:?}");
+        }
+    });
+    let compliance_conn = conn.clone();
+    tokio::spawn(async move {
+        if let Err(e) = start_compliance_job(&compliance_conn).await {
+            error!("Fleet compliance job crashed: {e:?}");
+        }
+    });
     let data_base_conn = conn.clone();
 
     let mut listened = ListenFd::from_env();
@@ -78,6 +98,7 @@ async fn main() -> std::io::Result<()> {
             .service(
                 web::scope("/v1")
                     // Public routes can be added here before the protected scope if needed
+                    .configure(components::emergency::init_public_routes)
                     .service(
                         web::scope("")
                             .wrap(JwtAuth::new(auth_base_url.clone()))
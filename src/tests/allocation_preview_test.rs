@@ -0,0 +1,140 @@
+#[cfg(test)]
+/// Tests for the allocation dry run.
+mod allocation_preview_tests {
+    use crate::components::emergency::schedule::{AllocationStrategy, EmergencyAllocationService};
+    use crate::entity::sea_orm_active_enums::{
+        AmbulanceCarDetailsMakeEnum, AmbulanceCarDetailsModelEnum, AmbulanceStatusEnum,
+        AmbulanceTypeEnum, EmergencyIncidentEnum, EmergencySeverityEnum, EmergencyStatusEnum,
+        EmergencyVerificationEnum,
+    };
+    use crate::entity::{ambulance, emergency};
+    use crate::utils::eta::Travel;
+    use chrono::NaiveDateTime;
+    use rust_decimal::Decimal;
+    use sea_orm::DatabaseConnection;
+    use uuid::Uuid;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn pending(created_at: NaiveDateTime) -> emergency::Model {
+        emergency::Model {
+            created_at,
+            updated_at: created_at,
+            id: Uuid::new_v4(),
+            hospital_id: None,
+            ambulance_id: None,
+            emergency_ic: "1".to_string(),
+            reported_by: None,
+            notes: None,
+            resolved_at: None,
+            modification_attempts: None,
+            emergency_latitude: Decimal::ZERO,
+            emergency_longitude: Decimal::ZERO,
+            status: EmergencyStatusEnum::Pending,
+            severity: EmergencySeverityEnum::Low,
+            incident_type: EmergencyIncidentEnum::Other,
+            description: None,
+            geohash: None,
+            eta_seconds: None,
+            parent_emergency_id: None,
+            dispatched_at: None,
+            en_route_at: None,
+            at_scene_at: None,
+            transporting_at: None,
+            at_hospital_at: None,
+            verification_status: EmergencyVerificationEnum::Verified,
+            callback_phone: None,
+        }
+    }
+
+    fn available(ambulance_ic: i32, r#type: AmbulanceTypeEnum) -> ambulance::Model {
+        let now = at("2026-03-01 09:00:00");
+        ambulance::Model {
+            created_at: now,
+            updated_at: now,
+            id: Uuid::new_v4(),
+            hospital_id: Uuid::nil(),
+            ambulance_ic,
+            vehicle_number: format!("B-{ambulance_ic}"),
+            make: None,
+            year: None,
+            capacity: None,
+            mission: None,
+            passengers: None,
+            driver_name: None,
+            driver_license: None,
+            last_service_date: None,
+            next_service_date: None,
+            mileage: None,
+            last_service_mileage: None,
+            fuel_type: None,
+            registration_number: None,
+            insurance_provider: None,
+            insurance_expiry_date: None,
+            notes: None,
+            car_details_year: 2024,
+            car_details_color: "white".to_string(),
+            car_details_is_ambulance: true,
+            car_details_license_plate: None,
+            car_details_mileage: None,
+            location_latitude: Decimal::ZERO,
+            location_longitude: Decimal::ZERO,
+            location_updated_at: None,
+            r#type,
+            status: AmbulanceStatusEnum::Available,
+            car_details_make: AmbulanceCarDetailsMakeEnum::MercedesBenz,
+            car_details_model: AmbulanceCarDetailsModelEnum::Sprinter,
+            geohash: None,
+        }
+    }
+
+    fn travel(minutes: f64) -> Travel {
+        Travel {
+            distance_km: minutes,
+            eta_seconds: minutes * 60.0,
+            routed: false,
+        }
+    }
+
+    /// With no database behind the service, the preview still plans the
+    /// capable unit and reports the one still missing, leaving both the
+    /// emergency and the vehicles untouched.
+    #[test]
+    fn test_preview_plans_without_dispatching() {
+        let service = EmergencyAllocationService::new(&DatabaseConnection::Disconnected)
+            .with_strategy(AllocationStrategy::Greedy);
+        let now = at("2026-03-01 10:00:00");
+        let emergencies = vec![pending(now)];
+        let basic = available(7, AmbulanceTypeEnum::BasicLifeSupport);
+        let van = available(8, AmbulanceTypeEnum::WheelchairVan);
+        let ambulances = vec![van, basic.clone()];
+
+        let preview = service.preview_plan(
+            &emergencies,
+            &[2],
+            &ambulances,
+            &[vec![travel(2.0), travel(6.0)]],
+            now,
+        );
+
+        assert_eq!(preview.strategy, "GREEDY");
+        assert_eq!(preview.requested_units, 2);
+        assert_eq!(preview.available_ambulances, 2);
+        assert_eq!(preview.assignments.len(), 1);
+        assert_eq!(preview.assignments[0].ambulance_id, basic.id);
+        assert_eq!(preview.assignments[0].capability, "REQUIRED");
+        assert_eq!(preview.assignments[0].eta_seconds, 360);
+        assert_eq!(preview.unmatched.len(), 1);
+        assert_eq!(preview.unmatched[0].missing_units, 1);
+
+        assert_eq!(emergencies[0].status, EmergencyStatusEnum::Pending);
+        assert_eq!(emergencies[0].ambulance_id, None);
+        assert!(
+            ambulances
+                .iter()
+                .all(|a| a.status == AmbulanceStatusEnum::Available)
+        );
+    }
+}
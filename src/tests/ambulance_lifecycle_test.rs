@@ -0,0 +1,176 @@
+#[cfg(test)]
+/// Tests for the ambulance status rules and how they move the emergency along.
+mod ambulance_lifecycle_tests {
+    use crate::components::ambulance::lifecycle::{
+        allowed_transitions, emergency_status_for, emergency_sync_path, ensure_transition,
+        is_on_mission, reverted_emergency_status,
+    };
+    use crate::components::emergency::schedule::{manual_assignment, stand_down};
+    use crate::entity::sea_orm_active_enums::{AmbulanceStatusEnum, EmergencyStatusEnum};
+    use sea_orm::Iterable;
+    use uuid::Uuid;
+
+    /// A mission from dispatch back to base is allowed step by step.
+    #[test]
+    fn test_mission_is_allowed() {
+        use AmbulanceStatusEnum::*;
+        let mission = [
+            Available,
+            Dispatched,
+            EnRouteToScene,
+            AtScene,
+            TransportingPatient,
+            EnRouteToHospital,
+            AtHospital,
+            ReturningToBase,
+            Available,
+        ];
+        for pair in mission.windows(2) {
+            assert!(
+                ensure_transition(&pair[0], &pair[1]).is_ok(),
+                "Expected {:?} -> {:?} to be allowed",
+                pair[0],
+                pair[1]
+            );
+        }
+    }
+
+    /// A vehicle under repair cannot go on a mission, and the error lists
+    /// where it may go instead.
+    #[test]
+    fn test_under_repair_cannot_jump_to_scene() {
+        let error = ensure_transition(
+            &AmbulanceStatusEnum::UnderRepair,
+            &AmbulanceStatusEnum::AtScene,
+        )
+        .unwrap_err();
+        assert!(error.error_message.contains("Allowed: Maintenance"));
+        assert!(
+            ensure_transition(
+                &AmbulanceStatusEnum::UnderRepair,
+                &AmbulanceStatusEnum::UnderRepair
+            )
+            .is_ok()
+        );
+    }
+
+    /// Every status has a way forward and none lists itself.
+    #[test]
+    fn test_every_status_has_exits() {
+        for status in AmbulanceStatusEnum::iter() {
+            let allowed = allowed_transitions(&status);
+            assert!(!allowed.is_empty(), "{status:?} is a dead end");
+            assert!(!allowed.contains(&status), "{status:?} lists itself");
+        }
+    }
+
+    /// The emergency steps through the statuses in between, never backwards
+    /// and never through a hold or escalation.
+    #[test]
+    fn test_emergency_sync_path() {
+        use EmergencyStatusEnum::*;
+        let target = emergency_status_for(&AmbulanceStatusEnum::AtScene).unwrap();
+        assert_eq!(
+            emergency_sync_path(&Pending, &target),
+            Some(vec![InProgress, AtScene])
+        );
+        let target = emergency_status_for(&AmbulanceStatusEnum::EnRouteToHospital).unwrap();
+        assert_eq!(
+            emergency_sync_path(&AtScene, &target),
+            Some(vec![InAmbulance, InTransitToHospital])
+        );
+        assert_eq!(
+            emergency_sync_path(&Escalated, &InProgress),
+            Some(vec![InProgress])
+        );
+        assert_eq!(emergency_sync_path(&AtScene, &InProgress), None);
+        assert_eq!(emergency_sync_path(&AtScene, &AtScene), Some(vec![]));
+        assert_eq!(emergency_status_for(&AmbulanceStatusEnum::Fueling), None);
+    }
+
+    /// Reverting a wrong geofence change steps the case back only while it is
+    /// still where that change put it.
+    #[test]
+    fn test_reverted_emergency_status() {
+        use AmbulanceStatusEnum::*;
+        assert_eq!(
+            reverted_emergency_status(&EmergencyStatusEnum::AtScene, &EnRouteToScene, &AtScene),
+            Some(EmergencyStatusEnum::InProgress)
+        );
+        assert_eq!(
+            reverted_emergency_status(
+                &EmergencyStatusEnum::ArrivedAtHospital,
+                &TransportingPatient,
+                &AtHospital
+            ),
+            Some(EmergencyStatusEnum::InTransitToHospital)
+        );
+        // Moved on by a dispatcher since
+        assert_eq!(
+            reverted_emergency_status(&EmergencyStatusEnum::InAmbulance, &EnRouteToScene, &AtScene),
+            None
+        );
+        // Leaving a hospital never moved the case
+        assert_eq!(
+            reverted_emergency_status(
+                &EmergencyStatusEnum::ArrivedAtHospital,
+                &AtHospital,
+                &ReturningToBase
+            ),
+            None
+        );
+    }
+
+    /// A unit that stands down leaves its case, which falls back to
+    /// `Pending` when nobody else is on the way, and can then be sent to
+    /// another case as its only unit.
+    #[test]
+    fn test_stand_down_then_redispatch() {
+        use AmbulanceStatusEnum::*;
+        let unit = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        assert!(is_on_mission(&EnRouteToScene));
+        assert!(!is_on_mission(&ReturningToBase));
+        assert!(ensure_transition(&EnRouteToScene, &Available).is_ok());
+
+        let alone = stand_down(
+            &[unit],
+            Some(unit),
+            unit,
+            1,
+            &EmergencyStatusEnum::InProgress,
+        );
+        assert!(alone.remaining.is_empty());
+        assert_eq!(alone.lead, None);
+        assert!(alone.requeue);
+        assert!(alone.needs_units);
+
+        let shared = stand_down(
+            &[unit, other],
+            Some(unit),
+            unit,
+            2,
+            &EmergencyStatusEnum::InProgress,
+        );
+        assert_eq!(shared.remaining, vec![other]);
+        assert_eq!(shared.lead, Some(other));
+        assert!(!shared.requeue);
+        assert!(shared.needs_units);
+
+        // A case already reached is not staffed again by the allocator
+        let reached = stand_down(
+            &[unit, other],
+            Some(other),
+            unit,
+            2,
+            &EmergencyStatusEnum::AtScene,
+        );
+        assert_eq!(reached.lead, Some(other));
+        assert!(!reached.needs_units);
+
+        assert!(ensure_transition(&Available, &Dispatched).is_ok());
+        let next = manual_assignment(None, unit, false);
+        assert_eq!(next.lead, Some(unit));
+        assert_eq!(next.released, None);
+    }
+}
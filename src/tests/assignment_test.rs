@@ -0,0 +1,114 @@
+#[cfg(test)]
+/// Tests for the batch assignment used by the `Optimal` allocation strategy.
+mod assignment_tests {
+    use crate::components::emergency::schedule::assignment::{
+        AllocationStrategy, INFEASIBLE, MAX_UNITS_PER_EMERGENCY, pair_cost, required_units, solve,
+        urgency_weight,
+    };
+    use crate::entity::sea_orm_active_enums::EmergencySeverityEnum;
+
+    fn total(cost: &[Vec<f64>], assignment: &[Option<usize>]) -> f64 {
+        assignment
+            .iter()
+            .enumerate()
+            .filter_map(|(i, j)| j.map(|j| cost[i][j]))
+            .sum()
+    }
+
+    /// Exhaustive search over all column permutations, for comparison on small inputs.
+    fn brute_force(cost: &[Vec<f64>], row: usize, used: &mut Vec<bool>) -> f64 {
+        if row == cost.len() {
+            return 0.0;
+        }
+        let mut best = f64::INFINITY;
+        for j in 0..used.len() {
+            if !used[j] {
+                used[j] = true;
+                best = best.min(cost[row][j] + brute_force(cost, row + 1, used));
+                used[j] = false;
+            }
+        }
+        best
+    }
+
+    /// The greedy choice for the first row is not globally optimal here.
+    #[test]
+    fn test_beats_greedy_on_classic_example() {
+        let cost = vec![vec![1.0, 2.0], vec![2.0, 100.0]];
+        let assignment = solve(&cost);
+        assert_eq!(assignment, vec![Some(1), Some(0)]);
+        assert_eq!(total(&cost, &assignment), 4.0);
+    }
+
+    /// Matches the exhaustive optimum on a handful of square and wide matrices.
+    #[test]
+    fn test_matches_brute_force() {
+        let matrices = vec![
+            vec![
+                vec![4.0, 1.0, 3.0],
+                vec![2.0, 0.0, 5.0],
+                vec![3.0, 2.0, 2.0],
+            ],
+            vec![
+                vec![7.0, 3.0, 9.0, 1.0],
+                vec![2.0, 8.0, 4.0, 6.0],
+                vec![5.0, 5.0, 1.0, 9.0],
+            ],
+            vec![
+                vec![-10.0, -3.5, -8.0],
+                vec![-9.0, -9.0, -1.0],
+                vec![-2.0, -7.0, -6.5],
+            ],
+        ];
+        for cost in matrices {
+            let assignment = solve(&cost);
+            let expected = brute_force(&cost, 0, &mut vec![false; cost[0].len()]);
+            assert!((total(&cost, &assignment) - expected).abs() < 1e-9);
+        }
+    }
+
+    /// With more emergencies than ambulances only the cheapest rows are served,
+    /// and infeasible pairs are never returned.
+    #[test]
+    fn test_tall_matrix_and_infeasible_pairs() {
+        let cost = vec![
+            vec![5.0, INFEASIBLE],
+            vec![1.0, 9.0],
+            vec![INFEASIBLE, INFEASIBLE],
+        ];
+        let assignment = solve(&cost);
+        assert_eq!(assignment, vec![Some(0), Some(1), None]);
+        assert!(solve(&[]).is_empty());
+    }
+
+    /// A critical case is served before a minor one even when its vehicle is far away,
+    /// and waiting time raises urgency.
+    #[test]
+    fn test_weights_prefer_urgent_cases() {
+        let critical = urgency_weight(&EmergencySeverityEnum::Critical, 0.0);
+        let minor = urgency_weight(&EmergencySeverityEnum::Low, 0.0);
+        assert!(pair_cost(critical, 80.0, false) < pair_cost(minor, 1.0, false));
+        assert!(urgency_weight(&EmergencySeverityEnum::Low, 90.0) > minor);
+        assert!(pair_cost(minor, 500.0, false) < pair_cost(minor, 1.0, true));
+    }
+
+    /// `ALLOCATION_STRATEGY` values are case-insensitive and unknown ones are rejected.
+    #[test]
+    fn test_strategy_parsing() {
+        assert_eq!("greedy".parse(), Ok(AllocationStrategy::Greedy));
+        assert_eq!(" Optimal ".parse(), Ok(AllocationStrategy::Optimal));
+        assert!("nearest".parse::<AllocationStrategy>().is_err());
+    }
+
+    /// Serious cases need a unit per patient, minor ones share, within the cap.
+    #[test]
+    fn test_required_units() {
+        assert_eq!(required_units(0, &EmergencySeverityEnum::Low), 1);
+        assert_eq!(required_units(5, &EmergencySeverityEnum::Low), 3);
+        assert_eq!(required_units(5, &EmergencySeverityEnum::Critical), 5);
+        assert_eq!(
+            required_units(40, &EmergencySeverityEnum::Extreme),
+            MAX_UNITS_PER_EMERGENCY
+        );
+    }
+}
@@ -0,0 +1,102 @@
+#[cfg(test)]
+/// Tests for the default capability matrix used by the allocator to match
+/// ambulance types to incidents.
+mod capability_matrix_tests {
+    use crate::components::emergency::schedule::capability::{CapabilityMatch, CapabilityMatrix};
+    use crate::entity::sea_orm_active_enums::{
+        AmbulanceTypeEnum, EmergencyIncidentEnum, EmergencySeverityEnum,
+    };
+
+    /// A wheelchair van must never be matched to a cardiac arrest, not even as a fallback.
+    #[test]
+    fn test_cardiac_arrest_needs_advanced_life_support() {
+        let matrix = CapabilityMatrix::default();
+        let required = matrix.required_types(
+            &EmergencyIncidentEnum::HeartAttack,
+            &EmergencySeverityEnum::High,
+        );
+        assert!(required.contains(&AmbulanceTypeEnum::AdvancedLifeSupport));
+        assert!(!required.contains(&AmbulanceTypeEnum::BasicLifeSupport));
+        assert_eq!(
+            matrix.classify(
+                &EmergencyIncidentEnum::HeartAttack,
+                &EmergencySeverityEnum::High,
+                &AmbulanceTypeEnum::WheelchairVan,
+            ),
+            None
+        );
+    }
+
+    /// Critical cases of any incident type require ALS or MICU.
+    #[test]
+    fn test_critical_severity_requires_als_or_micu() {
+        let matrix = CapabilityMatrix::default();
+        let required = matrix.required_types(
+            &EmergencyIncidentEnum::FallInjury,
+            &EmergencySeverityEnum::Critical,
+        );
+        assert!(required.contains(&AmbulanceTypeEnum::AdvancedLifeSupport));
+        assert!(required.contains(&AmbulanceTypeEnum::MobileIntensiveCareUnit));
+    }
+
+    /// Specialised incidents map to their specialised vehicles.
+    #[test]
+    fn test_specialised_incidents() {
+        let matrix = CapabilityMatrix::default();
+        assert_eq!(
+            matrix.required_types(
+                &EmergencyIncidentEnum::Childbirth,
+                &EmergencySeverityEnum::Medium
+            )[0],
+            AmbulanceTypeEnum::NeonatalAmbulance
+        );
+        assert_eq!(
+            matrix.required_types(
+                &EmergencyIncidentEnum::ChemicalSpill,
+                &EmergencySeverityEnum::Critical
+            ),
+            &[AmbulanceTypeEnum::HazmatAmbulance]
+        );
+    }
+
+    /// General purpose units are offered as a fallback when the required type is busy.
+    #[test]
+    fn test_fallback_classification() {
+        let matrix = CapabilityMatrix::default();
+        assert_eq!(
+            matrix.classify(
+                &EmergencyIncidentEnum::ChemicalSpill,
+                &EmergencySeverityEnum::High,
+                &AmbulanceTypeEnum::AdvancedLifeSupport,
+            ),
+            Some(CapabilityMatch::Fallback)
+        );
+        assert_eq!(
+            matrix.classify(
+                &EmergencyIncidentEnum::ChemicalSpill,
+                &EmergencySeverityEnum::High,
+                &AmbulanceTypeEnum::HazmatAmbulance,
+            ),
+            Some(CapabilityMatch::Required)
+        );
+    }
+
+    /// A matrix can be supplied as JSON, using the API's enum spelling.
+    #[test]
+    fn test_matrix_from_json() {
+        let matrix: CapabilityMatrix = serde_json::from_str(
+            r#"{
+                "rules": [
+                    { "incidents": ["STROKE"], "types": ["MOBILE_INTENSIVE_CARE_UNIT"] },
+                    { "types": ["BASIC_LIFE_SUPPORT"] }
+                ]
+            }"#,
+        )
+        .expect("matrix should deserialize");
+        assert_eq!(
+            matrix.required_types(&EmergencyIncidentEnum::Stroke, &EmergencySeverityEnum::Low),
+            &[AmbulanceTypeEnum::MobileIntensiveCareUnit]
+        );
+        assert!(matrix.fallback.is_empty());
+    }
+}
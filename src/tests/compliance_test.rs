@@ -0,0 +1,114 @@
+#[cfg(test)]
+/// Tests for the fleet compliance rules.
+mod compliance_tests {
+    use crate::components::ambulance::compliance::{
+        ComplianceCheck, CompliancePolicy, ComplianceState, ServiceRecord,
+        should_move_to_maintenance, worst_state,
+    };
+    use crate::entity::ambulance;
+    use crate::entity::sea_orm_active_enums::AmbulanceStatusEnum;
+    use chrono::{Duration, NaiveDate, NaiveDateTime};
+    use sea_orm::{DbBackend, EntityTrait, QueryFilter, QueryTrait};
+
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, 18)
+            .unwrap()
+            .and_hms_opt(10, 0, 0)
+            .unwrap()
+    }
+
+    fn in_order() -> ServiceRecord {
+        ServiceRecord {
+            next_service_date: Some(now() + Duration::days(90)),
+            mileage: Some(52_000),
+            last_service_mileage: Some(50_000),
+            insurance_expiry_date: Some(now() + Duration::days(200)),
+        }
+    }
+
+    /// A vehicle with its service and insurance well ahead has no issues.
+    #[test]
+    fn test_in_order_is_compliant() {
+        let issues = CompliancePolicy::default().assess(&in_order(), now());
+        assert!(issues.is_empty(), "{issues:?}");
+        assert_eq!(worst_state(&issues), ComplianceState::Compliant);
+    }
+
+    /// Service falls due by date or by distance, whichever comes first.
+    #[test]
+    fn test_service_due_by_date_or_mileage() {
+        let policy = CompliancePolicy::default();
+        let mut by_date = in_order();
+        by_date.next_service_date = Some(now() - Duration::days(1));
+        let issues = policy.assess(&by_date, now());
+        assert_eq!(issues[0].check, ComplianceCheck::ServiceDate);
+        assert_eq!(worst_state(&issues), ComplianceState::NonCompliant);
+
+        let mut soon = in_order();
+        soon.mileage = Some(64_500);
+        let issues = policy.assess(&soon, now());
+        assert_eq!(issues[0].check, ComplianceCheck::ServiceMileage);
+        assert_eq!(worst_state(&issues), ComplianceState::DueSoon);
+
+        soon.mileage = Some(65_000);
+        assert_eq!(
+            worst_state(&policy.assess(&soon, now())),
+            ComplianceState::NonCompliant
+        );
+    }
+
+    /// Insurance is flagged ahead of expiry and blocks the vehicle once
+    /// expired; missing records are flagged without blocking.
+    #[test]
+    fn test_insurance_and_missing_records() {
+        let policy = CompliancePolicy::default();
+        let mut record = in_order();
+        record.insurance_expiry_date = Some(now() + Duration::days(10));
+        assert_eq!(
+            worst_state(&policy.assess(&record, now())),
+            ComplianceState::DueSoon
+        );
+        record.insurance_expiry_date = Some(now() - Duration::hours(1));
+        assert_eq!(
+            worst_state(&policy.assess(&record, now())),
+            ComplianceState::NonCompliant
+        );
+        let issues = policy.assess(&ServiceRecord::default(), now());
+        assert_eq!(issues.len(), 2, "{issues:?}");
+        assert_eq!(worst_state(&issues), ComplianceState::DueSoon);
+    }
+
+    /// The allocator filter checks the same three rules in SQL.
+    #[test]
+    fn test_condition_covers_every_rule() {
+        let sql = ambulance::Entity::find()
+            .filter(CompliancePolicy::default().condition(now()))
+            .build(DbBackend::Postgres)
+            .to_string();
+        assert!(sql.contains(r#""next_service_date" >="#), "{sql}");
+        assert!(sql.contains(r#""insurance_expiry_date" >="#), "{sql}");
+        assert!(
+            sql.contains(r#""ambulance"."mileage" - "ambulance"."last_service_mileage" < 15000"#),
+            "{sql}"
+        );
+    }
+
+    /// The job only pulls in vehicles on the road, never ones under repair,
+    /// out of service or already in maintenance.
+    #[test]
+    fn test_enforce_skips_off_road_vehicles() {
+        assert!(should_move_to_maintenance(&AmbulanceStatusEnum::Available));
+        assert!(should_move_to_maintenance(
+            &AmbulanceStatusEnum::ReturningToBase
+        ));
+        assert!(!should_move_to_maintenance(&AmbulanceStatusEnum::AtScene));
+        for status in [
+            AmbulanceStatusEnum::Maintenance,
+            AmbulanceStatusEnum::UnderRepair,
+            AmbulanceStatusEnum::OutOfService,
+            AmbulanceStatusEnum::Unavailable,
+        ] {
+            assert!(!should_move_to_maintenance(&status), "{status:?}");
+        }
+    }
+}
@@ -0,0 +1,67 @@
+#[cfg(test)]
+/// Tests for the coverage grid and the repositioning planner.
+mod coverage_tests {
+    use crate::components::ambulance::coverage::{CoverageGrid, PlannedMove, suggest_moves};
+    use crate::utils::spatial::BoundingBox;
+
+    /// Cell centres map back to their own cell and points outside the area to none.
+    #[test]
+    fn test_grid_cells() {
+        let grid = CoverageGrid::new(
+            BoundingBox {
+                min_latitude: 44.40,
+                min_longitude: 26.00,
+                max_latitude: 44.50,
+                max_longitude: 26.20,
+            },
+            2.0,
+        );
+        // 11.1 km by about 15.9 km
+        assert_eq!((grid.rows, grid.cols), (6, 8));
+        for cell in 0..grid.len() {
+            let (latitude, longitude) = grid.centre(cell);
+            assert_eq!(grid.cell_of(latitude, longitude), Some(cell));
+        }
+        assert_eq!(grid.cell_of(44.50, 26.20), Some(grid.len() - 1));
+        assert_eq!(grid.cell_of(44.39, 26.10), None);
+    }
+
+    /// A unit moves to cover a busy gap only while another unit keeps its old
+    /// area within target, and a move that uncovers more demand than it
+    /// covers is never suggested.
+    #[test]
+    fn test_suggest_moves_closes_gaps() {
+        // Three cells west to east; both units sit in the west, the standby is east
+        let from_west = [2.0, 6.0, 20.0];
+        let from_east = [20.0, 6.0, 2.0];
+        let moves = suggest_moves(
+            &[1.0, 1.0, 5.0],
+            2,
+            1,
+            |cell, _| from_west[cell],
+            |cell, _, _| from_east[cell],
+            8.0,
+            3,
+        );
+        assert_eq!(
+            moves,
+            [PlannedMove {
+                unit: 0,
+                standby: 0,
+                uncovered_before: 5.0,
+                uncovered_after: 0.0,
+            }]
+        );
+
+        let lone_unit = suggest_moves(
+            &[5.0, 0.1, 1.0],
+            1,
+            1,
+            |cell, _| from_west[cell],
+            |cell, _, _| from_east[cell],
+            8.0,
+            3,
+        );
+        assert!(lone_unit.is_empty());
+    }
+}
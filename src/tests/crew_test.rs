@@ -0,0 +1,94 @@
+#[cfg(test)]
+/// Tests for the ambulance crew rules.
+mod crew_tests {
+    use crate::components::ambulance::crew::{
+        CrewRequirement, minimum_crew, missing_roles, qualifies, shift_window,
+    };
+    use crate::entity::sea_orm_active_enums::{AmbulanceTypeEnum, CrewRoleEnum, StaffRoleEnum};
+    use chrono::{NaiveDate, NaiveTime};
+
+    fn time(hour: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    /// Only doctors serve as physician; receptionists never crew a vehicle.
+    #[test]
+    fn test_qualifies() {
+        assert!(qualifies(&StaffRoleEnum::Doctor, &CrewRoleEnum::Physician));
+        assert!(!qualifies(&StaffRoleEnum::Nurse, &CrewRoleEnum::Physician));
+        assert!(qualifies(&StaffRoleEnum::Nurse, &CrewRoleEnum::Paramedic));
+        assert!(qualifies(&StaffRoleEnum::Technician, &CrewRoleEnum::Driver));
+        assert!(!qualifies(
+            &StaffRoleEnum::Receptionist,
+            &CrewRoleEnum::Driver
+        ));
+        assert!(!qualifies(
+            &StaffRoleEnum::Cleaner,
+            &CrewRoleEnum::Paramedic
+        ));
+    }
+
+    /// Intensive care units need a physician, rapid response vehicles a paramedic only.
+    #[test]
+    fn test_minimum_crew_per_type() {
+        assert_eq!(
+            minimum_crew(&AmbulanceTypeEnum::MobileIntensiveCareUnit),
+            CrewRequirement {
+                drivers: 1,
+                paramedics: 1,
+                physicians: 1
+            }
+        );
+        assert_eq!(
+            minimum_crew(&AmbulanceTypeEnum::RapidResponseVehicle),
+            CrewRequirement {
+                drivers: 0,
+                paramedics: 1,
+                physicians: 0
+            }
+        );
+    }
+
+    /// Empty seats are listed, and a spare physician covers a paramedic seat.
+    #[test]
+    fn test_missing_roles() {
+        let basic = AmbulanceTypeEnum::BasicLifeSupport;
+        assert_eq!(
+            missing_roles(&basic, &[]),
+            vec![CrewRoleEnum::Driver, CrewRoleEnum::Paramedic]
+        );
+        assert!(missing_roles(&basic, &[CrewRoleEnum::Driver, CrewRoleEnum::Physician]).is_empty());
+
+        let micu = AmbulanceTypeEnum::MobileIntensiveCareUnit;
+        assert_eq!(
+            missing_roles(&micu, &[CrewRoleEnum::Driver, CrewRoleEnum::Physician]),
+            vec![CrewRoleEnum::Paramedic]
+        );
+        assert!(
+            missing_roles(
+                &micu,
+                &[
+                    CrewRoleEnum::Driver,
+                    CrewRoleEnum::Physician,
+                    CrewRoleEnum::Physician
+                ]
+            )
+            .is_empty()
+        );
+    }
+
+    /// A shift ending before it starts runs into the next day.
+    #[test]
+    fn test_overnight_shift_window() {
+        let date = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+        let (start, end) = shift_window(date, time(8), time(20));
+        assert_eq!(
+            (start, end),
+            (date.and_time(time(8)), date.and_time(time(20)))
+        );
+
+        let (start, end) = shift_window(date, time(20), time(8));
+        assert_eq!(start, date.and_time(time(20)));
+        assert_eq!(end, date.succ_opt().unwrap().and_time(time(8)));
+    }
+}
@@ -0,0 +1,164 @@
+#[cfg(test)]
+/// Tests for the ranking of destination hospitals for transporting ambulances.
+mod destination_tests {
+    use crate::components::hospital::destination::{
+        DestinationNeeds, assess, parse_trauma_level, rank, required_department,
+    };
+    use crate::entity::sea_orm_active_enums::{
+        DepartmentNameEnum, EmergencyIncidentEnum, EmergencySeverityEnum,
+    };
+    use crate::entity::{hospital, hospital_diversion};
+    use crate::utils::eta::Travel;
+    use chrono::{Duration, NaiveDateTime};
+    use sea_orm::prelude::Uuid;
+
+    fn hospital(name: &str, capacity: Option<i32>, trauma_level: Option<&str>) -> hospital::Model {
+        let now =
+            NaiveDateTime::parse_from_str("2026-10-18 09:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        hospital::Model {
+            created_at: now,
+            updated_at: now,
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            address: String::new(),
+            phone: None,
+            website: None,
+            description: None,
+            capacity,
+            established: None,
+            ceo: None,
+            trauma_level: trauma_level.map(str::to_string),
+            revenue: None,
+            non_profit: None,
+            license_number: None,
+            accreditation: None,
+            patient_satisfaction_rating: None,
+            average_stay_length: None,
+            annual_budget: None,
+            owner: None,
+            latitude: None,
+            longitude: None,
+            hospital_ic: name.to_string(),
+        }
+    }
+
+    fn diversion(
+        department: Option<DepartmentNameEnum>,
+        reason: &str,
+        starts_at: NaiveDateTime,
+        ends_at: Option<NaiveDateTime>,
+    ) -> hospital_diversion::Model {
+        hospital_diversion::Model {
+            id: Uuid::new_v4(),
+            hospital_id: Uuid::nil(),
+            department,
+            reason: reason.to_string(),
+            starts_at,
+            ends_at,
+            declared_by: "charge-nurse".to_string(),
+            lifted_at: None,
+            lifted_by: None,
+            created_at: starts_at,
+        }
+    }
+
+    fn drive(minutes: f64) -> Travel {
+        Travel {
+            distance_km: minutes,
+            eta_seconds: minutes * 60.0,
+            routed: false,
+        }
+    }
+
+    /// Trauma levels are read from the free-text forms found in the data.
+    #[test]
+    fn test_parse_trauma_level() {
+        assert_eq!(parse_trauma_level("Level II"), Some(2));
+        assert_eq!(parse_trauma_level(" level iii "), Some(3));
+        assert_eq!(parse_trauma_level("1"), Some(1));
+        assert_eq!(parse_trauma_level("none"), None);
+        assert_eq!(
+            required_department(&EmergencyIncidentEnum::HeartAttack),
+            Some(DepartmentNameEnum::Cardiology)
+        );
+        assert_eq!(required_department(&EmergencyIncidentEnum::Other), None);
+    }
+
+    /// A farther hospital that can treat the case beats a closer one that
+    /// lacks the department, the trauma level or free capacity.
+    #[test]
+    fn test_rank_prefers_suitable_hospital() {
+        let needs = DestinationNeeds::of(
+            &EmergencyIncidentEnum::CarAccident,
+            &EmergencySeverityEnum::Critical,
+        );
+        assert_eq!(needs.department, Some(DepartmentNameEnum::Surgery));
+        assert_eq!(needs.trauma_level, Some(1));
+
+        let clinic = hospital("Clinic", Some(20), None);
+        let full = hospital("Full", Some(10), Some("Level I"));
+        let centre = hospital("Centre", Some(50), Some("Level I"));
+        let surgery = [DepartmentNameEnum::Surgery];
+
+        let ranked = rank(vec![
+            assess(&needs, &clinic, &drive(5.0), 0, &[], &[]),
+            assess(&needs, &full, &drive(8.0), 10, &surgery, &[]),
+            assess(&needs, &centre, &drive(20.0), 3, &surgery, &[]),
+        ]);
+        let names: Vec<&str> = ranked.iter().map(|c| c.hospital_name.as_str()).collect();
+        assert_eq!(names, ["Centre", "Full", "Clinic"]);
+        assert!(ranked[0].warnings.is_empty());
+        assert_eq!(ranked[0].available_capacity, Some(47));
+        assert_eq!(ranked[1].available_capacity, Some(0));
+        assert!(!ranked[2].has_department);
+    }
+
+    /// A diversion counts only inside its window, a diverted department
+    /// weighs like a missing one, and a diverted hospital is flagged.
+    #[test]
+    fn test_diversions_affect_ranking() {
+        let start =
+            NaiveDateTime::parse_from_str("2026-10-18 09:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let ct_down = diversion(None, "CT down", start, Some(start + Duration::hours(2)));
+        assert!(!ct_down.is_active_at(start - Duration::minutes(1)));
+        assert!(ct_down.is_active_at(start));
+        assert!(!ct_down.is_active_at(start + Duration::hours(2)));
+        let mut lifted = ct_down.clone();
+        lifted.lifted_at = Some(start + Duration::minutes(30));
+        assert!(!lifted.is_active_at(start + Duration::hours(1)));
+
+        let needs = DestinationNeeds::of(
+            &EmergencyIncidentEnum::HeartAttack,
+            &EmergencySeverityEnum::High,
+        );
+        let cardiology = [DepartmentNameEnum::Cardiology];
+        let near = hospital("Near", Some(30), None);
+        let far = hospital("Far", Some(30), None);
+        let cardiology_full = diversion(
+            Some(DepartmentNameEnum::Cardiology),
+            "cardiology full",
+            start,
+            None,
+        );
+        let oncology_closed = diversion(Some(DepartmentNameEnum::Oncology), "closed", start, None);
+
+        let ranked = rank(vec![
+            assess(
+                &needs,
+                &near,
+                &drive(5.0),
+                0,
+                &cardiology,
+                &[cardiology_full, oncology_closed],
+            ),
+            assess(&needs, &far, &drive(15.0), 0, &cardiology, &[]),
+        ]);
+        assert_eq!(ranked[0].hospital_name, "Far");
+        assert_eq!(ranked[1].warnings, ["on diversion: cardiology full"]);
+        assert!(ranked[1].has_department);
+        assert!(!ranked[1].diverted);
+
+        let diverted = assess(&needs, &near, &drive(5.0), 0, &cardiology, &[ct_down]);
+        assert!(diverted.diverted);
+    }
+}
@@ -0,0 +1,112 @@
+#[cfg(test)]
+/// Tests for the audit row kept for each automatic dispatch.
+mod dispatch_decision_tests {
+    use crate::components::emergency::schedule::AllocationStrategy;
+    use crate::components::emergency::schedule::emergency_allocation::decision_record;
+    use crate::entity::dispatch_decision::DispatchCandidate;
+    use crate::entity::sea_orm_active_enums::AmbulanceTypeEnum;
+    use chrono::NaiveDateTime;
+    use sea_orm::ActiveValue::Set;
+    use uuid::Uuid;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn candidate(score: f64, chosen: bool, assigned_elsewhere: bool) -> DispatchCandidate {
+        DispatchCandidate {
+            ambulance_id: Uuid::new_v4(),
+            ambulance_ic: score as i32,
+            ambulance_type: AmbulanceTypeEnum::BasicLifeSupport,
+            capability: "REQUIRED".to_string(),
+            distance_km: score / 10.0,
+            eta_seconds: Some((score * 6.0) as i32),
+            score,
+            chosen,
+            assigned_elsewhere,
+        }
+    }
+
+    /// The row carries the chosen unit's figures and lists every candidate
+    /// best first, the one sent elsewhere and the rejected ones included.
+    #[test]
+    fn test_records_chosen_and_rejected_candidates() {
+        let chosen = candidate(40.0, true, false);
+        let elsewhere = candidate(25.0, false, true);
+        let rejected = candidate(90.0, false, false);
+        let now = at("2026-03-01 10:00:00");
+        let emergency_id = Uuid::new_v4();
+        let record = decision_record(
+            AllocationStrategy::Optimal,
+            emergency_id,
+            &chosen,
+            vec![rejected.clone(), chosen.clone(), elsewhere.clone()],
+            now,
+        );
+
+        assert_eq!(record.emergency_id, Set(emergency_id));
+        assert_eq!(record.ambulance_id, Set(chosen.ambulance_id));
+        assert_eq!(record.strategy, Set("OPTIMAL".to_string()));
+        assert_eq!(record.capability, Set("REQUIRED".to_string()));
+        assert_eq!(record.score, Set(40.0));
+        assert_eq!(record.eta_seconds, Set(Some(240)));
+        assert_eq!(record.created_at, Set(now));
+
+        let Set(candidates) = record.candidates else {
+            panic!("candidates not set");
+        };
+        let candidates: Vec<DispatchCandidate> = serde_json::from_value(candidates).unwrap();
+        assert_eq!(candidates, vec![elsewhere, chosen, rejected]);
+    }
+
+    /// Only the ten best candidates are kept.
+    #[test]
+    fn test_keeps_the_best_candidates() {
+        let chosen = candidate(1.0, true, false);
+        let mut candidates: Vec<DispatchCandidate> = (2..=15)
+            .map(|score| candidate(f64::from(score), false, false))
+            .collect();
+        candidates.push(chosen.clone());
+        let record = decision_record(
+            AllocationStrategy::Greedy,
+            Uuid::new_v4(),
+            &chosen,
+            candidates,
+            at("2026-03-01 10:00:00"),
+        );
+
+        let Set(candidates) = record.candidates else {
+            panic!("candidates not set");
+        };
+        let candidates: Vec<DispatchCandidate> = serde_json::from_value(candidates).unwrap();
+        assert_eq!(candidates.len(), 10);
+        assert_eq!(candidates[0], chosen);
+        assert_eq!(candidates[9].score, 10.0);
+    }
+
+    /// A chosen vehicle ranked below the cut, as the optimiser may pick one,
+    /// is still listed, in place of the weakest of the others.
+    #[test]
+    fn test_keeps_the_chosen_candidate() {
+        let chosen = candidate(12.0, true, false);
+        let mut candidates: Vec<DispatchCandidate> = (1..=11)
+            .map(|score| candidate(f64::from(score), false, false))
+            .collect();
+        candidates.push(chosen.clone());
+        let record = decision_record(
+            AllocationStrategy::Optimal,
+            Uuid::new_v4(),
+            &chosen,
+            candidates,
+            at("2026-03-01 10:00:00"),
+        );
+
+        let Set(candidates) = record.candidates else {
+            panic!("candidates not set");
+        };
+        let candidates: Vec<DispatchCandidate> = serde_json::from_value(candidates).unwrap();
+        assert_eq!(candidates.len(), 10);
+        assert_eq!(candidates[8].score, 9.0);
+        assert_eq!(candidates[9], chosen);
+    }
+}
@@ -0,0 +1,55 @@
+#[cfg(test)]
+/// Tests for matching incident types of reports that may describe the same event.
+mod duplicates_tests {
+    use crate::components::emergency::duplicates::{are_related, parent_after_merge};
+    use crate::entity::sea_orm_active_enums::EmergencyIncidentEnum::*;
+    use uuid::Uuid;
+
+    /// Different kinds of road accident are reported for the same crash.
+    #[test]
+    fn test_traffic_incidents_are_related() {
+        assert!(are_related(&CarAccident, &CarAccident));
+        assert!(are_related(&CarAccident, &PedestrianAccident));
+        assert!(are_related(&MotorcycleAccident, &TrainAccident));
+    }
+
+    /// Incidents of unrelated families are never linked.
+    #[test]
+    fn test_unrelated_incidents_are_not_linked() {
+        assert!(!are_related(&CarAccident, &HouseFire));
+        assert!(!are_related(&Shooting, &Flood));
+        assert!(!are_related(&Childbirth, &Fracture));
+    }
+
+    /// A caller unsure of what happened may be reporting any nearby incident.
+    #[test]
+    fn test_unknown_matches_any_incident() {
+        assert!(are_related(&Unknown, &Explosion));
+        assert!(are_related(&GasLeak, &Unknown));
+    }
+
+    /// Reports linked to a merged duplicate move to the surviving incident;
+    /// other links are kept and the survivor never becomes its own parent.
+    #[test]
+    fn test_parent_after_merge() {
+        let (report, merged, survivor, other) = (
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+        );
+        assert_eq!(
+            parent_after_merge(report, Some(merged), merged, survivor),
+            Some(survivor)
+        );
+        assert_eq!(
+            parent_after_merge(report, Some(other), merged, survivor),
+            Some(other)
+        );
+        assert_eq!(parent_after_merge(report, None, merged, survivor), None);
+        assert_eq!(
+            parent_after_merge(survivor, Some(merged), merged, survivor),
+            None
+        );
+    }
+}
@@ -0,0 +1,89 @@
+#[cfg(test)]
+/// Tests for the emergency lifecycle rules used by `PATCH /emergency/{ic}/status`.
+mod emergency_lifecycle_tests {
+    use crate::components::emergency::lifecycle::{
+        Milestone, allowed_transitions, apply_milestone, ensure_transition, is_terminal,
+    };
+    use crate::entity::emergency;
+    use crate::entity::sea_orm_active_enums::EmergencyStatusEnum;
+    use chrono::NaiveDateTime;
+    use sea_orm::{ActiveValue, Iterable};
+
+    /// Walks the happy path from a fresh report to a resolved case.
+    #[test]
+    fn test_happy_path_is_allowed() {
+        let path = [
+            EmergencyStatusEnum::Pending,
+            EmergencyStatusEnum::InProgress,
+            EmergencyStatusEnum::AtScene,
+            EmergencyStatusEnum::InAmbulance,
+            EmergencyStatusEnum::InTransitToHospital,
+            EmergencyStatusEnum::ArrivedAtHospital,
+            EmergencyStatusEnum::Resolved,
+        ];
+        for pair in path.windows(2) {
+            assert!(
+                ensure_transition(&pair[0], &pair[1]).is_ok(),
+                "Expected {:?} -> {:?} to be allowed",
+                pair[0],
+                pair[1]
+            );
+        }
+    }
+
+    /// Skipping steps, such as resolving a case that was never attended, is rejected.
+    #[test]
+    fn test_illegal_jumps_are_rejected() {
+        let err = ensure_transition(
+            &EmergencyStatusEnum::Pending,
+            &EmergencyStatusEnum::Resolved,
+        )
+        .unwrap_err();
+        assert_eq!(err.error_status_code as u16, 422);
+        assert!(err.error_message.contains("Pending"));
+
+        assert!(
+            ensure_transition(
+                &EmergencyStatusEnum::InProgress,
+                &EmergencyStatusEnum::ArrivedAtHospital
+            )
+            .is_err()
+        );
+    }
+
+    /// Terminal statuses have no way out and every other status has at least one.
+    #[test]
+    fn test_terminal_statuses_are_closed() {
+        for status in EmergencyStatusEnum::iter() {
+            if is_terminal(&status) {
+                assert!(allowed_transitions(&status).is_empty());
+            } else {
+                assert!(
+                    !allowed_transitions(&status).is_empty(),
+                    "{status:?} should have at least one outgoing transition"
+                );
+            }
+        }
+    }
+
+    /// A milestone keeps the time it was first reached.
+    #[test]
+    fn test_milestone_keeps_first_time() {
+        let first =
+            NaiveDateTime::parse_from_str("2026-10-18 10:05:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let later = first + chrono::Duration::minutes(3);
+        assert_eq!(
+            Milestone::of_status(&EmergencyStatusEnum::AtScene),
+            Some(Milestone::AtScene)
+        );
+        assert_eq!(Milestone::of_status(&EmergencyStatusEnum::OnHold), None);
+
+        let mut active_model = emergency::ActiveModel {
+            at_scene_at: ActiveValue::Unchanged(None),
+            ..Default::default()
+        };
+        apply_milestone(&mut active_model, Milestone::AtScene, first);
+        apply_milestone(&mut active_model, Milestone::AtScene, later);
+        assert_eq!(active_model.at_scene_at, ActiveValue::Set(Some(first)));
+    }
+}
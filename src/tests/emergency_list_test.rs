@@ -0,0 +1,86 @@
+#[cfg(test)]
+/// Tests for the query model of `GET /emergency`.
+mod emergency_list_tests {
+    use crate::components::emergency::EmergencyService;
+    use crate::entity::emergency::{self, EmergencyListQuery, EmergencyListSort};
+    use crate::entity::sea_orm_active_enums::{EmergencySeverityEnum, EmergencyStatusEnum};
+    use actix_web::web::Query;
+    use sea_orm::{DbBackend, EntityTrait, QueryFilter, QueryTrait};
+
+    fn parse(query: &str) -> EmergencyListQuery {
+        Query::<EmergencyListQuery>::from_query(query)
+            .unwrap()
+            .into_inner()
+    }
+
+    /// Sets are read from comma-separated values and the rest keeps its defaults.
+    #[test]
+    fn test_parse_sets_and_defaults() {
+        let query = parse("status=Pending,%20Escalated&severity=HIGH,CRITICAL&sort=severity");
+        assert_eq!(
+            query.status,
+            Some(vec![
+                EmergencyStatusEnum::Pending,
+                EmergencyStatusEnum::Escalated
+            ])
+        );
+        assert_eq!(
+            query.severity,
+            Some(vec![
+                EmergencySeverityEnum::High,
+                EmergencySeverityEnum::Critical
+            ])
+        );
+        assert_eq!(query.sort, EmergencyListSort::Severity);
+        assert_eq!((query.page, query.per_page), (1, 10));
+        assert_eq!(query.assigned, None);
+    }
+
+    /// Unknown parameters and values are refused instead of ignored.
+    #[test]
+    fn test_parse_rejects_unknown() {
+        let unknown_field = Query::<EmergencyListQuery>::from_query("stauts=Pending");
+        assert!(unknown_field.is_err());
+        let unknown_value = Query::<EmergencyListQuery>::from_query("severity=HIGH,URGENT");
+        assert!(unknown_value.is_err());
+        let unknown_sort = Query::<EmergencyListQuery>::from_query("sort=distance");
+        assert!(unknown_sort.is_err());
+    }
+
+    /// A partial or inverted box, an empty time range and an unsupported
+    /// legacy filter are bad requests.
+    #[test]
+    fn test_condition_rejects_contradictions() {
+        for query in [
+            "min_lat=44.4&min_lon=26.0&max_lat=44.5",
+            "min_lat=44.5&min_lon=26.0&max_lat=44.4&max_lon=26.2",
+            "created_from=2026-10-18T10:00:00&created_to=2026-10-18T09:00:00",
+            "filter=name=foo",
+        ] {
+            assert!(
+                EmergencyService::list_condition(&parse(query)).is_err(),
+                "{query}"
+            );
+        }
+    }
+
+    /// Every supplied filter ends up in the SQL.
+    #[test]
+    fn test_condition_combines_filters() {
+        let query = parse(
+            "status=Pending&assigned=false&incident_type=FRACTURE\
+             &created_from=2026-10-01T00:00:00\
+             &min_lat=44.4&min_lon=26.0&max_lat=44.5&max_lon=26.2",
+        );
+        let condition = EmergencyService::list_condition(&query).unwrap();
+        let sql = emergency::Entity::find()
+            .filter(condition)
+            .build(DbBackend::Postgres)
+            .to_string();
+        assert!(sql.contains(r#""status" IN"#), "{sql}");
+        assert!(sql.contains(r#""ambulance_id" IS NULL"#), "{sql}");
+        assert!(sql.contains(r#""incident_type" IN"#), "{sql}");
+        assert!(sql.contains(r#""created_at" >="#), "{sql}");
+        assert!(sql.contains(r#""emergency_latitude" BETWEEN"#), "{sql}");
+    }
+}
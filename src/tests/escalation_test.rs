@@ -0,0 +1,103 @@
+#[cfg(test)]
+/// Tests for the escalation rules applied to unattended emergencies.
+mod escalation_tests {
+    use crate::components::emergency::history::{CHANGE_ENTRY_TYPE, ESCALATION_ACTOR};
+    use crate::components::emergency::schedule::escalation::{
+        EscalationPolicy, last_escalated_at, raised_severity,
+    };
+    use crate::entity::emergency;
+    use crate::entity::sea_orm_active_enums::{
+        EmergencyIncidentEnum, EmergencySeverityEnum, EmergencyStatusEnum,
+        EmergencyVerificationEnum,
+    };
+    use chrono::{Duration, NaiveDateTime};
+    use sea_orm::prelude::{Decimal, Uuid};
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn pending(severity: EmergencySeverityEnum, created_at: NaiveDateTime) -> emergency::Model {
+        emergency::Model {
+            created_at,
+            updated_at: created_at,
+            id: Uuid::nil(),
+            hospital_id: None,
+            ambulance_id: None,
+            emergency_ic: "1".to_string(),
+            reported_by: None,
+            notes: None,
+            resolved_at: None,
+            modification_attempts: None,
+            emergency_latitude: Decimal::ZERO,
+            emergency_longitude: Decimal::ZERO,
+            status: EmergencyStatusEnum::Pending,
+            severity,
+            incident_type: EmergencyIncidentEnum::Other,
+            description: None,
+            geohash: None,
+            eta_seconds: None,
+            parent_emergency_id: None,
+            dispatched_at: None,
+            en_route_at: None,
+            at_scene_at: None,
+            transporting_at: None,
+            at_hospital_at: None,
+            verification_status: EmergencyVerificationEnum::Verified,
+            callback_phone: None,
+        }
+    }
+
+    /// Severity climbs one step at a time and stops at `Extreme`.
+    #[test]
+    fn test_raised_severity_ladder() {
+        let mut severity = EmergencySeverityEnum::Low;
+        let mut steps = 0;
+        while let Some(next) = raised_severity(&severity) {
+            severity = next;
+            steps += 1;
+        }
+        assert_eq!(severity, EmergencySeverityEnum::Extreme);
+        assert_eq!(steps, 5);
+        assert_eq!(raised_severity(&EmergencySeverityEnum::Deceased), None);
+    }
+
+    /// The wait is measured from the last escalation, not from the report.
+    #[test]
+    fn test_overdue_resets_after_escalation() {
+        let policy = EscalationPolicy::default();
+        let created = at("2026-10-18 10:00:00");
+        let mut case = pending(EmergencySeverityEnum::High, created);
+
+        assert_eq!(
+            policy.overdue_by(&case, created + Duration::minutes(9)),
+            None
+        );
+        assert_eq!(
+            policy.overdue_by(&case, created + Duration::minutes(12)),
+            Some((12, 10))
+        );
+
+        case.modification_attempts = Some(serde_json::json!([{
+            "type": CHANGE_ENTRY_TYPE,
+            "at": "2026-10-18T10:12:00",
+            "actor": ESCALATION_ACTOR,
+            "source": "ESCALATION",
+            "field": "SEVERITY",
+            "old": "HIGH",
+            "new": "SEVERE",
+            "reason": "waited 12 min, threshold 10 min"
+        }]));
+        case.severity = EmergencySeverityEnum::Severe;
+        assert_eq!(last_escalated_at(&case), Some(at("2026-10-18 10:12:00")));
+        assert_eq!(
+            policy.overdue_by(&case, created + Duration::minutes(15)),
+            None
+        );
+        assert!(
+            policy
+                .overdue_by(&case, created + Duration::minutes(20))
+                .is_some()
+        );
+    }
+}
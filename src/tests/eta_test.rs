@@ -0,0 +1,147 @@
+#[cfg(test)]
+/// Tests for road-network ETA estimation.
+mod eta_tests {
+    use crate::entity::sea_orm_active_enums::AmbulanceTypeEnum;
+    use crate::utils::eta::RoadGraph;
+    use crate::utils::eta::estimate_matrix_on;
+    use crate::utils::eta::graph::Way;
+    use crate::utils::eta::osm::{parse_maxspeed, parse_osm};
+    use crate::utils::eta::profile::{RoadClass, SpeedProfile};
+    use crate::utils::eta::{TravelSnapshot, direct_estimate};
+    use uuid::Uuid;
+
+    fn road(nodes: &[(f64, f64)], forward: bool, backward: bool) -> Way {
+        Way {
+            nodes: nodes.to_vec(),
+            class: RoadClass::Secondary,
+            maxspeed_kmh: None,
+            forward,
+            backward,
+        }
+    }
+
+    /// Two banks of a river joined by a single bridge 11 km to the north.
+    fn river_graph() -> RoadGraph {
+        RoadGraph::from_ways(&[
+            road(
+                &[(44.40, 26.100), (44.43, 26.100), (44.50, 26.100)],
+                true,
+                true,
+            ),
+            road(&[(44.40, 26.115), (44.50, 26.115)], true, true),
+            road(&[(44.50, 26.100), (44.50, 26.115)], true, true),
+        ])
+    }
+
+    /// The unit across the river is closer as the crow flies but further by road.
+    #[test]
+    fn test_river_crossing_reorders_candidates() {
+        let graph = river_graph();
+        let emergency = (44.40, 26.100);
+        let vehicles = vec![
+            ((44.40, 26.115), AmbulanceTypeEnum::BasicLifeSupport),
+            ((44.43, 26.100), AmbulanceTypeEnum::BasicLifeSupport),
+        ];
+
+        let straight = estimate_matrix_on(None, &[emergency], &vehicles);
+        assert!(straight[0][0].eta_seconds < straight[0][1].eta_seconds);
+
+        let routed = estimate_matrix_on(Some(&graph), &[emergency], &vehicles);
+        assert!(routed[0].iter().all(|travel| travel.routed));
+        assert!(routed[0][1].eta_seconds < routed[0][0].eta_seconds);
+        assert!(routed[0][0].distance_km > 20.0);
+    }
+
+    /// A one-way street cannot be driven backwards, so that pair falls back
+    /// to the straight-line estimate.
+    #[test]
+    fn test_oneway_is_respected() {
+        let graph = RoadGraph::from_ways(&[road(&[(44.40, 26.10), (44.42, 26.10)], true, false)]);
+        let downstream = ((44.42, 26.10), AmbulanceTypeEnum::BasicLifeSupport);
+        let upstream = ((44.40, 26.10), AmbulanceTypeEnum::BasicLifeSupport);
+
+        let to_end = estimate_matrix_on(Some(&graph), &[(44.42, 26.10)], &[upstream]);
+        assert!(to_end[0][0].routed);
+        let to_start = estimate_matrix_on(Some(&graph), &[(44.40, 26.10)], &[downstream]);
+        assert!(!to_start[0][0].routed);
+    }
+
+    /// Units under lights outrun heavy vehicles, and aircraft ignore roads.
+    #[test]
+    fn test_speed_profiles() {
+        let rapid = SpeedProfile::for_type(&AmbulanceTypeEnum::RapidResponseVehicle);
+        let bariatric = SpeedProfile::for_type(&AmbulanceTypeEnum::BariatricAmbulance);
+        assert!(
+            rapid.road_speed_kmh(RoadClass::Motorway, None)
+                > bariatric.road_speed_kmh(RoadClass::Motorway, None)
+        );
+        assert_eq!(bariatric.road_speed_kmh(RoadClass::Motorway, None), 90.0);
+        assert_eq!(
+            bariatric.road_speed_kmh(RoadClass::Residential, Some(50.0)),
+            50.0
+        );
+
+        let graph = river_graph();
+        let air = ((44.40, 26.115), AmbulanceTypeEnum::AirAmbulance);
+        let travel = estimate_matrix_on(Some(&graph), &[(44.40, 26.100)], &[air]);
+        assert!(!travel[0][0].routed);
+        assert!(travel[0][0].distance_km < 1.5);
+    }
+
+    /// Only drivable ways become edges, honouring `oneway`; `maxspeed` units are converted.
+    #[test]
+    fn test_parse_osm_extract() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<osm version="0.6">
+  <node id="1" lat="44.4000" lon="26.1000"/>
+  <node id="2" lat="44.4010" lon="26.1000"/>
+  <node id="3" lat="44.4020" lon="26.1000"/>
+  <node id="4" lat="44.4020" lon="26.1010"><tag k="amenity" v="hospital"/></node>
+  <way id="10">
+    <nd ref="1"/><nd ref="2"/><nd ref="3"/>
+    <tag k="highway" v="residential"/>
+  </way>
+  <way id="11">
+    <nd ref="3"/><nd ref="4"/>
+    <tag k="highway" v="primary"/>
+    <tag k="oneway" v="yes"/>
+    <tag k="maxspeed" v="30 mph"/>
+  </way>
+  <way id="12">
+    <nd ref="1"/><nd ref="4"/>
+    <tag k="highway" v="footway"/>
+  </way>
+</osm>"#;
+        let graph = parse_osm(xml.as_bytes()).unwrap();
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(graph.edge_count(), 5);
+
+        assert_eq!(parse_maxspeed("50"), Some(50.0));
+        assert!((parse_maxspeed("30 mph").unwrap() - 48.28).abs() < 0.01);
+        assert_eq!(parse_maxspeed("RO:urban"), None);
+    }
+
+    /// Routed pairs are reused while both ends stay put; a unit that moved
+    /// and an emergency reported since fall back to the straight line.
+    #[test]
+    fn test_travel_snapshot_reuses_unmoved_pairs() {
+        let graph = river_graph();
+        let vehicle = AmbulanceTypeEnum::BasicLifeSupport;
+        let emergency = (Uuid::new_v4(), (44.40, 26.100));
+        let unit = (Uuid::new_v4(), (44.40, 26.115), vehicle.clone());
+        let matrix = estimate_matrix_on(Some(&graph), &[emergency.1], &[(unit.1, vehicle.clone())]);
+        assert!(matrix[0][0].routed);
+        let snapshot = TravelSnapshot::new(&[emergency], std::slice::from_ref(&unit), &matrix);
+
+        let moved = (unit.0, (44.41, 26.115), vehicle.clone());
+        let reported = (Uuid::new_v4(), (44.45, 26.100));
+        let travel = snapshot.matrix(&[emergency, reported], &[unit.clone(), moved.clone()]);
+        let profile = SpeedProfile::for_type(&vehicle);
+        assert_eq!(travel[0][0], matrix[0][0]);
+        assert_eq!(
+            travel[0][1],
+            direct_estimate(moved.1, emergency.1, &profile)
+        );
+        assert_eq!(travel[1][0], direct_estimate(unit.1, reported.1, &profile));
+    }
+}
@@ -0,0 +1,113 @@
+#[cfg(test)]
+/// Tests for how geofence crossings turn into ambulance status changes.
+mod geofence_tests {
+    use crate::components::ambulance::geofence::{
+        Fence, GeofenceMode, crossing, first_change, status_after_crossing,
+    };
+    use crate::entity::ambulance_location::TelemetryPoint;
+    use crate::entity::sea_orm_active_enums::{AmbulanceStatusEnum, GeofenceKindEnum};
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+
+    /// About 111 m of latitude.
+    const STEP: f64 = 0.001;
+
+    fn fence(kind: GeofenceKindEnum) -> Fence {
+        Fence {
+            kind,
+            emergency_id: None,
+            hospital_id: None,
+            latitude: 44.4268,
+            longitude: 26.1025,
+            radius_m: 150.0,
+        }
+    }
+
+    fn at(steps: f64) -> (f64, f64) {
+        (44.4268 + steps * STEP, 26.1025)
+    }
+
+    fn fix(steps: f64, accuracy_m: Option<f64>) -> TelemetryPoint {
+        let (latitude, longitude) = at(steps);
+        TelemetryPoint {
+            latitude: Decimal::from_f64_retain(latitude).unwrap(),
+            longitude: Decimal::from_f64_retain(longitude).unwrap(),
+            speed_kmh: None,
+            heading: None,
+            accuracy_m,
+            recorded_at: NaiveDate::from_ymd_opt(2026, 10, 18)
+                .unwrap()
+                .and_hms_opt(10, 0, 0)
+                .unwrap(),
+        }
+    }
+
+    /// A unit enters inside the radius but only leaves past the wider exit
+    /// radius, so it does not flap on the edge.
+    #[test]
+    fn test_crossing_with_hysteresis() {
+        let scene = fence(GeofenceKindEnum::Scene);
+        assert_eq!(crossing(&scene, at(5.0), at(1.0)), Some(true));
+        assert_eq!(crossing(&scene, at(1.0), at(0.5)), None);
+        // Just outside the radius but inside the exit radius
+        assert_eq!(crossing(&scene, at(1.0), at(1.8)), None);
+        assert_eq!(crossing(&scene, at(1.8), at(1.0)), Some(true));
+        assert_eq!(crossing(&scene, at(1.0), at(3.0)), Some(false));
+    }
+
+    /// Only the crossings that match where the unit is in its mission change it.
+    #[test]
+    fn test_status_after_crossing() {
+        use AmbulanceStatusEnum::*;
+        let (scene, hospital) = (GeofenceKindEnum::Scene, GeofenceKindEnum::Hospital);
+        assert_eq!(
+            status_after_crossing(&EnRouteToScene, &scene, true),
+            Some(AtScene)
+        );
+        assert_eq!(
+            status_after_crossing(&TransportingPatient, &hospital, true),
+            Some(AtHospital)
+        );
+        assert_eq!(
+            status_after_crossing(&AtHospital, &hospital, false),
+            Some(ReturningToBase)
+        );
+        assert_eq!(status_after_crossing(&AtScene, &scene, false), None);
+        assert_eq!(status_after_crossing(&Available, &hospital, true), None);
+        assert_eq!(status_after_crossing(&Dispatched, &hospital, true), None);
+    }
+
+    /// The change names the fence and the status; a fix too imprecise for
+    /// the fence is ignored.
+    #[test]
+    fn test_first_change() {
+        let fences = [fence(GeofenceKindEnum::Scene)];
+        let change = first_change(
+            &AmbulanceStatusEnum::Dispatched,
+            &fences,
+            at(5.0),
+            &fix(0.5, Some(10.0)),
+        )
+        .unwrap();
+        assert!(change.entered);
+        assert_eq!(change.to_status, AmbulanceStatusEnum::AtScene);
+        assert!(change.distance_m < 150.0, "{}", change.distance_m);
+        assert_eq!(
+            first_change(
+                &AmbulanceStatusEnum::Dispatched,
+                &fences,
+                at(5.0),
+                &fix(0.5, Some(500.0))
+            ),
+            None
+        );
+    }
+
+    /// The mode is read case-insensitively and unknown values are refused.
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!("Propose".parse(), Ok(GeofenceMode::Propose));
+        assert_eq!(" off ".parse(), Ok(GeofenceMode::Off));
+        assert!("sometimes".parse::<GeofenceMode>().is_err());
+    }
+}
@@ -0,0 +1,107 @@
+#[cfg(test)]
+/// Tests for the change history kept in `emergency.modification_attempts`.
+mod history_tests {
+    use crate::components::emergency::history::{
+        ChangeField, ChangeLog, ChangeSource, history_entries,
+    };
+    use crate::entity::emergency;
+    use crate::entity::sea_orm_active_enums::{
+        EmergencyIncidentEnum, EmergencySeverityEnum, EmergencyStatusEnum,
+        EmergencyVerificationEnum,
+    };
+    use chrono::NaiveDateTime;
+    use sea_orm::prelude::{Decimal, Uuid};
+
+    fn case(history: serde_json::Value) -> emergency::Model {
+        let created_at =
+            NaiveDateTime::parse_from_str("2026-10-18 10:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        emergency::Model {
+            created_at,
+            updated_at: created_at,
+            id: Uuid::nil(),
+            hospital_id: None,
+            ambulance_id: None,
+            emergency_ic: "1".to_string(),
+            reported_by: None,
+            notes: None,
+            resolved_at: None,
+            modification_attempts: Some(history),
+            emergency_latitude: Decimal::ZERO,
+            emergency_longitude: Decimal::ZERO,
+            status: EmergencyStatusEnum::Pending,
+            severity: EmergencySeverityEnum::High,
+            incident_type: EmergencyIncidentEnum::Other,
+            description: None,
+            geohash: None,
+            eta_seconds: None,
+            parent_emergency_id: None,
+            dispatched_at: None,
+            en_route_at: None,
+            at_scene_at: None,
+            transporting_at: None,
+            at_hospital_at: None,
+            verification_status: EmergencyVerificationEnum::Verified,
+            callback_phone: None,
+        }
+    }
+
+    /// Only fields whose value changed produce an entry, stamped with actor and source.
+    #[test]
+    fn test_record_skips_unchanged_values() {
+        let mut log = ChangeLog::new("dispatcher-7", ChangeSource::Api).with_reason("callback");
+        log.record(
+            ChangeField::Status,
+            &EmergencyStatusEnum::Pending,
+            &EmergencyStatusEnum::Pending,
+        );
+        log.record(
+            ChangeField::Notes,
+            &None,
+            &Some("second caller on scene".to_string()),
+        );
+
+        let entries = log.into_entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].field, ChangeField::Notes);
+        assert_eq!(entries[0].actor, "dispatcher-7");
+        assert_eq!(entries[0].source, ChangeSource::Api);
+        assert_eq!(entries[0].old, serde_json::Value::Null);
+        assert_eq!(entries[0].reason.as_deref(), Some("callback"));
+    }
+
+    /// Change entries read back oldest first; entries of any other type are
+    /// skipped.
+    #[test]
+    fn test_history_reads_change_entries() {
+        let model = case(serde_json::json!([
+            {
+                "type": "CHANGE",
+                "at": "2026-10-18T10:20:00",
+                "actor": "allocator",
+                "source": "ALLOCATOR",
+                "field": "STATUS",
+                "old": "Pending",
+                "new": "InProgress"
+            },
+            {
+                "type": "CHANGE",
+                "at": "2026-10-18T10:12:00",
+                "actor": "escalation",
+                "source": "ESCALATION",
+                "field": "SEVERITY",
+                "old": "HIGH",
+                "new": "SEVERE",
+                "reason": "waited 12 min, threshold 10 min"
+            },
+            { "type": "UNKNOWN" }
+        ]));
+
+        let entries = history_entries(&model);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].source, ChangeSource::Escalation);
+        assert_eq!(entries[0].field, ChangeField::Severity);
+        assert_eq!(entries[0].new, serde_json::json!("SEVERE"));
+        assert_eq!(entries[1].source, ChangeSource::Allocator);
+        assert_eq!(entries[1].actor, "allocator");
+    }
+}
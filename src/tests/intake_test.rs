@@ -0,0 +1,144 @@
+#[cfg(test)]
+/// Tests for the public intake: validation, rate limiting and auto-confirmation.
+mod intake_tests {
+    use crate::components::emergency::duplicates::parent_verifications;
+    use crate::components::emergency::intake::{
+        AutoConfirmPolicy, normalize_phone, validate_report,
+    };
+    use crate::entity::emergency::PublicReportPayload;
+    use crate::entity::sea_orm_active_enums::{EmergencyIncidentEnum, EmergencyVerificationEnum};
+    use crate::utils::rate_limit::RateLimiter;
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+    use std::time::{Duration, Instant};
+
+    fn payload(latitude: &str, longitude: &str, phone: &str) -> PublicReportPayload {
+        PublicReportPayload {
+            latitude: Decimal::from_str(latitude).unwrap(),
+            longitude: Decimal::from_str(longitude).unwrap(),
+            incident_type: EmergencyIncidentEnum::Fracture,
+            callback_phone: phone.to_string(),
+            description: Some("  ".to_string()),
+        }
+    }
+
+    /// Separators are dropped, the leading `+` kept, and anything else refused.
+    #[test]
+    fn test_normalize_phone() {
+        assert_eq!(
+            normalize_phone(" +40 (721) 555-010 "),
+            Some("+40721555010".to_string())
+        );
+        assert_eq!(
+            normalize_phone("0721.555.010"),
+            Some("0721555010".to_string())
+        );
+        assert_eq!(normalize_phone("112"), None);
+        assert_eq!(normalize_phone("0721 555 010 ext 4"), None);
+        assert_eq!(normalize_phone("1234567890123456"), None);
+    }
+
+    /// Coordinates out of range and bad phones are refused; a blank
+    /// description is dropped.
+    #[test]
+    fn test_validate_report() {
+        let (body, phone) = validate_report(payload("44.43", "26.10", "0721 555 010")).unwrap();
+        assert_eq!(phone, "0721555010");
+        assert_eq!(body.description, None);
+        assert_eq!(body.severity, None);
+
+        assert!(validate_report(payload("91", "26.10", "0721555010")).is_err());
+        assert!(validate_report(payload("44.43", "-180.5", "0721555010")).is_err());
+        assert!(validate_report(payload("44.43", "26.10", "call me")).is_err());
+    }
+
+    fn hit(limiter: &RateLimiter, key: &str, at: Instant) -> Result<(), Duration> {
+        limiter.check(key, at)?;
+        limiter.record(key, at);
+        Ok(())
+    }
+
+    /// A key is refused once its window is full and allowed again as old
+    /// hits leave it; other keys are counted apart.
+    #[test]
+    fn test_rate_limiter_sliding_window() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60), 100);
+        let start = Instant::now();
+        assert!(hit(&limiter, "10.0.0.1", start).is_ok());
+        assert!(hit(&limiter, "10.0.0.1", start + Duration::from_secs(30)).is_ok());
+        assert_eq!(
+            hit(&limiter, "10.0.0.1", start + Duration::from_secs(45)),
+            Err(Duration::from_secs(15))
+        );
+        assert!(hit(&limiter, "10.0.0.2", start + Duration::from_secs(45)).is_ok());
+        assert!(hit(&limiter, "10.0.0.1", start + Duration::from_secs(60)).is_ok());
+    }
+
+    /// Checking alone does not count, so a report refused by one limiter
+    /// leaves the other's allowance untouched.
+    #[test]
+    fn test_rate_limiter_check_does_not_count() {
+        let limiter = RateLimiter::new(1, Duration::from_secs(60), 100);
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.check("10.0.0.1", start).is_ok());
+        }
+        limiter.record("10.0.0.1", start);
+        assert_eq!(
+            limiter.check("10.0.0.1", start),
+            Err(Duration::from_secs(60))
+        );
+    }
+
+    /// A full table never refuses a new caller: the oldest key is dropped
+    /// and starts over, while the others keep being counted.
+    #[test]
+    fn test_rate_limiter_caps_keys() {
+        let limiter = RateLimiter::new(1, Duration::from_secs(60), 2);
+        let start = Instant::now();
+        assert!(hit(&limiter, "a", start).is_ok());
+        assert!(hit(&limiter, "b", start).is_ok());
+        assert!(hit(&limiter, "c", start + Duration::from_secs(10)).is_ok());
+        assert!(hit(&limiter, "a", start + Duration::from_secs(20)).is_ok());
+        assert_eq!(
+            hit(&limiter, "c", start + Duration::from_secs(30)),
+            Err(Duration::from_secs(40))
+        );
+    }
+
+    /// Enough distinct callers confirm at once; otherwise only the wait does,
+    /// and a zero setting turns its rule off.
+    #[test]
+    fn test_auto_confirm_policy() {
+        let policy = AutoConfirmPolicy {
+            after_minutes: 10,
+            distinct_callers: 3,
+        };
+        assert_eq!(policy.reason(1, 2), None);
+        assert!(policy.reason(1, 3).unwrap().contains("3 distinct callers"));
+        assert!(policy.reason(10, 1).unwrap().contains("limit 10 min"));
+
+        let off = AutoConfirmPolicy {
+            after_minutes: 0,
+            distinct_callers: 0,
+        };
+        assert_eq!(off.reason(600, 50), None);
+    }
+
+    /// Confirmed reports only join confirmed incidents; provisional ones may
+    /// also corroborate another provisional report.
+    #[test]
+    fn test_parent_verifications() {
+        assert_eq!(
+            parent_verifications(&EmergencyVerificationEnum::Verified),
+            vec![EmergencyVerificationEnum::Verified]
+        );
+        assert_eq!(
+            parent_verifications(&EmergencyVerificationEnum::Provisional),
+            vec![
+                EmergencyVerificationEnum::Verified,
+                EmergencyVerificationEnum::Provisional
+            ]
+        );
+    }
+}
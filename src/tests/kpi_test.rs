@@ -0,0 +1,70 @@
+#[cfg(test)]
+/// Tests for the response-time statistics behind `GET /emergency/kpi`.
+mod kpi_tests {
+    use crate::components::emergency::kpi::{
+        DurationStats, KpiGroupBy, KpiSample, build_groups, percentile,
+    };
+    use crate::entity::sea_orm_active_enums::{EmergencyIncidentEnum, EmergencySeverityEnum};
+    use chrono::{Duration, NaiveDateTime};
+
+    fn reported() -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2026-10-01 08:00:00", "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn sample(severity: EmergencySeverityEnum, dispatch_seconds: Option<i64>) -> KpiSample {
+        let created_at = reported();
+        KpiSample {
+            hospital_id: None,
+            incident_type: EmergencyIncidentEnum::CarAccident,
+            severity,
+            created_at,
+            dispatched_at: dispatch_seconds.map(|s| created_at + Duration::seconds(s)),
+            en_route_at: None,
+            at_scene_at: None,
+            transporting_at: None,
+            at_hospital_at: None,
+            resolved_at: None,
+        }
+    }
+
+    /// Percentiles interpolate between ranks like Postgres `percentile_cont`.
+    #[test]
+    fn test_percentile_interpolates() {
+        let values = [10.0, 20.0, 30.0, 40.0];
+        assert_eq!(percentile(&values, 0.0), 10.0);
+        assert_eq!(percentile(&values, 50.0), 25.0);
+        assert_eq!(percentile(&values, 100.0), 40.0);
+        assert!((percentile(&values, 90.0) - 37.0).abs() < 1e-9);
+
+        let stats = DurationStats::from_samples(vec![40.0, 10.0, 30.0, 20.0]).unwrap();
+        assert_eq!(stats.samples, 4);
+        assert_eq!(stats.p50_seconds, 25.0);
+        assert_eq!(stats.max_seconds, 40.0);
+        assert!(DurationStats::from_samples(Vec::new()).is_none());
+    }
+
+    /// Groups count every emergency, while each interval only counts the
+    /// emergencies that reached its milestone after being reported.
+    #[test]
+    fn test_groups_by_severity() {
+        let samples = vec![
+            sample(EmergencySeverityEnum::High, Some(60)),
+            sample(EmergencySeverityEnum::High, Some(180)),
+            sample(EmergencySeverityEnum::High, None),
+            sample(EmergencySeverityEnum::Low, Some(-30)),
+        ];
+        let groups = build_groups(&samples, KpiGroupBy::Severity);
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].key, "HIGH");
+        assert_eq!(groups[0].emergencies, 3);
+        let dispatch = groups[0].response_times.time_to_dispatch.as_ref().unwrap();
+        assert_eq!(dispatch.samples, 2);
+        assert_eq!(dispatch.p50_seconds, 120.0);
+        assert!(groups[0].response_times.time_to_scene.is_none());
+
+        assert_eq!(groups[1].key, "LOW");
+        assert!(groups[1].response_times.time_to_dispatch.is_none());
+        assert_eq!(build_groups(&samples, KpiGroupBy::None)[0].key, "all");
+    }
+}
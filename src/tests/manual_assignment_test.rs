@@ -0,0 +1,88 @@
+#[cfg(test)]
+/// Tests for how a dispatcher's manual assignment changes the units of a case.
+mod manual_assignment_tests {
+    use crate::components::ambulance::lifecycle::can_be_reassigned;
+    use crate::components::emergency::schedule::manual_assignment;
+    use crate::entity::sea_orm_active_enums::AmbulanceStatusEnum;
+    use chrono::NaiveDateTime;
+    use sea_orm::ActiveValue::Set;
+    use uuid::Uuid;
+
+    /// Without `force` a second unit joins the case and the lead stays.
+    #[test]
+    fn test_joins_case_with_a_lead() {
+        let lead = Uuid::new_v4();
+        let ambulance = Uuid::new_v4();
+        let plan = manual_assignment(Some(lead), ambulance, false);
+        assert_eq!(plan.released, None);
+        assert_eq!(plan.lead, Some(lead));
+    }
+
+    /// The first unit on a case leads it, with or without `force`.
+    #[test]
+    fn test_leads_case_without_units() {
+        let ambulance = Uuid::new_v4();
+        for force in [false, true] {
+            let plan = manual_assignment(None, ambulance, force);
+            assert_eq!(plan.released, None);
+            assert_eq!(plan.lead, Some(ambulance));
+        }
+    }
+
+    /// `force` releases the lead unit and takes its place.
+    #[test]
+    fn test_force_replaces_the_lead() {
+        let lead = Uuid::new_v4();
+        let ambulance = Uuid::new_v4();
+        let plan = manual_assignment(Some(lead), ambulance, true);
+        assert_eq!(plan.released, Some(lead));
+        assert_eq!(plan.lead, Some(ambulance));
+    }
+
+    /// The override row names the unit a forced assignment released, with
+    /// the dispatcher's reason.
+    #[test]
+    fn test_override_records_the_released_unit() {
+        let lead = Uuid::new_v4();
+        let ambulance = Uuid::new_v4();
+        let emergency = Uuid::new_v4();
+        let now =
+            NaiveDateTime::parse_from_str("2026-03-01 10:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+
+        let record = manual_assignment(Some(lead), ambulance, true).override_record(
+            emergency,
+            "closer unit",
+            "dispatcher-1",
+            now,
+        );
+        assert_eq!(record.emergency_id, Set(emergency));
+        assert_eq!(record.ambulance_id, Set(ambulance));
+        assert_eq!(record.previous_ambulance_id, Set(Some(lead)));
+        assert_eq!(record.reason, Set("closer unit".to_string()));
+        assert_eq!(record.forced, Set(true));
+        assert_eq!(record.dispatcher, Set("dispatcher-1".to_string()));
+        assert_eq!(record.created_at, Set(now));
+
+        let joined = manual_assignment(Some(lead), ambulance, false).override_record(
+            emergency,
+            "second unit",
+            "dispatcher-1",
+            now,
+        );
+        assert_eq!(joined.previous_ambulance_id, Set(None));
+        assert_eq!(joined.forced, Set(false));
+    }
+
+    /// A forced assignment only takes a unit still on its way: one already
+    /// at the scene or carrying patients stays, lead or not, since its own
+    /// status says where it is whatever the case's status.
+    #[test]
+    fn test_unit_at_scene_cannot_be_stolen() {
+        use AmbulanceStatusEnum::*;
+        assert!(can_be_reassigned(&Dispatched));
+        assert!(can_be_reassigned(&EnRouteToScene));
+        for status in [AtScene, TransportingPatient, EnRouteToHospital, AtHospital] {
+            assert!(!can_be_reassigned(&status), "{status:?}");
+        }
+    }
+}
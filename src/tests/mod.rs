@@ -1,7 +1,28 @@
+pub mod allocation_preview_test;
 pub mod ambulance;
+pub mod ambulance_lifecycle_test;
 pub mod ambulance_status_test;
+pub mod assignment_test;
+pub mod capability_matrix_test;
+pub mod compliance_test;
+pub mod coverage_test;
+pub mod crew_test;
 pub mod db_config;
 pub mod db_test;
+pub mod destination_test;
+pub mod dispatch_decision_test;
+pub mod duplicates_test;
+pub mod emergency_lifecycle_test;
+pub mod emergency_list_test;
+pub mod escalation_test;
+pub mod eta_test;
+pub mod geofence_test;
+pub mod history_test;
+pub mod intake_test;
+pub mod kpi_test;
+pub mod manual_assignment_test;
 pub mod patient_test;
+pub mod spatial_test;
+pub mod telemetry_test;
 pub mod utils;
 pub mod vehicle;
@@ -0,0 +1,47 @@
+#[cfg(test)]
+/// Tests for the geohash helpers behind radius and bounding-box queries.
+mod spatial_tests {
+    use crate::utils::helpers::calculate_distance;
+    use crate::utils::spatial::{BoundingBox, GEOHASH_PRECISION, geohash_of};
+
+    /// Encodes the reference point from the geohash specification.
+    #[test]
+    fn test_geohash_of_known_point() {
+        let hash = geohash_of(57.64911, 10.40744).unwrap();
+        assert_eq!(hash.len(), GEOHASH_PRECISION);
+        assert!(hash.starts_with("u4pruydqq"));
+        assert!(geohash_of(91.0, 0.0).is_none());
+    }
+
+    /// The box around a point reaches at least `radius_km` in every direction.
+    #[test]
+    fn test_box_around_covers_radius() {
+        let (lat, lon) = (44.4268, 26.1025);
+        let bbox = BoundingBox::around(lat, lon, 25.0);
+        assert!(calculate_distance(lat, lon, bbox.max_latitude, lon) >= 24.9);
+        assert!(calculate_distance(lat, lon, lat, bbox.min_longitude) >= 24.9);
+    }
+
+    /// Every point inside the box hashes under one of the covering prefixes.
+    #[test]
+    fn test_covering_prefixes_contain_points_in_box() {
+        let bbox = BoundingBox::around(44.4268, 26.1025, 10.0);
+        let prefixes = bbox.covering_prefixes();
+        assert!(!prefixes.is_empty() && prefixes.len() <= 4);
+        for step_lat in 0..=10 {
+            for step_lon in 0..=10 {
+                let lat = bbox.min_latitude
+                    + (bbox.max_latitude - bbox.min_latitude) * f64::from(step_lat) / 10.0;
+                let lon = bbox.min_longitude
+                    + (bbox.max_longitude - bbox.min_longitude) * f64::from(step_lon) / 10.0;
+                let hash = geohash_of(lat, lon).unwrap();
+                assert!(prefixes.iter().any(|p| hash.starts_with(p.as_str())));
+            }
+        }
+        assert!(
+            BoundingBox::around(0.0, 0.0, 20_000.0)
+                .covering_prefixes()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,85 @@
+#[cfg(test)]
+/// Tests for the validation of GPS points and the distance of a replayed track.
+mod telemetry_tests {
+    use crate::components::ambulance::telemetry::{point_error, track_distance_km};
+    use crate::entity::ambulance_location::{self, TelemetryPoint};
+    use chrono::{Duration, NaiveDate, NaiveDateTime};
+    use rust_decimal::Decimal;
+    use uuid::Uuid;
+
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, 18)
+            .unwrap()
+            .and_hms_opt(10, 0, 0)
+            .unwrap()
+    }
+
+    fn point(latitude: &str, longitude: &str) -> TelemetryPoint {
+        TelemetryPoint {
+            latitude: latitude.parse().unwrap(),
+            longitude: longitude.parse().unwrap(),
+            speed_kmh: Some(60.0),
+            heading: Some(90.0),
+            accuracy_m: Some(5.0),
+            recorded_at: now() - Duration::seconds(30),
+        }
+    }
+
+    fn location(latitude: &str, longitude: &str, seconds: i64) -> ambulance_location::Model {
+        ambulance_location::Model {
+            id: seconds,
+            ambulance_id: Uuid::nil(),
+            recorded_at: now() + Duration::seconds(seconds),
+            latitude: latitude.parse::<Decimal>().unwrap(),
+            longitude: longitude.parse::<Decimal>().unwrap(),
+            speed_kmh: None,
+            heading: None,
+            accuracy_m: None,
+            received_at: now(),
+        }
+    }
+
+    /// A plausible fix is accepted, including one slightly ahead of the server clock.
+    #[test]
+    fn test_valid_point() {
+        assert_eq!(point_error(&point("44.4268", "26.1025"), now(), None), None);
+        let mut ahead = point("44.4268", "26.1025");
+        ahead.recorded_at = now() + Duration::seconds(60);
+        assert_eq!(point_error(&ahead, now(), None), None);
+    }
+
+    /// Out-of-range coordinates and readings, future fixes and fixes past
+    /// the retention period are refused with a reason.
+    #[test]
+    fn test_invalid_points() {
+        let oldest = Some(now() - Duration::days(30));
+        let mut cases = vec![point("91", "26.1"), point("44.4", "-180.5")];
+        let mut speeding = point("44.4", "26.1");
+        speeding.speed_kmh = Some(-1.0);
+        let mut heading = point("44.4", "26.1");
+        heading.heading = Some(360.0);
+        let mut accuracy = point("44.4", "26.1");
+        accuracy.accuracy_m = Some(f64::NAN);
+        let mut future = point("44.4", "26.1");
+        future.recorded_at = now() + Duration::minutes(10);
+        let mut expired = point("44.4", "26.1");
+        expired.recorded_at = now() - Duration::days(31);
+        cases.extend([speeding, heading, accuracy, future, expired]);
+        for case in &cases {
+            assert!(point_error(case, now(), oldest).is_some(), "{case:?}");
+        }
+    }
+
+    /// The distance adds up every hop and is zero for a single point.
+    #[test]
+    fn test_track_distance() {
+        let track = [
+            location("44.4268", "26.1025", 0),
+            location("44.4358", "26.1025", 60),
+            location("44.4448", "26.1025", 120),
+        ];
+        let distance = track_distance_km(&track);
+        assert!((distance - 2.0).abs() < 0.05, "{distance}");
+        assert_eq!(track_distance_km(&track[..1]), 0.0);
+    }
+}
@@ -0,0 +1,234 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::utils::eta::profile::{RoadClass, SpeedProfile};
+use crate::utils::helpers::calculate_distance;
+
+/// Geohash length of the cells used to snap points to the graph, about 1.2 km × 0.6 km.
+const SNAP_CELL_PRECISION: usize = 6;
+
+/// A directed road segment between two graph nodes.
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    to: u32,
+    length_km: f64,
+    class: RoadClass,
+    maxspeed_kmh: Option<f64>,
+}
+
+/// One drivable way: its node positions in order and how it may be travelled.
+#[derive(Debug, Clone)]
+pub struct Way {
+    /// Latitude and longitude of each node.
+    pub nodes: Vec<(f64, f64)>,
+    pub class: RoadClass,
+    pub maxspeed_kmh: Option<f64>,
+    /// Open in the direction of `nodes`.
+    pub forward: bool,
+    /// Open against the direction of `nodes`.
+    pub backward: bool,
+}
+
+/// Directed road network with forward and reverse adjacency.
+pub struct RoadGraph {
+    positions: Vec<(f64, f64)>,
+    outgoing: Vec<Vec<Edge>>,
+    incoming: Vec<Vec<Edge>>,
+    cells: HashMap<String, Vec<u32>>,
+}
+
+/// Fastest path found by `RoadGraph::routes_to`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Route {
+    pub seconds: f64,
+    pub distance_km: f64,
+}
+
+#[derive(PartialEq)]
+struct QueueEntry {
+    seconds: f64,
+    node: u32,
+}
+
+impl Eq for QueueEntry {}
+
+impl Ord for QueueEntry {
+    // Reversed so `BinaryHeap` pops the smallest time first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.seconds.total_cmp(&self.seconds)
+    }
+}
+
+impl PartialOrd for QueueEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl RoadGraph {
+    /// Builds the graph; ways sharing a position share the node.
+    pub fn from_ways(ways: &[Way]) -> Self {
+        let mut graph = RoadGraph {
+            positions: Vec::new(),
+            outgoing: Vec::new(),
+            incoming: Vec::new(),
+            cells: HashMap::new(),
+        };
+        let mut index_of: HashMap<(u64, u64), u32> = HashMap::new();
+
+        for way in ways {
+            let indices: Vec<u32> = way
+                .nodes
+                .iter()
+                .map(|&position| graph.node(&mut index_of, position))
+                .collect();
+            for (pair, segment) in indices.windows(2).zip(way.nodes.windows(2)) {
+                let length_km =
+                    calculate_distance(segment[0].0, segment[0].1, segment[1].0, segment[1].1);
+                if way.forward {
+                    graph.add_edge(pair[0], pair[1], length_km, way);
+                }
+                if way.backward {
+                    graph.add_edge(pair[1], pair[0], length_km, way);
+                }
+            }
+        }
+        graph
+    }
+
+    fn node(&mut self, index_of: &mut HashMap<(u64, u64), u32>, position: (f64, f64)) -> u32 {
+        *index_of
+            .entry((position.0.to_bits(), position.1.to_bits()))
+            .or_insert_with(|| {
+                let index = self.positions.len() as u32;
+                self.positions.push(position);
+                self.outgoing.push(Vec::new());
+                self.incoming.push(Vec::new());
+                if let Ok(cell) = geohash::encode(
+                    geohash::Coord {
+                        x: position.1,
+                        y: position.0,
+                    },
+                    SNAP_CELL_PRECISION,
+                ) {
+                    self.cells.entry(cell).or_default().push(index);
+                }
+                index
+            })
+    }
+
+    fn add_edge(&mut self, from: u32, to: u32, length_km: f64, way: &Way) {
+        let edge = |to| Edge {
+            to,
+            length_km,
+            class: way.class,
+            maxspeed_kmh: way.maxspeed_kmh,
+        };
+        self.outgoing[from as usize].push(edge(to));
+        self.incoming[to as usize].push(edge(from));
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.outgoing.iter().map(Vec::len).sum()
+    }
+
+    /// Closest node to a point and its distance in km, searching the point's
+    /// snap cell and its eight neighbours. `None` when the point is off the map.
+    pub fn nearest_node(&self, latitude: f64, longitude: f64) -> Option<(u32, f64)> {
+        let cell = geohash::encode(
+            geohash::Coord {
+                x: longitude,
+                y: latitude,
+            },
+            SNAP_CELL_PRECISION,
+        )
+        .ok()?;
+        let mut cells = vec![cell.clone()];
+        if let Ok(around) = geohash::neighbors(&cell) {
+            cells.extend([
+                around.n, around.ne, around.e, around.se, around.s, around.sw, around.w, around.nw,
+            ]);
+        }
+        cells
+            .iter()
+            .filter_map(|cell| self.cells.get(cell))
+            .flatten()
+            .map(|&node| {
+                let (lat, lon) = self.positions[node as usize];
+                (node, calculate_distance(latitude, longitude, lat, lon))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Fastest routes from each of `sources` to `target` for a vehicle profile.
+    ///
+    /// Runs one Dijkstra search backwards from the target, stopping once every
+    /// source is settled or routes get longer than `cutoff_seconds`; sources
+    /// not reached by then get `None`.
+    pub fn routes_to(
+        &self,
+        target: u32,
+        sources: &[u32],
+        profile: &SpeedProfile,
+        cutoff_seconds: f64,
+    ) -> Vec<Option<Route>> {
+        let mut best: HashMap<u32, Route> = HashMap::new();
+        let mut settled: HashMap<u32, Route> = HashMap::new();
+        let mut remaining = sources.len();
+        let mut queue = BinaryHeap::new();
+
+        best.insert(
+            target,
+            Route {
+                seconds: 0.0,
+                distance_km: 0.0,
+            },
+        );
+        queue.push(QueueEntry {
+            seconds: 0.0,
+            node: target,
+        });
+
+        while let Some(QueueEntry { seconds, node }) = queue.pop() {
+            if seconds > cutoff_seconds || remaining == 0 {
+                break;
+            }
+            if settled.contains_key(&node) {
+                continue;
+            }
+            let route = best[&node];
+            settled.insert(node, route);
+            remaining -= sources.iter().filter(|&&s| s == node).count();
+
+            for edge in &self.incoming[node as usize] {
+                if settled.contains_key(&edge.to) {
+                    continue;
+                }
+                let speed = profile.road_speed_kmh(edge.class, edge.maxspeed_kmh);
+                let candidate = Route {
+                    seconds: seconds + edge.length_km / speed * 3600.0,
+                    distance_km: route.distance_km + edge.length_km,
+                };
+                let improves = best
+                    .get(&edge.to)
+                    .is_none_or(|known| candidate.seconds < known.seconds);
+                if improves {
+                    best.insert(edge.to, candidate);
+                    queue.push(QueueEntry {
+                        seconds: candidate.seconds,
+                        node: edge.to,
+                    });
+                }
+            }
+        }
+
+        sources
+            .iter()
+            .map(|source| settled.get(source).copied())
+            .collect()
+    }
+}
@@ -0,0 +1,244 @@
+//! Drive-time estimates over a road graph loaded from a local OpenStreetMap extract.
+//!
+//! Without a graph, or for points it cannot route, estimates fall back to the
+//! straight-line distance stretched by a detour factor.
+
+pub mod graph;
+pub mod osm;
+pub mod profile;
+
+use log::{error, warn};
+use once_cell::sync::OnceCell;
+use std::collections::HashMap;
+use std::path::Path;
+use uuid::Uuid;
+
+pub use graph::RoadGraph;
+pub use osm::load_osm_file;
+pub use profile::SpeedProfile;
+
+use crate::entity::sea_orm_active_enums::AmbulanceTypeEnum;
+use crate::utils::helpers::calculate_distance;
+
+/// Environment variable with the path of the OpenStreetMap XML extract to route on.
+pub const ROAD_GRAPH_PATH_ENV: &str = "ROAD_GRAPH_PATH";
+
+/// Ratio of road to straight-line distance assumed when no route is known.
+const DETOUR_FACTOR: f64 = 1.4;
+/// Average road speed assumed when no route is known, before the vehicle factor, in km/h.
+const UNROUTED_SPEED_KMH: f64 = 45.0;
+/// Speed between a point and its nearest graph node, in km/h.
+const ACCESS_SPEED_KMH: f64 = 20.0;
+/// Routes longer than this are not searched and fall back to the estimate.
+const ROUTE_CUTOFF_SECONDS: f64 = 3.0 * 3600.0;
+
+static ROAD_GRAPH: OnceCell<RoadGraph> = OnceCell::new();
+
+/// Loads the extract named by `ROAD_GRAPH_PATH`, once, at startup.
+///
+/// A missing variable or unreadable file is logged and leaves straight-line
+/// estimates in place, so the service still starts.
+pub fn init_road_graph() {
+    let Ok(path) = std::env::var(ROAD_GRAPH_PATH_ENV) else {
+        warn!("{ROAD_GRAPH_PATH_ENV} not set, ETAs use straight-line estimates");
+        return;
+    };
+    match load_osm_file(Path::new(&path)) {
+        Ok(graph) => {
+            println!(
+                "Road graph loaded from {path}: {} nodes, {} edges",
+                graph.node_count(),
+                graph.edge_count()
+            );
+            let _ = ROAD_GRAPH.set(graph);
+        }
+        Err(e) => error!("Failed to load road graph, ETAs use straight-line estimates: {e}"),
+    }
+}
+
+/// The road graph loaded at startup, if any.
+pub fn road_graph() -> Option<&'static RoadGraph> {
+    ROAD_GRAPH.get()
+}
+
+/// Travel from a vehicle to a point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Travel {
+    pub distance_km: f64,
+    pub eta_seconds: f64,
+    /// Computed on the road graph rather than estimated from the straight line.
+    pub routed: bool,
+}
+
+impl Travel {
+    pub fn eta_minutes(&self) -> f64 {
+        self.eta_seconds / 60.0
+    }
+
+    /// Whole seconds, as stored in the database.
+    pub fn eta_seconds_rounded(&self) -> i32 {
+        self.eta_seconds.round().min(i32::MAX as f64) as i32
+    }
+}
+
+/// Straight-line estimate: direct flight for air and water units, otherwise
+/// the distance stretched by `DETOUR_FACTOR` at an average road speed.
+pub fn direct_estimate(from: (f64, f64), to: (f64, f64), profile: &SpeedProfile) -> Travel {
+    let straight_km = calculate_distance(from.0, from.1, to.0, to.1);
+    let (distance_km, speed_kmh) = match profile.direct_kmh {
+        Some(speed) => (straight_km, speed),
+        None => (
+            straight_km * DETOUR_FACTOR,
+            (UNROUTED_SPEED_KMH * profile.road_factor).min(profile.max_kmh),
+        ),
+    };
+    Travel {
+        distance_km,
+        eta_seconds: distance_km / speed_kmh * 3600.0,
+        routed: false,
+    }
+}
+
+/// Travel from a vehicle of the given type at `from` to `to`.
+pub fn estimate(from: (f64, f64), vehicle: &AmbulanceTypeEnum, to: (f64, f64)) -> Travel {
+    estimate_matrix(&[to], &[(from, vehicle.clone())])[0][0]
+}
+
+/// Travel from every vehicle to every target, indexed `[target][vehicle]`.
+///
+/// Uses one backward search per target and speed profile, so the cost grows
+/// with the number of targets, not with the number of pairs.
+pub fn estimate_matrix(
+    targets: &[(f64, f64)],
+    vehicles: &[((f64, f64), AmbulanceTypeEnum)],
+) -> Vec<Vec<Travel>> {
+    estimate_matrix_on(road_graph(), targets, vehicles)
+}
+
+/// `estimate_matrix` on an explicit graph, `None` for straight-line estimates only.
+pub fn estimate_matrix_on(
+    graph: Option<&RoadGraph>,
+    targets: &[(f64, f64)],
+    vehicles: &[((f64, f64), AmbulanceTypeEnum)],
+) -> Vec<Vec<Travel>> {
+    let profiles: Vec<SpeedProfile> = vehicles
+        .iter()
+        .map(|(_, vehicle)| SpeedProfile::for_type(vehicle))
+        .collect();
+    let mut matrix: Vec<Vec<Travel>> = targets
+        .iter()
+        .map(|&target| {
+            vehicles
+                .iter()
+                .zip(&profiles)
+                .map(|((from, _), profile)| direct_estimate(*from, target, profile))
+                .collect()
+        })
+        .collect();
+    let Some(graph) = graph else {
+        return matrix;
+    };
+
+    let snapped: Vec<Option<(u32, f64)>> = vehicles
+        .iter()
+        .map(|((lat, lon), _)| graph.nearest_node(*lat, *lon))
+        .collect();
+    // Vehicles sharing a type share a profile and therefore a search
+    let mut by_type: HashMap<&AmbulanceTypeEnum, Vec<usize>> = HashMap::new();
+    for (index, (_, vehicle)) in vehicles.iter().enumerate() {
+        if profiles[index].direct_kmh.is_none() && snapped[index].is_some() {
+            by_type.entry(vehicle).or_default().push(index);
+        }
+    }
+
+    for (target_index, &(lat, lon)) in targets.iter().enumerate() {
+        let Some((target_node, target_access_km)) = graph.nearest_node(lat, lon) else {
+            continue;
+        };
+        for indices in by_type.values() {
+            let profile = profiles[indices[0]];
+            let sources: Vec<u32> = indices
+                .iter()
+                .filter_map(|&i| snapped[i].map(|(node, _)| node))
+                .collect();
+            let routes = graph.routes_to(target_node, &sources, &profile, ROUTE_CUTOFF_SECONDS);
+            for (&vehicle_index, route) in indices.iter().zip(routes) {
+                let (Some(route), Some((_, source_access_km))) = (route, snapped[vehicle_index])
+                else {
+                    continue;
+                };
+                let access_km = source_access_km + target_access_km;
+                matrix[target_index][vehicle_index] = Travel {
+                    distance_km: route.distance_km + access_km,
+                    eta_seconds: route.seconds + access_km / ACCESS_SPEED_KMH * 3600.0,
+                    routed: true,
+                };
+            }
+        }
+    }
+    matrix
+}
+
+/// Travel from one vehicle to one target, with the positions it was computed for.
+#[derive(Debug, Clone, Copy)]
+struct KnownTravel {
+    from: (f64, f64),
+    to: (f64, f64),
+    travel: Travel,
+}
+
+/// Drive times computed ahead of time, looked up by target and vehicle id.
+///
+/// Lets callers route outside a transaction and reuse the result on rows read
+/// again under lock; a pair that moved or was not known falls back to the
+/// straight-line estimate rather than searching the graph.
+#[derive(Debug)]
+pub struct TravelSnapshot {
+    known: HashMap<(Uuid, Uuid), KnownTravel>,
+}
+
+impl TravelSnapshot {
+    /// Keeps `matrix`, indexed `[target][vehicle]` as from `estimate_matrix`.
+    pub fn new(
+        targets: &[(Uuid, (f64, f64))],
+        vehicles: &[(Uuid, (f64, f64), AmbulanceTypeEnum)],
+        matrix: &[Vec<Travel>],
+    ) -> Self {
+        let mut known = HashMap::new();
+        for (&(target_id, to), row) in targets.iter().zip(matrix) {
+            for ((vehicle_id, from, _), travel) in vehicles.iter().zip(row) {
+                known.insert(
+                    (target_id, *vehicle_id),
+                    KnownTravel {
+                        from: *from,
+                        to,
+                        travel: *travel,
+                    },
+                );
+            }
+        }
+        TravelSnapshot { known }
+    }
+
+    /// Travel from every vehicle to every target, `[target][vehicle]`.
+    pub fn matrix(
+        &self,
+        targets: &[(Uuid, (f64, f64))],
+        vehicles: &[(Uuid, (f64, f64), AmbulanceTypeEnum)],
+    ) -> Vec<Vec<Travel>> {
+        targets
+            .iter()
+            .map(|&(target_id, to)| {
+                vehicles
+                    .iter()
+                    .map(|(vehicle_id, from, vehicle)| {
+                        match self.known.get(&(target_id, *vehicle_id)) {
+                            Some(known) if known.from == *from && known.to == to => known.travel,
+                            _ => direct_estimate(*from, to, &SpeedProfile::for_type(vehicle)),
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
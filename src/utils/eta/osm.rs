@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+
+use crate::utils::eta::graph::{RoadGraph, Way};
+use crate::utils::eta::profile::RoadClass;
+
+const KMH_PER_MPH: f64 = 1.609_344;
+
+/// Loads the drivable ways of an OpenStreetMap XML extract (`.osm`).
+pub fn load_osm_file(path: &Path) -> Result<RoadGraph, String> {
+    let file = File::open(path).map_err(|e| format!("Cannot open {}: {e}", path.display()))?;
+    parse_osm(BufReader::new(file))
+}
+
+/// A `<way>` as read from the extract, before its node references are resolved.
+struct RawWay {
+    refs: Vec<i64>,
+    tags: HashMap<String, String>,
+}
+
+/// Parses OpenStreetMap XML into a road graph.
+pub fn parse_osm<R: BufRead>(input: R) -> Result<RoadGraph, String> {
+    let mut reader = Reader::from_reader(input);
+    let mut buf = Vec::new();
+    let mut positions: HashMap<i64, (f64, f64)> = HashMap::new();
+    let mut raw_ways: Vec<RawWay> = Vec::new();
+    let mut current: Option<RawWay> = None;
+
+    loop {
+        let event = reader
+            .read_event_into(&mut buf)
+            .map_err(|e| format!("Invalid OSM XML at byte {}: {e}", reader.buffer_position()))?;
+        match event {
+            Event::Start(ref element) | Event::Empty(ref element) => {
+                let is_empty = matches!(event, Event::Empty(_));
+                match element.name().as_ref() {
+                    b"node" => {
+                        let id = attribute(element, b"id").and_then(|v| v.parse().ok());
+                        let lat = attribute(element, b"lat").and_then(|v| v.parse().ok());
+                        let lon = attribute(element, b"lon").and_then(|v| v.parse().ok());
+                        if let (Some(id), Some(lat), Some(lon)) = (id, lat, lon) {
+                            positions.insert(id, (lat, lon));
+                        }
+                    }
+                    b"way" if !is_empty => {
+                        current = Some(RawWay {
+                            refs: Vec::new(),
+                            tags: HashMap::new(),
+                        });
+                    }
+                    b"nd" => {
+                        if let Some(way) = current.as_mut()
+                            && let Some(id) =
+                                attribute(element, b"ref").and_then(|v| v.parse().ok())
+                        {
+                            way.refs.push(id);
+                        }
+                    }
+                    b"tag" => {
+                        if let Some(way) = current.as_mut()
+                            && let (Some(key), Some(value)) =
+                                (attribute(element, b"k"), attribute(element, b"v"))
+                        {
+                            way.tags.insert(key, value);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::End(ref element) if element.name().as_ref() == b"way" => {
+                if let Some(way) = current.take() {
+                    raw_ways.push(way);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    let ways: Vec<Way> = raw_ways
+        .into_iter()
+        .filter_map(|raw| drivable_way(raw, &positions))
+        .collect();
+    Ok(RoadGraph::from_ways(&ways))
+}
+
+fn attribute(element: &BytesStart, key: &[u8]) -> Option<String> {
+    element
+        .attributes()
+        .flatten()
+        .find(|a| a.key.as_ref() == key)
+        .and_then(|a| String::from_utf8(a.value.into_owned()).ok())
+}
+
+/// Turns a raw way into a graph way, `None` when vehicles cannot use it.
+fn drivable_way(raw: RawWay, positions: &HashMap<i64, (f64, f64)>) -> Option<Way> {
+    let class = RoadClass::from_highway(raw.tags.get("highway")?)?;
+    let nodes: Vec<(f64, f64)> = raw
+        .refs
+        .iter()
+        .filter_map(|id| positions.get(id).copied())
+        .collect();
+    if nodes.len() < 2 {
+        return None;
+    }
+
+    let roundabout = raw.tags.get("junction").map(String::as_str) == Some("roundabout");
+    let (forward, backward) = match raw.tags.get("oneway").map(String::as_str) {
+        Some("yes" | "true" | "1") => (true, false),
+        Some("-1" | "reverse") => (false, true),
+        Some("no" | "false" | "0") => (true, true),
+        _ if roundabout || class.implies_oneway() => (true, false),
+        _ => (true, true),
+    };
+
+    Some(Way {
+        nodes,
+        class,
+        maxspeed_kmh: raw.tags.get("maxspeed").and_then(|v| parse_maxspeed(v)),
+        forward,
+        backward,
+    })
+}
+
+/// Numeric `maxspeed` in km/h (`"50"`, `"30 mph"`); symbolic values such as
+/// `"RO:urban"` fall back to the road class default.
+pub fn parse_maxspeed(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let digits_end = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let speed: f64 = value[..digits_end].parse().ok()?;
+    if value[digits_end..].trim() == "mph" {
+        Some(speed * KMH_PER_MPH)
+    } else {
+        Some(speed)
+    }
+}
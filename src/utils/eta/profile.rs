@@ -0,0 +1,106 @@
+use crate::entity::sea_orm_active_enums::AmbulanceTypeEnum;
+
+/// Road classes of the OpenStreetMap `highway` tag that vehicles can drive on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RoadClass {
+    Motorway,
+    Trunk,
+    Primary,
+    Secondary,
+    Tertiary,
+    Unclassified,
+    Residential,
+    Service,
+    Track,
+}
+
+impl RoadClass {
+    /// Class of a `highway=*` value, `None` for ways closed to vehicles
+    /// (footways, cycleways, steps, construction, ...).
+    pub fn from_highway(value: &str) -> Option<Self> {
+        let class = match value.trim_end_matches("_link") {
+            "motorway" => RoadClass::Motorway,
+            "trunk" => RoadClass::Trunk,
+            "primary" => RoadClass::Primary,
+            "secondary" => RoadClass::Secondary,
+            "tertiary" => RoadClass::Tertiary,
+            "unclassified" | "road" => RoadClass::Unclassified,
+            "residential" | "living_street" => RoadClass::Residential,
+            "service" => RoadClass::Service,
+            "track" => RoadClass::Track,
+            _ => return None,
+        };
+        Some(class)
+    }
+
+    /// Typical free-flow speed when the way has no usable `maxspeed`, in km/h.
+    pub fn default_speed_kmh(&self) -> f64 {
+        match self {
+            RoadClass::Motorway => 110.0,
+            RoadClass::Trunk => 90.0,
+            RoadClass::Primary => 70.0,
+            RoadClass::Secondary => 60.0,
+            RoadClass::Tertiary => 50.0,
+            RoadClass::Unclassified => 40.0,
+            RoadClass::Residential => 30.0,
+            RoadClass::Service => 20.0,
+            RoadClass::Track => 15.0,
+        }
+    }
+
+    /// Motorways are one-way per carriageway even when the tag is missing.
+    pub fn implies_oneway(&self) -> bool {
+        matches!(self, RoadClass::Motorway)
+    }
+}
+
+/// How fast a vehicle type moves.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeedProfile {
+    /// Multiplier on the road speed; above 1 for units driving under lights and siren.
+    pub road_factor: f64,
+    /// Speed the vehicle never exceeds on any road, in km/h.
+    pub max_kmh: f64,
+    /// Vehicles that do not use the road network travel in a straight line at this speed.
+    pub direct_kmh: Option<f64>,
+}
+
+impl SpeedProfile {
+    pub fn for_type(ambulance_type: &AmbulanceTypeEnum) -> Self {
+        use AmbulanceTypeEnum::*;
+        let (road_factor, max_kmh, direct_kmh) = match ambulance_type {
+            RapidResponseVehicle | SupervisorVehicle => (1.3, 150.0, None),
+            BasicLifeSupport
+            | AdvancedLifeSupport
+            | PediatricAmbulance
+            | NeonatalAmbulance
+            | CriticalCareTransport
+            | RescueAmbulance
+            | EventMedicalServices => (1.2, 130.0, None),
+            MobileIntensiveCareUnit => (1.1, 120.0, None),
+            BariatricAmbulance | HazmatAmbulance | CommandVehicle | UtilityVehicle => {
+                (1.0, 90.0, None)
+            }
+            WheelchairVan
+            | AmbulatoryTransport
+            | PsychiatricTransport
+            | LongDistanceTransport
+            | TrainingAmbulance => (1.0, 110.0, None),
+            AirAmbulance => (1.0, 220.0, Some(220.0)),
+            WaterAmbulance => (1.0, 35.0, Some(35.0)),
+        };
+        SpeedProfile {
+            road_factor,
+            max_kmh,
+            direct_kmh,
+        }
+    }
+
+    /// Speed on a road of `class`, using the posted `maxspeed` when known, in km/h.
+    pub fn road_speed_kmh(&self, class: RoadClass, maxspeed_kmh: Option<f64>) -> f64 {
+        let base = maxspeed_kmh
+            .filter(|speed| *speed > 0.0)
+            .unwrap_or_else(|| class.default_speed_kmh());
+        (base * self.road_factor).min(self.max_kmh)
+    }
+}
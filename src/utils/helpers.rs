@@ -4,6 +4,7 @@ use chrono::{DateTime, NaiveDateTime, Utc};
 use chrono_tz::Europe;
 use nanoid::nanoid;
 use sea_orm::DbErr;
+use std::str::FromStr;
 
 pub fn calculate_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
     const EARTH_RADIUS: f64 = 6371.0;
@@ -101,6 +102,36 @@ pub fn check_if_is_duplicate_key_from_data_base<T>(
     }
 }
 
+/// Reads an environment variable and parses it, returning `default` when the
+/// variable is missing or cannot be parsed.
+pub fn env_or<T: FromStr>(var: &str, default: T) -> T {
+    std::env::var(var)
+        .ok()
+        .and_then(|raw| raw.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+/// Deserializes a comma-separated query value such as `status=Pending,Escalated`
+/// into a list, each item read as a JSON string so enums keep their serde names.
+pub fn comma_separated<'de, D, T>(deserializer: D) -> Result<Option<Vec<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::de::DeserializeOwned,
+{
+    let Some(raw) = <Option<String> as serde::Deserialize>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    raw.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            serde_json::from_value(serde_json::Value::String(item.to_string()))
+                .map_err(|e| serde::de::Error::custom(format!("`{item}`: {e}")))
+        })
+        .collect::<Result<Vec<T>, _>>()
+        .map(Some)
+}
+
 pub fn now_time() -> NaiveDateTime {
     chrono::Utc::now()
         .with_timezone(&Europe::Bucharest)
@@ -1,2 +1,5 @@
 // Renamed to avoid "module inception" warning
+pub mod eta;
 pub mod helpers;
+pub mod rate_limit;
+pub mod spatial;
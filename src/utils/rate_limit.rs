@@ -0,0 +1,94 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// In-memory sliding-window limiter: at most `limit` hits per key within any
+/// `window`, tracking at most `max_keys` keys so a flood of distinct callers
+/// cannot grow the table without bound. State is per process and is lost on
+/// restart.
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: usize,
+    window: Duration,
+    max_keys: usize,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    hits: HashMap<String, VecDeque<Instant>>,
+    /// Tracked keys, oldest first, so a full table drops its oldest key.
+    order: VecDeque<String>,
+    last_sweep: Option<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: usize, window: Duration, max_keys: usize) -> Self {
+        RateLimiter {
+            limit,
+            window,
+            max_keys,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Whether `key` may make another hit at `now`. Returns `Err` with the
+    /// time until its oldest hit leaves the window when it is over its limit.
+    /// Nothing is counted; see `record`.
+    pub fn check(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(times) = state.hits.get(key) else {
+            return Ok(());
+        };
+        let mut recent = times
+            .iter()
+            .filter(|at| now.duration_since(**at) < self.window);
+        match recent.clone().nth(self.limit.saturating_sub(1)) {
+            Some(_) => {
+                let oldest = recent.next().copied().unwrap_or(now);
+                Err(self.window.saturating_sub(now.duration_since(oldest)))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Counts a hit for `key` at `now`.
+    ///
+    /// Idle keys are swept at most once per window. A new key that finds the
+    /// table full takes the place of the oldest one, which starts over.
+    pub fn record(&self, key: &str, now: Instant) {
+        let mut guard = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let state = &mut *guard;
+        if state
+            .last_sweep
+            .is_none_or(|last| now.duration_since(last) >= self.window)
+        {
+            let window = self.window;
+            state.hits.retain(|_, times| {
+                times
+                    .back()
+                    .is_some_and(|last| now.duration_since(*last) < window)
+            });
+            let hits = &state.hits;
+            state.order.retain(|k| hits.contains_key(k));
+            state.last_sweep = Some(now);
+        }
+        if !state.hits.contains_key(key) {
+            while state.hits.len() >= self.max_keys.max(1) {
+                let Some(oldest) = state.order.pop_front() else {
+                    break;
+                };
+                state.hits.remove(&oldest);
+            }
+            state.order.push_back(key.to_string());
+        }
+        let times = state.hits.entry(key.to_string()).or_default();
+        while times
+            .front()
+            .is_some_and(|first| now.duration_since(*first) >= self.window)
+        {
+            times.pop_front();
+        }
+        times.push_back(now);
+    }
+}
@@ -0,0 +1,235 @@
+//! Geohash-backed radius and bounding-box queries over located entities.
+
+use geohash::Coord;
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use sea_orm::prelude::Decimal;
+use sea_orm::*;
+
+use crate::entity::{ambulance, emergency};
+use crate::utils::helpers::calculate_distance;
+
+/// Length of the geohash stored on each row, about 5 m × 5 m cells.
+pub const GEOHASH_PRECISION: usize = 9;
+
+/// Length of one degree of latitude, in km.
+pub const KM_PER_DEGREE_LATITUDE: f64 = 111.32;
+/// Largest coordinates `geohash::encode` maps into the expected edge cells.
+const MAX_LATITUDE: f64 = 89.999_999;
+const MAX_LONGITUDE: f64 = 179.999_999;
+
+/// Geohash of a point, `None` when the coordinates are out of range.
+pub fn geohash_of(latitude: f64, longitude: f64) -> Option<String> {
+    geohash::encode(
+        Coord {
+            x: longitude,
+            y: latitude,
+        },
+        GEOHASH_PRECISION,
+    )
+    .ok()
+}
+
+/// Geohash of a point stored as decimal columns.
+pub fn geohash_of_decimal(latitude: Decimal, longitude: Decimal) -> Option<String> {
+    geohash_of(latitude.to_f64()?, longitude.to_f64()?)
+}
+
+/// Area between two parallels and two meridians, in degrees; boxes crossing
+/// the antimeridian are not supported.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_latitude: f64,
+    pub min_longitude: f64,
+    pub max_latitude: f64,
+    pub max_longitude: f64,
+}
+
+impl BoundingBox {
+    /// Smallest box containing the circle of `radius_km` around a point.
+    pub fn around(latitude: f64, longitude: f64, radius_km: f64) -> Self {
+        let delta_latitude = radius_km / KM_PER_DEGREE_LATITUDE;
+        let min_latitude = (latitude - delta_latitude).max(-90.0);
+        let max_latitude = (latitude + delta_latitude).min(90.0);
+        // Meridians converge, so a degree of longitude shrinks towards the poles
+        let widest = min_latitude
+            .abs()
+            .max(max_latitude.abs())
+            .to_radians()
+            .cos();
+        let delta_longitude = radius_km / (KM_PER_DEGREE_LATITUDE * widest.max(f64::EPSILON));
+        let (min_longitude, max_longitude) = if delta_longitude >= 180.0 {
+            (-180.0, 180.0)
+        } else {
+            (
+                (longitude - delta_longitude).max(-180.0),
+                (longitude + delta_longitude).min(180.0),
+            )
+        };
+        BoundingBox {
+            min_latitude,
+            min_longitude,
+            max_latitude,
+            max_longitude,
+        }
+    }
+
+    /// Geohash prefixes whose cells together cover the box.
+    ///
+    /// Uses the longest prefix whose cells are at least as large as the box, so
+    /// the box overlaps at most 2×2 cells: the ones holding its corners. Empty
+    /// when even single-character cells are too small, i.e. no prefix filter.
+    pub fn covering_prefixes(&self) -> Vec<String> {
+        let height = self.max_latitude - self.min_latitude;
+        let width = self.max_longitude - self.min_longitude;
+        let Some(precision) = (1..=GEOHASH_PRECISION).rev().find(|&precision| {
+            let (cell_height, cell_width) = cell_size(precision);
+            cell_height >= height && cell_width >= width
+        }) else {
+            return Vec::new();
+        };
+
+        let mut prefixes = Vec::with_capacity(4);
+        for latitude in [self.min_latitude, self.max_latitude] {
+            for longitude in [self.min_longitude, self.max_longitude] {
+                let corner = Coord {
+                    x: longitude.min(MAX_LONGITUDE),
+                    y: latitude.min(MAX_LATITUDE),
+                };
+                if let Ok(prefix) = geohash::encode(corner, precision)
+                    && !prefixes.contains(&prefix)
+                {
+                    prefixes.push(prefix);
+                }
+            }
+        }
+        prefixes
+    }
+}
+
+/// Height and width in degrees of a geohash cell with `precision` characters.
+fn cell_size(precision: usize) -> (f64, f64) {
+    let bits = 5 * precision as i32;
+    let longitude_bits = (bits + 1) / 2;
+    let latitude_bits = bits / 2;
+    (
+        180.0 / 2f64.powi(latitude_bits),
+        360.0 / 2f64.powi(longitude_bits),
+    )
+}
+
+/// An entity with a position and a geohash maintained from it.
+pub trait Geolocated: EntityTrait {
+    fn geohash_column() -> Self::Column;
+    fn latitude_column() -> Self::Column;
+    fn longitude_column() -> Self::Column;
+    /// Latitude and longitude of a row.
+    fn coordinates(model: &Self::Model) -> (f64, f64);
+}
+
+impl Geolocated for ambulance::Entity {
+    fn geohash_column() -> Self::Column {
+        ambulance::Column::Geohash
+    }
+    fn latitude_column() -> Self::Column {
+        ambulance::Column::LocationLatitude
+    }
+    fn longitude_column() -> Self::Column {
+        ambulance::Column::LocationLongitude
+    }
+    fn coordinates(model: &Self::Model) -> (f64, f64) {
+        (
+            model.location_latitude.to_f64().unwrap_or(0.0),
+            model.location_longitude.to_f64().unwrap_or(0.0),
+        )
+    }
+}
+
+impl Geolocated for emergency::Entity {
+    fn geohash_column() -> Self::Column {
+        emergency::Column::Geohash
+    }
+    fn latitude_column() -> Self::Column {
+        emergency::Column::EmergencyLatitude
+    }
+    fn longitude_column() -> Self::Column {
+        emergency::Column::EmergencyLongitude
+    }
+    fn coordinates(model: &Self::Model) -> (f64, f64) {
+        (
+            model.emergency_latitude.to_f64().unwrap_or(0.0),
+            model.emergency_longitude.to_f64().unwrap_or(0.0),
+        )
+    }
+}
+
+/// Condition matching rows inside `bbox`.
+///
+/// The geohash prefixes narrow the scan through the index; the coordinate
+/// bounds then drop the parts of the cells outside the box.
+pub fn bounding_box_condition<E: Geolocated>(bbox: &BoundingBox) -> Condition {
+    let prefixes = bbox.covering_prefixes();
+    let mut condition = Condition::all()
+        .add(E::latitude_column().between(degrees(bbox.min_latitude), degrees(bbox.max_latitude)))
+        .add(
+            E::longitude_column().between(degrees(bbox.min_longitude), degrees(bbox.max_longitude)),
+        );
+    if !prefixes.is_empty() {
+        condition = condition.add(prefixes.into_iter().fold(Condition::any(), |any, prefix| {
+            any.add(E::geohash_column().starts_with(prefix))
+        }));
+    }
+    condition
+}
+
+/// Condition matching rows in any of the boxes.
+pub fn any_bounding_box_condition<E: Geolocated>(boxes: &[BoundingBox]) -> Condition {
+    boxes.iter().fold(Condition::any(), |any, bbox| {
+        any.add(bounding_box_condition::<E>(bbox))
+    })
+}
+
+/// Rows of `select` inside `bbox`.
+pub async fn find_within_bounding_box<E, C>(
+    conn: &C,
+    select: Select<E>,
+    bbox: &BoundingBox,
+) -> Result<Vec<E::Model>, DbErr>
+where
+    E: Geolocated,
+    C: ConnectionTrait,
+{
+    select
+        .filter(bounding_box_condition::<E>(bbox))
+        .all(conn)
+        .await
+}
+
+/// Rows of `select` within `radius_km` of a point with their distance, nearest first.
+pub async fn find_within_radius<E, C>(
+    conn: &C,
+    select: Select<E>,
+    latitude: f64,
+    longitude: f64,
+    radius_km: f64,
+) -> Result<Vec<(E::Model, f64)>, DbErr>
+where
+    E: Geolocated,
+    C: ConnectionTrait,
+{
+    let bbox = BoundingBox::around(latitude, longitude, radius_km);
+    let rows = find_within_bounding_box(conn, select, &bbox).await?;
+    let mut nearby: Vec<(E::Model, f64)> = rows
+        .into_iter()
+        .filter_map(|row| {
+            let (lat, lon) = E::coordinates(&row);
+            let distance = calculate_distance(latitude, longitude, lat, lon);
+            (distance <= radius_km).then_some((row, distance))
+        })
+        .collect();
+    nearby.sort_by(|a, b| a.1.total_cmp(&b.1));
+    Ok(nearby)
+}
+
+fn degrees(value: f64) -> Decimal {
+    Decimal::from_f64(value).unwrap_or_default()
+}